@@ -95,6 +95,10 @@ pub enum Error {
     /// returned error response.
     #[error("Invalid protocol used when communicating with a node: {0}")]
     InvalidProtocol(SocketAddr),
+    /// Connection to a node has reached its limit of in-flight requests, so a new request could not
+    /// be sent. The request has not reached the node and can be safely sent elsewhere.
+    #[error("Too many in-flight requests on connection to: {0}")]
+    ConnectionBusy(SocketAddr),
 }
 
 pub fn column_is_empty_err<T: Display>(column_name: T) -> Error {
@@ -159,6 +163,7 @@ impl Clone for Error {
             Error::UnexpectedAuthResponse(value) => Error::UnexpectedAuthResponse(*value),
             Error::UnexpectedStartupResponse(value) => Error::UnexpectedStartupResponse(*value),
             Error::InvalidProtocol(addr) => Error::InvalidProtocol(*addr),
+            Error::ConnectionBusy(addr) => Error::ConnectionBusy(*addr),
         }
     }
 }
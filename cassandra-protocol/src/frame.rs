@@ -2,10 +2,14 @@ use crate::compression::{Compression, CompressionError};
 use crate::frame::message_request::RequestBody;
 use crate::frame::message_response::ResponseBody;
 use crate::types::data_serialization_types::decode_timeuuid;
-use crate::types::{from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN};
+use crate::types::{
+    from_cursor_bytes_map, from_cursor_string_list, serialize_bytes_map, try_i16_from_bytes,
+    try_i32_from_bytes, UUID_LEN,
+};
 use bitflags::bitflags;
 use derivative::Derivative;
 use derive_more::{Constructor, Display};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use thiserror::Error;
@@ -81,8 +85,8 @@ pub struct ParsedEnvelope {
     pub envelope: Envelope,
 }
 
-#[derive(Derivative, Clone, PartialEq, Eq, Hash)]
-#[derivative(Debug)]
+#[derive(Derivative, Clone, PartialEq, Eq)]
+#[derivative(Debug, Hash)]
 pub struct Envelope {
     pub version: Version,
     pub direction: Direction,
@@ -93,6 +97,9 @@ pub struct Envelope {
     pub body: Vec<u8>,
     pub tracing_id: Option<Uuid>,
     pub warnings: Vec<String>,
+    /// Custom payload, present if [`Flags::CUSTOM_PAYLOAD`] is set. Supported since protocol V4.
+    #[derivative(Hash = "ignore")]
+    pub custom_payload: HashMap<String, Vec<u8>>,
 }
 
 impl Envelope {
@@ -117,9 +124,22 @@ impl Envelope {
             body,
             tracing_id,
             warnings,
+            custom_payload: HashMap::new(),
         }
     }
 
+    /// Sets the custom payload, along with [`Flags::CUSTOM_PAYLOAD`]. Empty payloads are
+    /// ignored, as are all payloads for protocol versions older than V4, which don't support them.
+    #[must_use]
+    pub fn with_custom_payload(mut self, custom_payload: HashMap<String, Vec<u8>>) -> Self {
+        if !custom_payload.is_empty() && self.version >= Version::V4 {
+            self.flags.insert(Flags::CUSTOM_PAYLOAD);
+            self.custom_payload = custom_payload;
+        }
+
+        self
+    }
+
     #[inline]
     pub fn request_body(&self) -> error::Result<RequestBody> {
         RequestBody::try_from(self.body.as_slice(), self.opcode, self.version)
@@ -140,6 +160,11 @@ impl Envelope {
         &self.warnings
     }
 
+    #[inline]
+    pub fn custom_payload(&self) -> &HashMap<String, Vec<u8>> {
+        &self.custom_payload
+    }
+
     /// Parses the raw bytes of a cassandra envelope returning a [`ParsedEnvelope`] struct.
     /// The typical use case is reading from a buffer that may contain 0 or more envelopes and where
     /// the last envelope may be incomplete. The possible return values are:
@@ -179,7 +204,7 @@ impl Envelope {
 
         let body_len = full_body.len();
 
-        // Use cursor to get tracing id, warnings and actual body
+        // Use cursor to get tracing id, warnings, custom payload and actual body
         let mut body_cursor = Cursor::new(full_body.as_slice());
 
         let tracing_id = if flags.contains(Flags::TRACING) && direction == Direction::Response {
@@ -198,6 +223,13 @@ impl Envelope {
             vec![]
         };
 
+        let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+            from_cursor_bytes_map(&mut body_cursor)
+                .map_err(ParseEnvelopeError::InvalidCustomPayload)?
+        } else {
+            HashMap::new()
+        };
+
         let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
 
         std::io::Read::read_to_end(&mut body_cursor, &mut body)
@@ -214,6 +246,7 @@ impl Envelope {
                 body,
                 tracing_id,
                 warnings,
+                custom_payload,
             },
         ))
     }
@@ -282,6 +315,17 @@ impl Envelope {
             }
         }
 
+        if self.flags.contains(Flags::CUSTOM_PAYLOAD) {
+            let mut custom_payload = vec![];
+            serialize_bytes_map(
+                &mut Cursor::new(&mut custom_payload),
+                &self.custom_payload,
+                self.version,
+            );
+
+            flags_buffer.append(&mut custom_payload);
+        }
+
         if is_compressed {
             // avoid having to copy the body if there is nothing in flags_buffer
             let encoded_body = if flags_buffer.is_empty() {
@@ -340,6 +384,8 @@ pub enum ParseEnvelopeError {
     InvalidUuid(uuid::Error),
     #[error("Invalid warnings: {0}")]
     InvalidWarnings(error::Error),
+    #[error("Invalid custom payload: {0}")]
+    InvalidCustomPayload(error::Error),
 }
 
 /// Protocol version.
@@ -683,6 +729,7 @@ mod tests {
             body: vec![],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
         let body = ResponseBody::Ready;
         helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
@@ -702,6 +749,7 @@ mod tests {
             body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "blah".into(),
@@ -738,6 +786,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "some query".into(),
@@ -770,6 +819,7 @@ mod tests {
             body: vec![],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
         let body = RequestBody::Query(BodyReqQuery {
             query: "another query".into(),
@@ -855,6 +905,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
         let body = ResponseBody::Result(ResResultBody::Prepared(BodyResResultPrepared {
             id: CBytesShort::new(vec![
@@ -924,6 +975,7 @@ mod tests {
             ],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
 
         (envelope, raw_envelope)
@@ -944,6 +996,7 @@ mod tests {
             body,
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
 
         (envelope, raw_envelope)
@@ -1128,6 +1181,7 @@ mod flags {
             body: vec![0, 0, 0, 4, 98, 108, 97, 104, 0, 0, 64],
             tracing_id: None,
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
 
         let body = RequestBody::Query(BodyReqQuery {
@@ -1169,6 +1223,7 @@ mod flags {
                 4, 54, 67, 12, 43, 2, 98, 76, 32, 50, 87, 5, 1, 33, 43, 87,
             ])),
             warnings: vec![],
+            custom_payload: HashMap::new(),
         };
 
         let body = ResponseBody::Result(ResResultBody::Void);
@@ -1200,8 +1255,50 @@ mod flags {
             tracing_id: None,
             body: vec![0, 0, 0, 1],
             warnings: vec!["Hello World".into()],
+            custom_payload: HashMap::new(),
+        };
+
+        helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
+    }
+
+    #[test]
+    fn test_custom_payload_response() {
+        let raw_envelope = [
+            132, // version
+            12,  // flags
+            5, 64, // stream id
+            8,  // opcode
+            0, 0, 0, 27, // length
+            // warnings
+            0, 1, 0, 5, 72, 101, 108, 108, 111, // warnings
+            // custom payload
+            0, 1, 0, 3, 107, 101, 121, 0, 0, 0, 3, 1, 2, 3, // custom payload
+            0, 0, 0, 1, // body
+        ];
+
+        let body = ResponseBody::Result(ResResultBody::Void);
+
+        let envelope = Envelope {
+            version: Version::V4,
+            opcode: Opcode::Result,
+            flags: Flags::WARNING | Flags::CUSTOM_PAYLOAD,
+            direction: Direction::Response,
+            stream_id: 1344,
+            tracing_id: None,
+            body: vec![0, 0, 0, 1],
+            warnings: vec!["Hello".into()],
+            custom_payload: HashMap::from([("key".into(), vec![1, 2, 3])]),
         };
 
         helpers::test_encode_decode_roundtrip_response(&raw_envelope, envelope, body);
     }
+
+    #[test]
+    fn test_custom_payload_unsupported_version() {
+        let custom_payload = HashMap::from([("key".into(), vec![1, 2, 3])]);
+
+        let envelope = Envelope::new_req_options(Version::V3).with_custom_payload(custom_payload);
+        assert!(!envelope.flags.contains(Flags::CUSTOM_PAYLOAD));
+        assert!(envelope.custom_payload.is_empty());
+    }
 }
@@ -1,6 +1,6 @@
 use crate::error::Error;
 use bytes::Buf;
-use derive_more::Constructor;
+use derive_more::{Constructor, Display};
 use std::cmp::min;
 use std::convert::TryFrom;
 use std::num::Wrapping;
@@ -8,7 +8,7 @@ use std::num::Wrapping;
 const C1: Wrapping<i64> = Wrapping(0x87c3_7b91_1142_53d5_u64 as i64);
 const C2: Wrapping<i64> = Wrapping(0x4cf5_ad43_2745_937f_u64 as i64);
 
-/// A token on the ring. Only Murmur3 tokens are supported for now.
+/// A token on the ring, generated by [`Partitioner::Murmur3`].
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash, Constructor)]
 pub struct Murmur3Token {
     pub value: i64,
@@ -104,6 +104,151 @@ impl From<i64> for Murmur3Token {
     }
 }
 
+/// A token on the ring, generated by [`Partitioner::Random`] - an absolute value of the MD5 hash of
+/// the routing key.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash, Constructor)]
+pub struct RandomToken {
+    pub value: u128,
+}
+
+impl RandomToken {
+    pub fn generate(routing_key: &[u8]) -> Self {
+        let digest = md5::compute(routing_key);
+        RandomToken::new(i128::from_be_bytes(digest.0).unsigned_abs())
+    }
+}
+
+impl TryFrom<String> for RandomToken {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .parse()
+            .map_err(|error| format!("Error parsing token: {error}").into())
+            .map(RandomToken::new)
+    }
+}
+
+/// A token on the ring, generated by [`Partitioner::ByteOrdered`] - the raw routing key.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash, Constructor)]
+pub struct ByteOrderedToken {
+    pub value: Vec<u8>,
+}
+
+impl ByteOrderedToken {
+    pub fn generate(routing_key: &[u8]) -> Self {
+        ByteOrderedToken::new(routing_key.to_vec())
+    }
+}
+
+impl TryFrom<String> for ByteOrderedToken {
+    type Error = Error;
+
+    // tokens are hex-encoded by the server
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = value.strip_prefix("0x").unwrap_or(&value);
+        if value.len() % 2 != 0 || !value.is_ascii() {
+            return Err(format!("Error parsing token: invalid hex string {value}").into());
+        }
+
+        (0..value.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&value[index..index + 2], 16))
+            .collect::<Result<_, _>>()
+            .map_err(|error| format!("Error parsing token: {error}").into())
+            .map(ByteOrderedToken::new)
+    }
+}
+
+/// A token on the ring. All tokens in a cluster come from the same [`Partitioner`], so tokens
+/// generated by different partitioners should not be compared.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Debug, Hash)]
+pub enum Token {
+    Murmur3(Murmur3Token),
+    Random(RandomToken),
+    ByteOrdered(ByteOrderedToken),
+}
+
+impl Default for Token {
+    fn default() -> Self {
+        Token::Murmur3(Default::default())
+    }
+}
+
+impl From<Murmur3Token> for Token {
+    fn from(value: Murmur3Token) -> Self {
+        Token::Murmur3(value)
+    }
+}
+
+impl From<RandomToken> for Token {
+    fn from(value: RandomToken) -> Self {
+        Token::Random(value)
+    }
+}
+
+impl From<ByteOrderedToken> for Token {
+    fn from(value: ByteOrderedToken) -> Self {
+        Token::ByteOrdered(value)
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Murmur3(token) => write!(f, "{}", token.value),
+            Token::Random(token) => write!(f, "{}", token.value),
+            Token::ByteOrdered(token) => token
+                .value
+                .iter()
+                .try_for_each(|byte| write!(f, "{byte:02x}")),
+        }
+    }
+}
+
+/// Partitioner used by the cluster to distribute data between nodes.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug, Hash, Display)]
+pub enum Partitioner {
+    #[default]
+    Murmur3,
+    Random,
+    ByteOrdered,
+}
+
+impl Partitioner {
+    /// Generates a token for given routing key.
+    pub fn hash(&self, routing_key: &[u8]) -> Token {
+        match self {
+            Partitioner::Murmur3 => Murmur3Token::generate(routing_key).into(),
+            Partitioner::Random => RandomToken::generate(routing_key).into(),
+            Partitioner::ByteOrdered => ByteOrderedToken::generate(routing_key).into(),
+        }
+    }
+
+    /// Parses a token in the format returned by the server.
+    pub fn parse_token(&self, value: String) -> Result<Token, Error> {
+        match self {
+            Partitioner::Murmur3 => Murmur3Token::try_from(value).map(Token::from),
+            Partitioner::Random => RandomToken::try_from(value).map(Token::from),
+            Partitioner::ByteOrdered => ByteOrderedToken::try_from(value).map(Token::from),
+        }
+    }
+}
+
+impl TryFrom<&str> for Partitioner {
+    type Error = Error;
+
+    /// Creates a partitioner from its class name, as reported in `system.local`.
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.rsplit('.').next().unwrap_or(value) {
+            "Murmur3Partitioner" => Ok(Partitioner::Murmur3),
+            "RandomPartitioner" => Ok(Partitioner::Random),
+            "ByteOrderedPartitioner" => Ok(Partitioner::ByteOrdered),
+            _ => Err(format!("Unsupported partitioner: {value}").into()),
+        }
+    }
+}
+
 #[inline]
 fn rotl64(v: Wrapping<i64>, n: u32) -> Wrapping<i64> {
     Wrapping((v.0 << n) | (v.0 as u64 >> (64 - n)) as i64)
@@ -136,4 +281,63 @@ mod test {
             assert_eq!(generated_token.value, s.1);
         }
     }
+
+    #[test]
+    fn test_generate_random_token() {
+        for s in [
+            ("testvalue", 29416761314838505200589635796249845380),
+            ("example_key", 125683033245568201169531869465475303949),
+        ] {
+            let generated_token = RandomToken::generate(s.0.as_bytes());
+            assert_eq!(generated_token.value, s.1);
+        }
+    }
+
+    #[test]
+    fn test_parse_tokens() {
+        assert_eq!(
+            Partitioner::Murmur3.parse_token("-42".into()).unwrap(),
+            Token::Murmur3(Murmur3Token::new(-42))
+        );
+        assert_eq!(
+            Partitioner::Random
+                .parse_token("170141183460469231731687303715884105728".into())
+                .unwrap(),
+            Token::Random(RandomToken::new(1 << 127))
+        );
+        assert_eq!(
+            Partitioner::ByteOrdered
+                .parse_token("00ff1a".into())
+                .unwrap(),
+            Token::ByteOrdered(ByteOrderedToken::new(vec![0, 255, 26]))
+        );
+        assert!(Partitioner::ByteOrdered.parse_token("0f1".into()).is_err());
+        assert!(Partitioner::Murmur3.parse_token("abc".into()).is_err());
+    }
+
+    #[test]
+    fn test_partitioner_from_class_name() {
+        assert_eq!(
+            Partitioner::try_from("org.apache.cassandra.dht.Murmur3Partitioner").unwrap(),
+            Partitioner::Murmur3
+        );
+        assert_eq!(
+            Partitioner::try_from("org.apache.cassandra.dht.RandomPartitioner").unwrap(),
+            Partitioner::Random
+        );
+        assert_eq!(
+            Partitioner::try_from("org.apache.cassandra.dht.ByteOrderedPartitioner").unwrap(),
+            Partitioner::ByteOrdered
+        );
+        assert!(Partitioner::try_from("org.apache.cassandra.dht.LocalPartitioner").is_err());
+    }
+
+    #[test]
+    fn test_byte_ordered_token_order_and_display() {
+        let lower = Token::from(ByteOrderedToken::generate(&[0x01, 0xff]));
+        let higher = Token::from(ByteOrderedToken::generate(&[0x02]));
+
+        assert!(lower < higher);
+        assert_eq!(lower.to_string(), "01ff");
+    }
 }
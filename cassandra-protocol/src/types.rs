@@ -4,6 +4,7 @@ use crate::frame::traits::FromCursor;
 use crate::frame::{Serialize, Version};
 use crate::types::data_serialization_types::*;
 use derive_more::Constructor;
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::io::{self, Write};
 use std::io::{Cursor, Read};
@@ -272,6 +273,49 @@ pub fn from_cursor_string_list(cursor: &mut Cursor<&[u8]>) -> CDRSResult<Vec<Str
     Ok(list)
 }
 
+pub(crate) fn serialize_bytes_map(
+    cursor: &mut Cursor<&mut Vec<u8>>,
+    map: &HashMap<String, Vec<u8>>,
+    version: Version,
+) {
+    let len = map.len() as CIntShort;
+    len.serialize(cursor, version);
+
+    for (key, value) in map {
+        serialize_str(cursor, key, version);
+
+        let len = value.len() as CInt;
+        len.serialize(cursor, version);
+        let _ = cursor.write(value);
+    }
+}
+
+/// Reads a bytes map, e.g. a custom payload. Null values are read as empty.
+pub fn from_cursor_bytes_map(cursor: &mut Cursor<&[u8]>) -> CDRSResult<HashMap<String, Vec<u8>>> {
+    let mut buff = [0; SHORT_LEN];
+    cursor.read_exact(&mut buff)?;
+
+    let len = CIntShort::from_be_bytes(buff);
+    let mut map = HashMap::with_capacity(len as usize);
+    for _ in 0..len {
+        let key = from_cursor_str(cursor)?.to_string();
+
+        let mut buff = [0; INT_LEN];
+        cursor.read_exact(&mut buff)?;
+
+        let len = CInt::from_be_bytes(buff);
+        let value = if len < 0 {
+            vec![]
+        } else {
+            cursor_next_value(cursor, len as usize)?
+        };
+
+        map.insert(key, value);
+    }
+
+    Ok(map)
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Ord, PartialOrd)]
 /// The structure that represents a Cassandra byte type.
 pub struct CBytes {
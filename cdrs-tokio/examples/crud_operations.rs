@@ -3,7 +3,6 @@ extern crate maplit;
 use cdrs_tokio::authenticators::StaticPasswordAuthenticatorProvider;
 use cdrs_tokio::cluster::session::{Session, SessionBuilder, TcpSessionBuilder};
 use cdrs_tokio::cluster::{NodeTcpConfigBuilder, TcpConnectionManager};
-use cdrs_tokio::frame::TryFromRow;
 use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
 use cdrs_tokio::query::*;
 use cdrs_tokio::query_values;
@@ -112,17 +111,14 @@ async fn insert_struct(session: &mut CurrentSession) {
 
 async fn select_struct(session: &mut CurrentSession) {
     let select_struct_cql = "SELECT * FROM test_ks.my_test_table";
-    let rows = session
+    let rows: Vec<RowStruct> = session
         .query(select_struct_cql)
         .await
         .expect("query")
-        .response_body()
-        .expect("get body")
-        .into_rows()
-        .expect("into rows");
+        .rows_typed()
+        .expect("into RowStruct");
 
-    for row in rows {
-        let my_row: RowStruct = RowStruct::try_from_row(row).expect("into RowStruct");
+    for my_row in rows {
         println!("struct got: {my_row:?}");
     }
 }
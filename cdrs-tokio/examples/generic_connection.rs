@@ -1,7 +1,7 @@
 use cdrs_tokio::cluster::connection_pool::ConnectionPoolConfig;
 use cdrs_tokio::cluster::session::{
     NodeDistanceEvaluatorWrapper, ReconnectionPolicyWrapper, RetryPolicyWrapper,
-    DEFAULT_TRANSPORT_BUFFER_SIZE,
+    DEFAULT_MAX_IN_FLIGHT_REQUESTS, DEFAULT_TRANSPORT_BUFFER_SIZE,
 };
 use cdrs_tokio::cluster::{ConnectionManager, KeyspaceHolder};
 use cdrs_tokio::compression::Compression;
@@ -9,6 +9,7 @@ use cdrs_tokio::frame::{Envelope, Version};
 use cdrs_tokio::frame_encoding::ProtocolFrameEncodingFactory;
 use cdrs_tokio::future::BoxFuture;
 use cdrs_tokio::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+use cdrs_tokio::metrics::NoopMetricsRecorder;
 use cdrs_tokio::retry::ConstantReconnectionPolicy;
 use cdrs_tokio::IntoCdrsValue;
 use cdrs_tokio::{
@@ -113,6 +114,8 @@ impl VirtualConnectionManager {
                 Box::<ProtocolFrameEncodingFactory>::default(),
                 Compression::None,
                 DEFAULT_TRANSPORT_BUFFER_SIZE,
+                DEFAULT_MAX_IN_FLIGHT_REQUESTS,
+                Arc::new(NoopMetricsRecorder),
                 true,
                 config.version,
                 #[cfg(feature = "http-proxy")]
@@ -260,17 +263,14 @@ async fn insert_struct(session: &mut CurrentSession) {
 //noinspection DuplicatedCode
 async fn select_struct(session: &mut CurrentSession) {
     let select_struct_cql = "SELECT * FROM test_ks.my_test_table";
-    let rows = session
+    let rows: Vec<RowStruct> = session
         .query(select_struct_cql)
         .await
         .expect("query")
-        .response_body()
-        .expect("get body")
-        .into_rows()
-        .expect("into rows");
+        .rows_typed()
+        .expect("into RowStruct");
 
-    for row in rows {
-        let my_row: RowStruct = RowStruct::try_from_row(row).expect("into RowStruct");
+    for my_row in rows {
         println!("struct got: {my_row:?}");
     }
 }
@@ -5,8 +5,10 @@ use cdrs_tokio::frame::TryFromRow;
 use cdrs_tokio::load_balancing::RoundRobinLoadBalancingStrategy;
 use cdrs_tokio::query::*;
 use cdrs_tokio::query_values;
+use cdrs_tokio::statement::StatementParamsBuilder;
 use cdrs_tokio::transport::TransportTcp;
 use cdrs_tokio::{IntoCdrsValue, TryFromRow};
+use futures::TryStreamExt;
 use std::sync::Arc;
 
 type CurrentSession = Session<
@@ -50,10 +52,12 @@ async fn main() {
         .await
         .unwrap();
     let lb = RoundRobinLoadBalancingStrategy::new();
-    let session = TcpSessionBuilder::new(lb, cluster_config)
-        .build()
-        .await
-        .unwrap();
+    let session = Arc::new(
+        TcpSessionBuilder::new(lb, cluster_config)
+            .build()
+            .await
+            .unwrap(),
+    );
 
     create_keyspace(&session).await;
     create_udt(&session).await;
@@ -67,6 +71,8 @@ async fn main() {
     paged_with_values_list(&session).await;
     println!("\n\nPager with query value (no list)\n");
     paged_with_value(&session).await;
+    println!("\n\nStreamed rows\n");
+    streamed_selection_query(&session).await;
     println!("\n\nFinished paged query tests\n");
 }
 
@@ -128,6 +134,18 @@ async fn paged_selection_query(session: &CurrentSession) {
     }
 }
 
+async fn streamed_selection_query(session: &Arc<CurrentSession>) {
+    let q = "SELECT * FROM test_ks.my_test_table;";
+    let mut rows = session
+        .query_stream(q, StatementParamsBuilder::new().with_page_size(2).build())
+        .with_prefetch_depth(2)
+        .into_typed::<RowStruct>();
+
+    while let Some(my_row) = rows.try_next().await.expect("stream next") {
+        println!("row - {my_row:?}");
+    }
+}
+
 async fn paged_with_value(session: &CurrentSession) {
     let create_table_cql =
         "CREATE TABLE IF NOT EXISTS test_ks.another_test_table (a int, b int, c int, d int, e int, primary key((a, b), c, d));";
@@ -0,0 +1,139 @@
+//! Translation of node addresses reported by the cluster to addresses the driver connects to.
+//!
+//! Nodes advertise their broadcast RPC addresses in `system.peers` tables. When the cluster runs
+//! behind NAT, in containers or in Kubernetes, those are often private addresses unreachable by
+//! the client. An address translator maps them to the addresses which should be used instead.
+//! Translated addresses are only used for establishing connections - nodes are still identified
+//! by their broadcast RPC addresses, e.g. in server events.
+
+use cassandra_protocol::error::Result;
+use futures::FutureExt;
+use fxhash::FxHashMap;
+use std::net::SocketAddr;
+
+use crate::cluster::NodeInfo;
+use crate::future::BoxFuture;
+
+/// Translator of node broadcast RPC addresses to addresses used for connecting to nodes.
+pub trait AddressTranslator {
+    /// Returns the address to connect to given node, based on its broadcast RPC address, host id,
+    /// datacenter and rack. If an error is returned, the broadcast RPC address is used.
+    fn translate_address<'a>(&'a self, node: &'a NodeInfo) -> BoxFuture<'a, Result<SocketAddr>>;
+}
+
+/// A translator using a static map of broadcast RPC addresses to connect addresses. Addresses not
+/// present in the map are left as they are.
+#[derive(Clone, Default, Debug)]
+pub struct StaticAddressTranslator {
+    addresses: FxHashMap<SocketAddr, SocketAddr>,
+}
+
+impl StaticAddressTranslator {
+    pub fn new(addresses: impl IntoIterator<Item = (SocketAddr, SocketAddr)>) -> Self {
+        StaticAddressTranslator {
+            addresses: addresses.into_iter().collect(),
+        }
+    }
+}
+
+impl AddressTranslator for StaticAddressTranslator {
+    fn translate_address<'a>(&'a self, node: &'a NodeInfo) -> BoxFuture<'a, Result<SocketAddr>> {
+        let address = self
+            .addresses
+            .get(&node.broadcast_rpc_address)
+            .copied()
+            .unwrap_or(node.broadcast_rpc_address);
+
+        futures::future::ready(Ok(address)).boxed()
+    }
+}
+
+/// A translator which resolves every node to the same host name, keeping the port of the node.
+/// Useful when all nodes are reachable only through a single proxy, which exposes different nodes
+/// on different ports. Nodes sharing the same port are translated to the same address, so each
+/// node needs a distinct port. The host name is resolved on every translation, so changes of proxy
+/// addresses are picked up on subsequent metadata refreshes.
+#[derive(Clone, Debug)]
+pub struct FixedHostNameAddressTranslator {
+    host_name: String,
+}
+
+impl FixedHostNameAddressTranslator {
+    pub fn new(host_name: String) -> Self {
+        FixedHostNameAddressTranslator { host_name }
+    }
+}
+
+impl AddressTranslator for FixedHostNameAddressTranslator {
+    fn translate_address<'a>(&'a self, node: &'a NodeInfo) -> BoxFuture<'a, Result<SocketAddr>> {
+        async move {
+            tokio::net::lookup_host((self.host_name.as_str(), node.broadcast_rpc_address.port()))
+                .await?
+                .next()
+                .ok_or_else(|| format!("Cannot resolve host name: {}", self.host_name).into())
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use uuid::Uuid;
+
+    use crate::address_translator::{
+        AddressTranslator, FixedHostNameAddressTranslator, StaticAddressTranslator,
+    };
+    use crate::cluster::NodeInfo;
+
+    fn node_info(broadcast_rpc_address: SocketAddr) -> NodeInfo {
+        NodeInfo::new(
+            Uuid::new_v4(),
+            broadcast_rpc_address,
+            None,
+            "dc1".into(),
+            Default::default(),
+            "rack1".into(),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_translate_static_addresses() {
+        let private_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 9042);
+        let public_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)), 19042);
+        let unknown_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 9042);
+
+        let translator = StaticAddressTranslator::new([(private_address, public_address)]);
+
+        assert_eq!(
+            translator
+                .translate_address(&node_info(private_address))
+                .await
+                .unwrap(),
+            public_address
+        );
+        assert_eq!(
+            translator
+                .translate_address(&node_info(unknown_address))
+                .await
+                .unwrap(),
+            unknown_address
+        );
+    }
+
+    #[tokio::test]
+    async fn should_translate_to_fixed_host_name() {
+        let translator = FixedHostNameAddressTranslator::new("127.0.0.1".into());
+
+        assert_eq!(
+            translator
+                .translate_address(&node_info(SocketAddr::new(
+                    IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+                    9043
+                )))
+                .await
+                .unwrap(),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9043)
+        );
+    }
+}
@@ -9,20 +9,34 @@ pub use self::keyspace_holder::KeyspaceHolder;
 pub use self::node_address::NodeAddress;
 pub use self::node_info::NodeInfo;
 pub use self::pager::{ExecPager, PagerState, QueryPager, SessionPager};
+pub use self::query_result::QueryResult;
+pub use self::query_trace::{
+    QueryTrace, TraceEvent, TraceFetchConfig, DEFAULT_TRACE_FETCH_ATTEMPTS,
+    DEFAULT_TRACE_FETCH_INTERVAL,
+};
+pub use self::row_stream::{RowStream, TypedRowStream, DEFAULT_PREFETCH_DEPTH};
 #[cfg(feature = "rust-tls")]
 pub use self::rustls_connection_manager::RustlsConnectionManager;
 pub use self::session::connect_generic;
 pub(crate) use self::session_context::SessionContext;
 pub use self::tcp_connection_manager::TcpConnectionManager;
-pub use self::token_map::TokenMap;
+pub use self::token_map::{ReplicaMap, TokenMap, TokenRange};
 pub use self::topology::cluster_metadata::ClusterMetadata;
+use crate::address_translator::AddressTranslator;
 use crate::cluster::connection_pool::ConnectionPoolConfig;
 use crate::future::BoxFuture;
+use crate::interceptor::RequestInterceptor;
+use crate::metrics::{MetricsRecorder, NoopMetricsRecorder};
+use crate::request_tracker::RequestTracker;
+use crate::timestamp_generator::TimestampGenerator;
 use crate::transport::CdrsTransport;
 use cassandra_protocol::error;
 use cassandra_protocol::frame::Version;
-pub use cassandra_protocol::token::Murmur3Token;
+pub use cassandra_protocol::token::{
+    ByteOrderedToken, Murmur3Token, Partitioner, RandomToken, Token,
+};
 use std::sync::Arc;
+use std::time::Duration;
 
 mod cluster_metadata_manager;
 #[cfg(feature = "http-proxy")]
@@ -41,14 +55,20 @@ mod metadata_builder;
 mod node_address;
 mod node_info;
 mod pager;
+mod prepared_statement_cache;
+mod query_result;
+mod query_trace;
+mod row_stream;
 #[cfg(feature = "rust-tls")]
 mod rustls_connection_manager;
+mod schema_builder;
 pub mod send_envelope;
 pub mod session;
 mod session_context;
 mod tcp_connection_manager;
 mod token_map;
 pub mod topology;
+mod version_negotiation;
 
 /// Generic connection configuration trait that can be used to create user-supplied
 /// connection objects that can be used with the `session::connect()` function.
@@ -72,4 +92,58 @@ pub trait GenericClusterConfig<T: CdrsTransport, CM: ConnectionManager<T>>: Send
     fn beta_protocol(&self) -> bool {
         false
     }
+
+    /// Default request timeout. See
+    /// [`SessionBuilder::with_request_timeout`](session::SessionBuilder::with_request_timeout).
+    fn request_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Prepared statement cache size. See
+    /// [`SessionBuilder::with_prepared_statement_cache_size`](session::SessionBuilder::with_prepared_statement_cache_size).
+    fn prepared_statement_cache_size(&self) -> usize {
+        session::DEFAULT_PREPARED_STATEMENT_CACHE_SIZE
+    }
+
+    /// Prepare statements on all nodes. See
+    /// [`SessionBuilder::with_prepare_on_all_nodes`](session::SessionBuilder::with_prepare_on_all_nodes).
+    fn prepare_on_all_nodes(&self) -> bool {
+        false
+    }
+
+    /// Schema agreement timeout. See
+    /// [`SessionBuilder::with_schema_agreement_timeout`](session::SessionBuilder::with_schema_agreement_timeout).
+    fn schema_agreement_timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Client-side timestamp generator. See
+    /// [`SessionBuilder::with_timestamp_generator`](session::SessionBuilder::with_timestamp_generator).
+    fn timestamp_generator(&self) -> Option<Box<dyn TimestampGenerator + Send + Sync>> {
+        None
+    }
+
+    /// Node address translator. See
+    /// [`SessionBuilder::with_address_translator`](session::SessionBuilder::with_address_translator).
+    fn address_translator(&self) -> Option<Box<dyn AddressTranslator + Send + Sync>> {
+        None
+    }
+
+    /// Recorder of driver metrics. See
+    /// [`SessionBuilder::with_metrics_recorder`](session::SessionBuilder::with_metrics_recorder).
+    fn metrics_recorder(&self) -> Arc<dyn MetricsRecorder + Send + Sync> {
+        Arc::new(NoopMetricsRecorder)
+    }
+
+    /// Request interceptors. See
+    /// [`SessionBuilder::with_request_interceptor`](session::SessionBuilder::with_request_interceptor).
+    fn request_interceptors(&self) -> Vec<Arc<dyn RequestInterceptor + Send + Sync>> {
+        Vec::new()
+    }
+
+    /// Request tracker. See
+    /// [`SessionBuilder::with_request_tracker`](session::SessionBuilder::with_request_tracker).
+    fn request_tracker(&self) -> Option<Arc<dyn RequestTracker + Send + Sync>> {
+        None
+    }
 }
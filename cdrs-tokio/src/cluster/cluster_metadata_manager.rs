@@ -2,8 +2,8 @@ use arc_swap::ArcSwap;
 use cassandra_protocol::error::{Error, Result};
 use cassandra_protocol::events::{SchemaChange, ServerEvent};
 use cassandra_protocol::frame::events::{
-    SchemaChangeOptions, SchemaChangeType, StatusChange, StatusChangeType, TopologyChange,
-    TopologyChangeType,
+    SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType, StatusChange, StatusChangeType,
+    TopologyChange, TopologyChangeType,
 };
 use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
 use cassandra_protocol::frame::message_query::BodyReqQuery;
@@ -14,30 +14,39 @@ use cassandra_protocol::types::rows::Row;
 use cassandra_protocol::types::{AsRustType, ByName, IntoRustByName};
 use fxhash::FxHashMap;
 use itertools::Itertools;
-use rand::{rng, Rng};
-use serde_json::{Map, Value as JsonValue};
-use std::convert::TryInto;
+use std::convert::TryFrom;
 use std::net::{IpAddr, SocketAddr};
-use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::*;
+use uuid::Uuid;
 
+use crate::address_translator::AddressTranslator;
 use crate::cluster::connection_pool::ConnectionPoolFactory;
-use crate::cluster::metadata_builder::{add_new_node, build_initial_metadata, refresh_metadata};
-use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState, ReplicationStrategy};
-use crate::cluster::Murmur3Token;
+use crate::cluster::metadata_builder::{
+    add_new_node, build_initial_metadata, refresh_metadata, translate_address, translate_addresses,
+};
+use crate::cluster::schema_builder::{
+    add_schema_elements, build_json_row, build_keyspaces, JsonRow, SchemaRows,
+};
+use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState};
+use crate::cluster::Partitioner;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
 use crate::cluster::{NodeInfo, SessionContext};
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::transport::CdrsTransport;
 
+// schema tables which might not exist in older server versions
+const OPTIONAL_SCHEMA_TABLES: [&str; 5] = ["views", "indexes", "types", "functions", "aggregates"];
+
 fn find_in_peers(
     peers: &[Row],
     broadcast_rpc_address: SocketAddr,
     control_addr: SocketAddr,
+    partitioner: Partitioner,
 ) -> Result<Option<NodeInfo>> {
     peers
         .iter()
@@ -46,12 +55,12 @@ fn find_in_peers(
                 .filter(|peer_address| {
                     *peer_address == broadcast_rpc_address && is_peer_row_valid(peer)
                 })
-                .map(|peer_address| build_node_info(peer, peer_address))
+                .map(|peer_address| build_node_info(peer, peer_address, partitioner))
         })
         .transpose()
 }
 
-async fn send_query<T: CdrsTransport>(
+pub(crate) async fn send_query<T: CdrsTransport>(
     query: &str,
     transport: &T,
     version: Version,
@@ -99,7 +108,11 @@ async fn send_query_with_params<T: CdrsTransport>(
         .map(|body| body.into_rows())
 }
 
-fn build_node_info(row: &Row, broadcast_rpc_address: SocketAddr) -> Result<NodeInfo> {
+fn build_node_info(
+    row: &Row,
+    broadcast_rpc_address: SocketAddr,
+    partitioner: Partitioner,
+) -> Result<NodeInfo> {
     row.get_r_by_name("host_id").and_then(move |host_id| {
         let broadcast_address: Option<IpAddr> = row
             .get_by_name("broadcast_address")
@@ -133,10 +146,13 @@ fn build_node_info(row: &Row, broadcast_rpc_address: SocketAddr) -> Result<NodeI
             datacenter,
             tokens
                 .into_iter()
-                .map(|token| {
-                    token.try_into().unwrap_or_else(|_| {
-                    warn!(%broadcast_rpc_address, "Unsupported token type - using a dummy value.");
-                    Murmur3Token::new(rng().random()) })
+                .filter_map(|token| {
+                    partitioner
+                        .parse_token(token)
+                        .map_err(|error| {
+                            warn!(%error, %broadcast_rpc_address, "Unsupported token - ignoring.");
+                        })
+                        .ok()
                 })
                 .collect(),
             rack,
@@ -144,6 +160,23 @@ fn build_node_info(row: &Row, broadcast_rpc_address: SocketAddr) -> Result<NodeI
     })
 }
 
+fn build_partitioner(row: &Row) -> Partitioner {
+    let partitioner: Result<Option<String>> = row.get_by_name("partitioner");
+    match partitioner {
+        Ok(Some(partitioner)) => {
+            Partitioner::try_from(partitioner.as_str()).unwrap_or_else(|error| {
+                warn!(%error, "Unsupported partitioner - token-aware routing will not work.");
+                Default::default()
+            })
+        }
+        Ok(None) => Default::default(),
+        Err(error) => {
+            warn!(%error, "Error getting partitioner.");
+            Default::default()
+        }
+    }
+}
+
 fn build_node_broadcast_rpc_address(
     row: &Row,
     broadcast_rpc_address: Option<SocketAddr>,
@@ -238,82 +271,6 @@ async fn fetch_control_connection_info<T: CdrsTransport>(
     .ok_or_else(|| format!("Node {control_addr} failed to return info about itself!").into())
 }
 
-fn build_keyspace(row: &Row) -> Result<(String, KeyspaceMetadata)> {
-    let keyspace_name = row.get_r_by_name("keyspace_name")?;
-
-    let replication: String = row.get_r_by_name("replication")?;
-    let replication: JsonValue = serde_json::from_str(&replication).map_err(|error| {
-        Error::General(format!(
-            "Error parsing replication for {keyspace_name}: {error}"
-        ))
-    })?;
-
-    let replication_strategy = match replication {
-        JsonValue::Object(properties) => build_replication_strategy(properties)?,
-        _ => {
-            return Err(Error::InvalidReplicationFormat {
-                keyspace: keyspace_name,
-            })
-        }
-    };
-
-    Ok((keyspace_name, KeyspaceMetadata::new(replication_strategy)))
-}
-
-fn build_replication_strategy(
-    mut properties: Map<String, JsonValue>,
-) -> Result<ReplicationStrategy> {
-    match properties.remove("class") {
-        Some(JsonValue::String(class)) => Ok(match class.as_str() {
-            "org.apache.cassandra.locator.SimpleStrategy" | "SimpleStrategy" => {
-                ReplicationStrategy::SimpleStrategy {
-                    replication_factor: extract_replication_factor(
-                        properties.get("replication_factor"),
-                    )?,
-                }
-            }
-            "org.apache.cassandra.locator.NetworkTopologyStrategy" | "NetworkTopologyStrategy" => {
-                ReplicationStrategy::NetworkTopologyStrategy {
-                    datacenter_replication_factor: extract_datacenter_replication_factor(
-                        properties,
-                    )?,
-                }
-            }
-            _ => ReplicationStrategy::Other,
-        }),
-        _ => Err("Missing replication strategy class!".into()),
-    }
-}
-
-fn extract_datacenter_replication_factor(
-    properties: Map<String, JsonValue>,
-) -> Result<FxHashMap<String, usize>> {
-    properties
-        .into_iter()
-        .map(|(key, replication_factor)| {
-            extract_replication_factor(Some(&replication_factor))
-                .map(move |replication_factor| (key, replication_factor))
-        })
-        .try_collect()
-}
-
-fn extract_replication_factor(value: Option<&JsonValue>) -> Result<usize> {
-    match value {
-        Some(JsonValue::String(replication_factor)) => {
-            let result = if let Some(slash) = replication_factor.find('/') {
-                usize::from_str(&replication_factor[..slash])
-            } else {
-                usize::from_str(replication_factor)
-            };
-
-            result.map_err(|error| {
-                format!("Failed to parse ('{replication_factor}'): {error}").into()
-            })
-        }
-        _ => Err("Missing replication factor!".into()),
-    }
-}
-
 pub(crate) struct ClusterMetadataManager<
     T: CdrsTransport + 'static,
     CM: ConnectionManager<T> + 'static,
@@ -325,18 +282,24 @@ pub(crate) struct ClusterMetadataManager<
     is_schema_v2: AtomicBool,
     session_context: Arc<SessionContext<T>>,
     node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+    address_translator: Option<Box<dyn AddressTranslator + Send + Sync>>,
     version: Version,
     beta_protocol: bool,
+    // notified about nodes becoming up or added to the cluster
+    node_up_sender: UnboundedSender<Arc<Node<T, CM>>>,
 }
 
 impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMetadataManager<T, CM> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         contact_points: Vec<Arc<Node<T, CM>>>,
         connection_pool_factory: Arc<ConnectionPoolFactory<T, CM>>,
         session_context: Arc<SessionContext<T>>,
         node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+        address_translator: Option<Box<dyn AddressTranslator + Send + Sync>>,
         version: Version,
         beta_protocol: bool,
+        node_up_sender: UnboundedSender<Arc<Node<T, CM>>>,
     ) -> Self {
         ClusterMetadataManager {
             metadata: ArcSwap::from_pointee(ClusterMetadata::default()),
@@ -346,8 +309,10 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             is_schema_v2: AtomicBool::new(true),
             session_context,
             node_distance_evaluator,
+            address_translator,
             version,
             beta_protocol,
+            node_up_sender,
         }
     }
 
@@ -385,15 +350,134 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
     }
 
     async fn process_schema_event(&self, event: SchemaChange) {
-        if let SchemaChangeOptions::Keyspace(keyspace) = &event.options {
-            match event.change_type {
-                SchemaChangeType::Created | SchemaChangeType::Updated => {
-                    self.refresh_keyspace(keyspace).await
-                }
-                SchemaChangeType::Dropped => {
+        if let Err(error) = self.try_process_schema_event(&event).await {
+            error!(?error, ?event, "Error refreshing schema!");
+        }
+    }
+
+    async fn try_process_schema_event(&self, event: &SchemaChange) -> Result<()> {
+        let dropped = match event.change_type {
+            SchemaChangeType::Created | SchemaChangeType::Updated => false,
+            SchemaChangeType::Dropped => true,
+            _ => {
+                warn!(?event, "Unrecognized schema event.");
+                return Ok(());
+            }
+        };
+
+        match (&event.target, &event.options) {
+            (_, SchemaChangeOptions::Keyspace(keyspace)) => {
+                if dropped {
                     self.remove_keyspace(keyspace);
+                    Ok(())
+                } else {
+                    self.refresh_keyspace(keyspace).await
                 }
-                _ => warn!(?event, "Unrecognized schema event."),
+            }
+            (SchemaChangeTarget::Table, SchemaChangeOptions::TableType(keyspace, table)) => {
+                // views are also reported as tables
+                let rows = if dropped {
+                    Default::default()
+                } else {
+                    let table_keys = [("keyspace_name", keyspace.as_str()), ("table_name", table)];
+                    let view_keys = [("keyspace_name", keyspace.as_str()), ("view_name", table)];
+
+                    let (tables, columns, indexes, views) = tokio::try_join!(
+                        self.query_schema("tables", &table_keys),
+                        self.query_schema("columns", &table_keys),
+                        self.query_schema("indexes", &table_keys),
+                        self.query_schema("views", &view_keys),
+                    )?;
+
+                    SchemaRows {
+                        tables,
+                        columns,
+                        indexes,
+                        views,
+                        ..Default::default()
+                    }
+                };
+
+                self.refresh_keyspace_elements(keyspace, rows, |keyspace| {
+                    keyspace.tables.remove(table);
+                    keyspace.views.remove(table);
+                })
+                .await
+            }
+            (SchemaChangeTarget::Type, SchemaChangeOptions::TableType(keyspace, name)) => {
+                let rows = if dropped {
+                    Default::default()
+                } else {
+                    SchemaRows {
+                        types: self
+                            .query_schema(
+                                "types",
+                                &[("keyspace_name", keyspace), ("type_name", name)],
+                            )
+                            .await?,
+                        ..Default::default()
+                    }
+                };
+
+                self.refresh_keyspace_elements(keyspace, rows, |keyspace| {
+                    keyspace.user_types.remove(name);
+                })
+                .await
+            }
+            (
+                SchemaChangeTarget::Function,
+                SchemaChangeOptions::FunctionAggregate(keyspace, name, argument_types),
+            ) => {
+                // other overloads are refreshed along the way
+                let rows = if dropped {
+                    Default::default()
+                } else {
+                    SchemaRows {
+                        functions: self
+                            .query_schema(
+                                "functions",
+                                &[("keyspace_name", keyspace), ("function_name", name)],
+                            )
+                            .await?,
+                        ..Default::default()
+                    }
+                };
+
+                self.refresh_keyspace_elements(keyspace, rows, |keyspace| {
+                    keyspace.functions.retain(|function| {
+                        function.name != *name || function.argument_types != *argument_types
+                    });
+                })
+                .await
+            }
+            (
+                SchemaChangeTarget::Aggregate,
+                SchemaChangeOptions::FunctionAggregate(keyspace, name, argument_types),
+            ) => {
+                let rows = if dropped {
+                    Default::default()
+                } else {
+                    SchemaRows {
+                        aggregates: self
+                            .query_schema(
+                                "aggregates",
+                                &[("keyspace_name", keyspace), ("aggregate_name", name)],
+                            )
+                            .await?,
+                        ..Default::default()
+                    }
+                };
+
+                self.refresh_keyspace_elements(keyspace, rows, |keyspace| {
+                    keyspace.aggregates.retain(|aggregate| {
+                        aggregate.name != *name || aggregate.argument_types != *argument_types
+                    });
+                })
+                .await
+            }
+            _ => {
+                warn!(?event, "Unrecognized schema event.");
+                Ok(())
             }
         }
     }
@@ -440,8 +524,9 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
 
                         // node was down or in an unknown state
                         let node = node.clone_with_node_state(NodeState::Up);
-                        self.metadata
-                            .store(Arc::new(metadata.clone_with_node(node)));
+                        let metadata = Arc::new(metadata.clone_with_node(node));
+                        self.metadata.store(metadata.clone());
+                        self.notify_node_up(&metadata, event.addr);
                     } else {
                         debug!(?node, "Ignoring up node event for already up node.");
                     }
@@ -477,47 +562,159 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         }
     }
 
+    fn notify_node_up(&self, metadata: &ClusterMetadata<T, CM>, broadcast_rpc_address: SocketAddr) {
+        if let Some(node) = metadata.find_node_by_rpc_address(broadcast_rpc_address) {
+            // the receiver might be gone when the session is being dropped
+            let _ = self.node_up_sender.send(node);
+        }
+    }
+
     fn remove_keyspace(&self, keyspace: &str) {
         let metadata = self.metadata.load().clone();
         self.metadata
             .store(Arc::new(metadata.clone_without_keyspace(keyspace)));
     }
 
-    async fn refresh_keyspace(&self, keyspace: &str) {
-        if let Err(error) = self.try_refresh_keyspace(keyspace).await {
-            error!(?error, %keyspace, "Error refreshing keyspace!");
+    async fn refresh_keyspace(&self, keyspace: &str) -> Result<()> {
+        debug!(%keyspace, "Refreshing keyspace.");
+
+        let rows = self.query_schema_rows(Some(keyspace)).await?;
+        let disappeared = rows.keyspaces.is_empty();
+
+        match build_keyspaces(rows).remove(keyspace) {
+            Some(keyspace_metadata) => {
+                let metadata = self.metadata.load().clone();
+                self.metadata.store(Arc::new(
+                    metadata.clone_with_keyspace(keyspace.into(), keyspace_metadata),
+                ));
+            }
+            None if disappeared => {
+                warn!(%keyspace, "Keyspace to refresh disappeared.");
+                self.remove_keyspace(keyspace);
+            }
+            // invalid keyspace row has already been logged - keep the last known metadata
+            None => {}
         }
+
+        Ok(())
     }
 
-    async fn try_refresh_keyspace(&self, keyspace: &str) -> Result<()> {
-        debug!(%keyspace, "Refreshing keyspace.");
+    // removes elements from a known keyspace and adds new ones from given rows; unknown keyspaces
+    // are refreshed as a whole
+    async fn refresh_keyspace_elements(
+        &self,
+        keyspace: &str,
+        rows: SchemaRows,
+        remove_elements: impl FnOnce(&mut KeyspaceMetadata),
+    ) -> Result<()> {
+        debug!(%keyspace, "Refreshing keyspace elements.");
+
+        let metadata = self.metadata.load().clone();
+        let mut keyspace_metadata = match metadata.keyspace(keyspace) {
+            Some(keyspace_metadata) => keyspace_metadata.clone(),
+            None => return self.refresh_keyspace(keyspace).await,
+        };
+
+        remove_elements(&mut keyspace_metadata);
+
+        let mut keyspaces = FxHashMap::default();
+        keyspaces.insert(keyspace.to_string(), keyspace_metadata);
+        add_schema_elements(&mut keyspaces, rows);
 
+        if let Some(keyspace_metadata) = keyspaces.remove(keyspace) {
+            self.metadata.store(Arc::new(
+                metadata.clone_with_keyspace(keyspace.into(), keyspace_metadata),
+            ));
+        }
+
+        Ok(())
+    }
+
+    // queries rows of given system_schema table as JSON, optionally filtered by key columns; optional
+    // tables missing in the server version are treated as empty and invalid rows are skipped
+    async fn query_schema(&self, table: &str, keys: &[(&str, &str)]) -> Result<Vec<JsonRow>> {
         let control_transport = self.control_transport()?;
-        send_query_with_values(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces WHERE keyspace_name = ?",
-            QueryValues::SimpleValues(vec![keyspace.into()]),
-            control_transport.as_ref(),
-            self.version,
-            self.beta_protocol,
-        )
-        .await
-        .map(|rows| { rows.and_then(|mut rows| rows.pop()) })
-        .and_then(|row| {
-            match row {
-                Some(row) => {
-                    let (keyspace_name, keyspace) = build_keyspace(&row)?;
-                    let metadata = self.metadata.load().clone();
-                    self.metadata.store(Arc::new(
-                        metadata.clone_with_keyspace(keyspace_name, keyspace),
-                    ));
-                }
-                None => {
-                    warn!(%keyspace, "Keyspace to refresh disappeared.");
-                    self.remove_keyspace(keyspace);
-                }
+
+        let result = if keys.is_empty() {
+            send_query(
+                &format!("SELECT JSON * FROM system_schema.{table}"),
+                control_transport.as_ref(),
+                self.version,
+                self.beta_protocol,
+            )
+            .await
+        } else {
+            let filter = keys
+                .iter()
+                .map(|(key, _)| format!("{key} = ?"))
+                .join(" AND ");
+            let values = keys.iter().map(|(_, value)| (*value).into()).collect();
+
+            send_query_with_values(
+                &format!("SELECT JSON * FROM system_schema.{table} WHERE {filter}"),
+                QueryValues::SimpleValues(values),
+                control_transport.as_ref(),
+                self.version,
+                self.beta_protocol,
+            )
+            .await
+        };
+
+        let rows = match result {
+            Ok(rows) => rows.unwrap_or_default(),
+            // optional table does not exist
+            Err(Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::Invalid,
+                        message,
+                    },
+                ..
+            }) if OPTIONAL_SCHEMA_TABLES.contains(&table) => {
+                debug!(%table, %message, "Cannot query schema table - assuming it's empty.");
+                return Ok(vec![]);
             }
+            Err(error) => return Err(error),
+        };
 
-            Ok(())
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                build_json_row(row)
+                    .map_err(|error| {
+                        warn!(%error, %table, "Skipping invalid schema row.");
+                    })
+                    .ok()
+            })
+            .collect())
+    }
+
+    // queries schema of all keyspaces or the given one
+    async fn query_schema_rows(&self, keyspace: Option<&str>) -> Result<SchemaRows> {
+        let keys = keyspace
+            .map(|keyspace| vec![("keyspace_name", keyspace)])
+            .unwrap_or_default();
+
+        let (keyspaces, tables, columns, views, indexes, types, functions, aggregates) = tokio::try_join!(
+            self.query_schema("keyspaces", &keys),
+            self.query_schema("tables", &keys),
+            self.query_schema("columns", &keys),
+            self.query_schema("views", &keys),
+            self.query_schema("indexes", &keys),
+            self.query_schema("types", &keys),
+            self.query_schema("functions", &keys),
+            self.query_schema("aggregates", &keys),
+        )?;
+
+        Ok(SchemaRows {
+            keyspaces,
+            tables,
+            columns,
+            views,
+            indexes,
+            types,
+            functions,
+            aggregates,
         })
     }
 
@@ -532,12 +729,19 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         let new_node_info = self.find_new_node_info(broadcast_rpc_address).await;
         match new_node_info {
             Ok(Some(new_node_info)) => {
-                self.metadata.store(Arc::new(add_new_node(
+                let connect_address =
+                    translate_address(&new_node_info, self.address_translator.as_deref()).await;
+
+                let metadata = Arc::new(add_new_node(
                     new_node_info,
                     metadata.as_ref(),
                     &self.connection_pool_factory,
                     state,
-                )));
+                    connect_address,
+                ));
+
+                self.metadata.store(metadata.clone());
+                self.notify_node_up(&metadata, broadcast_rpc_address);
             }
             Ok(None) => {
                 warn!(%broadcast_rpc_address, "Cannot find new node info. Ignoring new node.");
@@ -556,6 +760,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
 
         let control_transport = self.control_transport()?;
         let control_addr = control_transport.address();
+        let partitioner = self.metadata.load().partitioner();
 
         // in the awkward case we have the control connection node up, it won't be in peers
         if broadcast_rpc_address == control_addr {
@@ -567,7 +772,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             )
             .await?;
 
-            return build_node_info(&local_info, broadcast_rpc_address).map(Some);
+            return build_node_info(&local_info, broadcast_rpc_address, partitioner).map(Some);
         }
 
         send_query(
@@ -579,7 +784,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         .await
         .map(|peers| {
             peers.and_then(|peers| {
-                find_in_peers(&peers, broadcast_rpc_address, control_addr).transpose()
+                find_in_peers(&peers, broadcast_rpc_address, control_addr, partitioner).transpose()
             })
         })?
         .transpose()
@@ -608,6 +813,11 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         self.metadata.load().clone()
     }
 
+    #[cfg(test)]
+    pub(crate) fn store_metadata(&self, metadata: ClusterMetadata<T, CM>) {
+        self.metadata.store(Arc::new(metadata));
+    }
+
     #[inline]
     pub(crate) fn find_node_by_rpc_address(
         &self,
@@ -620,9 +830,12 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
 
     // Refreshes stored metadata. Note: it is expected to be called by the control connection.
     pub(crate) async fn refresh_metadata(&self, full_refresh: bool) -> Result<()> {
-        let (node_infos, keyspaces) =
+        let ((node_infos, partitioner), keyspaces) =
             tokio::try_join!(self.refresh_node_infos(), self.refresh_keyspaces())?;
 
+        let connect_addresses =
+            translate_addresses(&node_infos, self.address_translator.as_deref()).await;
+
         if self
             .did_initial_refresh
             .compare_exchange(false, true, Ordering::Relaxed, Ordering::Relaxed)
@@ -631,9 +844,11 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
             self.metadata.store(Arc::new(build_initial_metadata(
                 node_infos,
                 keyspaces,
+                partitioner,
                 &self.contact_points,
                 &self.connection_pool_factory,
                 self.node_distance_evaluator.as_ref(),
+                &connect_addresses,
             )));
         } else {
             self.metadata.rcu(move |old_metadata| {
@@ -641,16 +856,20 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                     build_initial_metadata(
                         node_infos.clone(),
                         keyspaces.clone(),
+                        partitioner,
                         &self.contact_points,
                         &self.connection_pool_factory,
                         self.node_distance_evaluator.as_ref(),
+                        &connect_addresses,
                     )
                 } else {
                     refresh_metadata(
                         &node_infos,
+                        partitioner,
                         old_metadata.as_ref(),
                         &self.connection_pool_factory,
                         self.node_distance_evaluator.as_ref(),
+                        &connect_addresses,
                     )
                 }
             });
@@ -659,23 +878,58 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         Ok(())
     }
 
-    async fn refresh_keyspaces(&self) -> Result<FxHashMap<String, KeyspaceMetadata>> {
+    /// Checks if all up nodes have the same schema version, as seen by the control connection
+    /// node.
+    pub(crate) async fn check_schema_agreement(&self) -> Result<bool> {
         let control_transport = self.control_transport()?;
-        send_query(
-            "SELECT keyspace_name, toJson(replication) AS replication FROM system_schema.keyspaces",
-            control_transport.as_ref(),
-            self.version,
-            self.beta_protocol,
-        )
-        .await
-        .and_then(|rows| {
-            rows.map(|rows| rows.iter().map(build_keyspace).try_collect())
-                .transpose()
+        let control_addr = control_transport.address();
+
+        let (local, peers) = tokio::try_join!(
+            send_query(
+                "SELECT schema_version FROM system.local WHERE key='local'",
+                control_transport.as_ref(),
+                self.version,
+                self.beta_protocol,
+            ),
+            self.query_peers(control_transport.as_ref())
+        )?;
+
+        let metadata = self.metadata();
+
+        // down nodes can't agree, so ignore them
+        let peers = peers.into_iter().flatten().filter(|peer| {
+            is_peer_row_valid(peer)
+                && broadcast_rpc_address_from_row(peer, control_addr)
+                    .and_then(|broadcast_rpc_address| {
+                        metadata.find_node_by_rpc_address(broadcast_rpc_address)
+                    })
+                    .map(|node| {
+                        let state = node.state();
+                        state != NodeState::Down && state != NodeState::ForcedDown
+                    })
+                    .unwrap_or(true)
+        });
+
+        let schema_versions: Vec<Option<Uuid>> = local
+            .into_iter()
+            .flatten()
+            .chain(peers)
+            .map(|row| row.get_by_name("schema_version"))
+            .try_collect()?;
+
+        Ok(schema_versions.into_iter().flatten().unique().count() <= 1)
+    }
+
+    async fn refresh_keyspaces(&self) -> Result<FxHashMap<String, Arc<KeyspaceMetadata>>> {
+        self.query_schema_rows(None).await.map(|rows| {
+            build_keyspaces(rows)
+                .into_iter()
+                .map(|(name, keyspace)| (name, Arc::new(keyspace)))
+                .collect()
         })
-        .map(|keyspaces| keyspaces.unwrap_or_default())
     }
 
-    async fn refresh_node_infos(&self) -> Result<Vec<NodeInfo>> {
+    async fn refresh_node_infos(&self) -> Result<(Vec<NodeInfo>, Partitioner)> {
         let control_transport = self.control_transport()?;
         let control_addr = control_transport.address();
 
@@ -695,7 +949,12 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         let local_broadcast_rpc_address =
             build_node_broadcast_rpc_address(&local, local_broadcast_rpc_address, control_addr);
 
-        let mut node_infos = vec![build_node_info(&local, local_broadcast_rpc_address)?];
+        let partitioner = build_partitioner(&local);
+        let mut node_infos = vec![build_node_info(
+            &local,
+            local_broadcast_rpc_address,
+            partitioner,
+        )?];
 
         let peers = self.query_peers(control_transport.as_ref()).await?;
         if let Some(peers) = peers {
@@ -707,8 +966,9 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                         return None;
                     }
 
-                    broadcast_rpc_address_from_row(row, control_addr)
-                        .map(|broadcast_rpc_address| build_node_info(row, broadcast_rpc_address))
+                    broadcast_rpc_address_from_row(row, control_addr).map(|broadcast_rpc_address| {
+                        build_node_info(row, broadcast_rpc_address, partitioner)
+                    })
                 })
                 .fold_ok(node_infos, |mut node_infos, node_info| {
                     node_infos.push(node_info);
@@ -716,7 +976,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
                 })?;
         }
 
-        Ok(node_infos)
+        Ok((node_infos, partitioner))
     }
 
     async fn query_peers(&self, transport: &T) -> Result<Option<Vec<Row>>> {
@@ -762,3 +1022,500 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ClusterMeta
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::{Error, Result};
+    use cassandra_protocol::events::SchemaChange;
+    use cassandra_protocol::frame::events::{
+        SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType,
+    };
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use cassandra_protocol::frame::message_request::RequestBody;
+    use cassandra_protocol::frame::message_result::ColType;
+    use cassandra_protocol::frame::{Envelope, Version};
+    use cassandra_protocol::types::CBytes;
+    use futures::FutureExt;
+    use serde_json::{json, Value as JsonValue};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::sync::watch;
+    use uuid::Uuid;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::connection_pool::ConnectionPoolFactory;
+    use crate::cluster::schema_builder::{build_keyspaces, SchemaRows};
+    use crate::cluster::topology::{Node, NodeDistance, NodeState};
+    use crate::cluster::{ClusterMetadata, ClusterMetadataManager, SessionContext};
+    use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+    use crate::metrics::NoopMetricsRecorder;
+    use crate::retry::MockReconnectionPolicy;
+    use crate::test_utils::create_rows_envelope;
+    use crate::transport::MockCdrsTransport;
+
+    type MockManager =
+        ClusterMetadataManager<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+    const CONTROL_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+
+    fn create_invalid_error() -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "unconfigured table".into(),
+                ty: ErrorType::Invalid,
+            },
+            addr: CONTROL_ADDRESS,
+        }
+    }
+
+    fn json_row(value: JsonValue) -> serde_json::Map<String, JsonValue> {
+        value.as_object().unwrap().clone()
+    }
+
+    fn function_row(argument_type: &str) -> JsonValue {
+        json!({
+            "keyspace_name": "ks",
+            "function_name": "f",
+            "argument_names": ["a"],
+            "argument_types": [argument_type],
+            "body": "return a;",
+            "called_on_null_input": false,
+            "language": "java",
+            "return_type": argument_type
+        })
+    }
+
+    // responds to schema queries with given rows of queried system_schema tables; rows of other
+    // tables are empty
+    fn schema_responder(
+        tables: Vec<(&'static str, Vec<JsonValue>)>,
+    ) -> impl Fn(&str) -> Result<Envelope> + Send + Sync + 'static {
+        move |query| {
+            let table = query
+                .split("system_schema.")
+                .nth(1)
+                .and_then(|rest| rest.split(' ').next())
+                .unwrap_or_else(|| panic!("Unexpected query: {}", query));
+
+            let rows = tables
+                .iter()
+                .find(|(name, _)| *name == table)
+                .map(|(_, rows)| {
+                    rows.iter()
+                        .map(|row| vec![CBytes::new(row.to_string().into_bytes())])
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(create_rows_envelope(&[("[json]", ColType::Varchar)], rows))
+        }
+    }
+
+    // responds to schema version queries with given versions of the control node and its peers
+    fn schema_version_responder(
+        local: Uuid,
+        peers: Vec<(Ipv4Addr, Uuid)>,
+    ) -> impl Fn(&str) -> Result<Envelope> + Send + Sync + 'static {
+        move |query| {
+            if query.contains("system.local") {
+                Ok(create_rows_envelope(
+                    &[("schema_version", ColType::Uuid)],
+                    vec![vec![CBytes::new(local.as_bytes().to_vec())]],
+                ))
+            } else if query.contains("system.peers_v2") {
+                Ok(create_rows_envelope(
+                    &[
+                        ("native_address", ColType::Inet),
+                        ("native_port", ColType::Int),
+                        ("schema_version", ColType::Uuid),
+                    ],
+                    peers
+                        .iter()
+                        .map(|(address, schema_version)| {
+                            vec![
+                                CBytes::new(address.octets().to_vec()),
+                                CBytes::new((CONTROL_ADDRESS.port() as i32).to_be_bytes().to_vec()),
+                                CBytes::new(schema_version.as_bytes().to_vec()),
+                            ]
+                        })
+                        .collect(),
+                ))
+            } else {
+                panic!("Unexpected query: {}", query)
+            }
+        }
+    }
+
+    // creates a manager with a control connection, which responds to queries using given function
+    fn create_manager(
+        respond: impl Fn(&str) -> Result<Envelope> + Send + Sync + 'static,
+    ) -> MockManager {
+        let mut transport = MockCdrsTransport::new();
+        transport
+            .expect_write_envelope()
+            .returning(move |envelope, _| {
+                let response = match envelope.request_body() {
+                    Ok(RequestBody::Query(body)) => respond(&body.query),
+                    body => panic!("Unexpected request: {:?}", body),
+                };
+
+                async move { response }.boxed()
+            });
+        transport.expect_address().return_const(CONTROL_ADDRESS);
+
+        let session_context = SessionContext::default();
+        session_context
+            .control_connection_transport
+            .store(Some(Arc::new(transport)));
+
+        let (_, keyspace_receiver) = watch::channel(None);
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Version::V4,
+            MockConnectionManager::new(),
+            keyspace_receiver,
+            Arc::new(MockReconnectionPolicy::new()),
+            Arc::new(NoopMetricsRecorder),
+        ));
+
+        ClusterMetadataManager::new(
+            vec![],
+            connection_pool_factory,
+            Arc::new(session_context),
+            Box::new(AllLocalNodeDistanceEvaluator),
+            None,
+            Version::V4,
+            false,
+            unbounded_channel().0,
+        )
+    }
+
+    // stores a keyspace with two tables, a user type and a function
+    fn store_keyspace(manager: &MockManager) {
+        let keyspaces = build_keyspaces(SchemaRows {
+            keyspaces: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "durable_writes": true,
+                "replication": {
+                    "class": "org.apache.cassandra.locator.SimpleStrategy",
+                    "replication_factor": "1"
+                }
+            }))],
+            tables: vec![
+                json_row(json!({"keyspace_name": "ks", "table_name": "t1", "comment": "old"})),
+                json_row(json!({"keyspace_name": "ks", "table_name": "t2"})),
+            ],
+            types: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "type_name": "address",
+                "field_names": ["street"],
+                "field_types": ["text"]
+            }))],
+            functions: vec![json_row(function_row("int"))],
+            ..Default::default()
+        })
+        .into_iter()
+        .map(|(name, keyspace)| (name, Arc::new(keyspace)))
+        .collect();
+
+        manager.metadata.store(Arc::new(ClusterMetadata::new(
+            Default::default(),
+            keyspaces,
+        )));
+    }
+
+    // stores nodes with given addresses and states
+    fn store_nodes(manager: &MockManager, nodes: &[(Ipv4Addr, NodeState)]) {
+        let nodes = nodes
+            .iter()
+            .map(|(address, state)| {
+                let host_id = Uuid::new_v4();
+                let node = Node::new_with_state(
+                    manager.connection_pool_factory.clone(),
+                    SocketAddr::new(IpAddr::V4(*address), CONTROL_ADDRESS.port()),
+                    None,
+                    Some(host_id),
+                    Some(NodeDistance::Local),
+                    *state,
+                    Default::default(),
+                    "".into(),
+                    "".into(),
+                );
+
+                (host_id, Arc::new(node))
+            })
+            .collect();
+
+        manager
+            .metadata
+            .store(Arc::new(ClusterMetadata::new(nodes, Default::default())));
+    }
+
+    fn schema_change(
+        change_type: SchemaChangeType,
+        target: SchemaChangeTarget,
+        options: SchemaChangeOptions,
+    ) -> SchemaChange {
+        SchemaChange {
+            change_type,
+            target,
+            options,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_refresh_updated_table() {
+        let manager = create_manager(schema_responder(vec![
+            (
+                "tables",
+                vec![json!({"keyspace_name": "ks", "table_name": "t1", "comment": "new"})],
+            ),
+            (
+                "columns",
+                vec![json!({
+                    "keyspace_name": "ks",
+                    "table_name": "t1",
+                    "column_name": "id",
+                    "clustering_order": "none",
+                    "kind": "partition_key",
+                    "position": 0,
+                    "type": "int"
+                })],
+            ),
+        ]));
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Updated,
+                SchemaChangeTarget::Table,
+                SchemaChangeOptions::TableType("ks".into(), "t1".into()),
+            ))
+            .await
+            .unwrap();
+
+        let metadata = manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+
+        let table = keyspace.table("t1").unwrap();
+        assert_eq!(table.options.get("comment").unwrap(), "'new'");
+        assert_eq!(table.columns[0].name, "id");
+
+        assert!(keyspace.table("t2").is_some());
+        assert!(keyspace.user_type("address").is_some());
+    }
+
+    #[tokio::test]
+    async fn should_remove_dropped_table() {
+        let manager = create_manager(|query| panic!("Unexpected query: {}", query));
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Dropped,
+                SchemaChangeTarget::Table,
+                SchemaChangeOptions::TableType("ks".into(), "t2".into()),
+            ))
+            .await
+            .unwrap();
+
+        let metadata = manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+        assert!(keyspace.table("t1").is_some());
+        assert!(keyspace.table("t2").is_none());
+    }
+
+    #[tokio::test]
+    async fn should_refresh_created_type() {
+        let manager = create_manager(schema_responder(vec![(
+            "types",
+            vec![json!({
+                "keyspace_name": "ks",
+                "type_name": "phone",
+                "field_names": ["number"],
+                "field_types": ["text"]
+            })],
+        )]));
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Created,
+                SchemaChangeTarget::Type,
+                SchemaChangeOptions::TableType("ks".into(), "phone".into()),
+            ))
+            .await
+            .unwrap();
+
+        let metadata = manager.metadata();
+        let keyspace = metadata.keyspace("ks").unwrap();
+        assert_eq!(
+            keyspace.user_type("phone").unwrap().fields[0].name,
+            "number"
+        );
+        assert!(keyspace.user_type("address").is_some());
+        assert_eq!(keyspace.tables.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn should_refresh_function_overloads() {
+        let manager = create_manager(schema_responder(vec![(
+            "functions",
+            vec![function_row("int"), function_row("text")],
+        )]));
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Created,
+                SchemaChangeTarget::Function,
+                SchemaChangeOptions::FunctionAggregate(
+                    "ks".into(),
+                    "f".into(),
+                    vec!["text".into()],
+                ),
+            ))
+            .await
+            .unwrap();
+
+        let metadata = manager.metadata();
+        let functions = &metadata.keyspace("ks").unwrap().functions;
+        assert_eq!(functions.len(), 2);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Dropped,
+                SchemaChangeTarget::Function,
+                SchemaChangeOptions::FunctionAggregate("ks".into(), "f".into(), vec!["int".into()]),
+            ))
+            .await
+            .unwrap();
+
+        let metadata = manager.metadata();
+        let functions = &metadata.keyspace("ks").unwrap().functions;
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].argument_types, vec!["text"]);
+    }
+
+    #[tokio::test]
+    async fn should_remove_dropped_keyspace() {
+        let manager = create_manager(|query| panic!("Unexpected query: {}", query));
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Dropped,
+                SchemaChangeTarget::Keyspace,
+                SchemaChangeOptions::Keyspace("ks".into()),
+            ))
+            .await
+            .unwrap();
+
+        assert!(manager.metadata().keyspace("ks").is_none());
+    }
+
+    #[tokio::test]
+    async fn should_keep_keyspace_when_required_schema_table_is_invalid() {
+        let responder = schema_responder(vec![]);
+        let manager = create_manager(move |query| {
+            if query.contains("system_schema.keyspaces") {
+                Err(create_invalid_error())
+            } else {
+                responder(query)
+            }
+        });
+        store_keyspace(&manager);
+
+        let result = manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Updated,
+                SchemaChangeTarget::Keyspace,
+                SchemaChangeOptions::Keyspace("ks".into()),
+            ))
+            .await;
+
+        assert!(result.is_err());
+        assert!(manager.metadata().keyspace("ks").is_some());
+    }
+
+    #[tokio::test]
+    async fn should_treat_invalid_optional_schema_tables_as_empty() {
+        let responder = schema_responder(vec![(
+            "tables",
+            vec![json!({"keyspace_name": "ks", "table_name": "t1"})],
+        )]);
+        let manager = create_manager(move |query| {
+            if query.contains("system_schema.views") {
+                Err(create_invalid_error())
+            } else {
+                responder(query)
+            }
+        });
+        store_keyspace(&manager);
+
+        manager
+            .try_process_schema_event(&schema_change(
+                SchemaChangeType::Updated,
+                SchemaChangeTarget::Table,
+                SchemaChangeOptions::TableType("ks".into(), "t1".into()),
+            ))
+            .await
+            .unwrap();
+
+        assert!(manager
+            .metadata()
+            .keyspace("ks")
+            .unwrap()
+            .table("t1")
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn should_agree_on_single_schema_version() {
+        let schema_version = Uuid::new_v4();
+        let manager = create_manager(schema_version_responder(
+            schema_version,
+            vec![
+                (Ipv4Addr::new(127, 0, 0, 2), schema_version),
+                (Ipv4Addr::new(127, 0, 0, 3), schema_version),
+            ],
+        ));
+
+        assert!(manager.check_schema_agreement().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_not_agree_on_different_schema_versions() {
+        let manager = create_manager(schema_version_responder(
+            Uuid::new_v4(),
+            vec![(Ipv4Addr::new(127, 0, 0, 2), Uuid::new_v4())],
+        ));
+        store_nodes(&manager, &[(Ipv4Addr::new(127, 0, 0, 2), NodeState::Up)]);
+
+        assert!(!manager.check_schema_agreement().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn should_ignore_down_peers_in_schema_agreement() {
+        let schema_version = Uuid::new_v4();
+        let manager = create_manager(schema_version_responder(
+            schema_version,
+            vec![
+                (Ipv4Addr::new(127, 0, 0, 2), schema_version),
+                (Ipv4Addr::new(127, 0, 0, 3), Uuid::new_v4()),
+                (Ipv4Addr::new(127, 0, 0, 4), Uuid::new_v4()),
+            ],
+        ));
+        store_nodes(
+            &manager,
+            &[
+                (Ipv4Addr::new(127, 0, 0, 2), NodeState::Up),
+                (Ipv4Addr::new(127, 0, 0, 3), NodeState::Down),
+                (Ipv4Addr::new(127, 0, 0, 4), NodeState::ForcedDown),
+            ],
+        );
+
+        assert!(manager.check_schema_agreement().await.unwrap());
+    }
+}
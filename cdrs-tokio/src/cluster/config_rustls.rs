@@ -20,6 +20,7 @@ pub struct NodeRustlsConfig {
     pub(crate) authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     pub(crate) config: Arc<ClientConfig>,
     pub(crate) version: Version,
+    pub(crate) version_negotiation: bool,
     pub(crate) beta_protocol: bool,
     #[cfg(feature = "http-proxy")]
     pub(crate) http_proxy: Option<HttpProxyConfig>,
@@ -35,6 +36,7 @@ pub struct NodeRustlsConfigBuilder {
     authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     config: Arc<ClientConfig>,
     version: Version,
+    version_negotiation: bool,
     beta_protocol: bool,
     #[cfg(feature = "http-proxy")]
     http_proxy: Option<HttpProxyConfig>,
@@ -48,6 +50,7 @@ impl NodeRustlsConfigBuilder {
             authenticator_provider: Arc::new(NoneAuthenticatorProvider),
             config,
             version: Version::V4,
+            version_negotiation: false,
             beta_protocol: false,
             #[cfg(feature = "http-proxy")]
             http_proxy: None,
@@ -86,6 +89,16 @@ impl NodeRustlsConfigBuilder {
         self
     }
 
+    /// Enables protocol version negotiation. The driver starts with the highest supported version
+    /// (V5) and downgrades to V4 and V3 when contact points reject it, or when other nodes in the
+    /// cluster don't support it, e.g. during a rolling upgrade. The negotiated version is used for
+    /// the whole session and the version set via `with_version()` is ignored.
+    #[must_use]
+    pub fn with_version_negotiation(mut self, version_negotiation: bool) -> Self {
+        self.version_negotiation = version_negotiation;
+        self
+    }
+
     /// Sets beta protocol usage flag
     #[must_use]
     pub fn with_beta_protocol(mut self, beta_protocol: bool) -> Self {
@@ -115,6 +128,7 @@ impl NodeRustlsConfigBuilder {
             authenticator_provider: self.authenticator_provider,
             config: self.config,
             version: self.version,
+            version_negotiation: self.version_negotiation,
             beta_protocol: self.beta_protocol,
             #[cfg(feature = "http-proxy")]
             http_proxy: self.http_proxy,
@@ -17,6 +17,7 @@ pub struct NodeTcpConfig {
     #[derivative(Debug = "ignore")]
     pub(crate) authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     pub(crate) version: Version,
+    pub(crate) version_negotiation: bool,
     pub(crate) beta_protocol: bool,
     #[cfg(feature = "http-proxy")]
     pub(crate) http_proxy: Option<HttpProxyConfig>,
@@ -30,6 +31,7 @@ pub struct NodeTcpConfigBuilder {
     #[derivative(Debug = "ignore")]
     authenticator_provider: Arc<dyn SaslAuthenticatorProvider + Send + Sync>,
     version: Version,
+    version_negotiation: bool,
     beta_protocol: bool,
     #[cfg(feature = "http-proxy")]
     http_proxy: Option<HttpProxyConfig>,
@@ -41,6 +43,7 @@ impl Default for NodeTcpConfigBuilder {
             addrs: vec![],
             authenticator_provider: Arc::new(NoneAuthenticatorProvider),
             version: Version::V4,
+            version_negotiation: false,
             beta_protocol: false,
             #[cfg(feature = "http-proxy")]
             http_proxy: None,
@@ -85,6 +88,16 @@ impl NodeTcpConfigBuilder {
         self
     }
 
+    /// Enables protocol version negotiation. The driver starts with the highest supported version
+    /// (V5) and downgrades to V4 and V3 when contact points reject it, or when other nodes in the
+    /// cluster don't support it, e.g. during a rolling upgrade. The negotiated version is used for
+    /// the whole session and the version set via `with_version()` is ignored.
+    #[must_use]
+    pub fn with_version_negotiation(mut self, version_negotiation: bool) -> Self {
+        self.version_negotiation = version_negotiation;
+        self
+    }
+
     /// Sets beta protocol usage flag
     #[must_use]
     pub fn with_beta_protocol(mut self, beta_protocol: bool) -> Self {
@@ -112,6 +125,7 @@ impl NodeTcpConfigBuilder {
             contact_points,
             authenticator_provider: self.authenticator_provider,
             version: self.version,
+            version_negotiation: self.version_negotiation,
             beta_protocol: self.beta_protocol,
             #[cfg(feature = "http-proxy")]
             http_proxy: self.http_proxy,
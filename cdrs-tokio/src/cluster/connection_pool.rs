@@ -19,6 +19,7 @@ use tracing::*;
 use crate::cluster::topology::{Node, NodeDistance, NodeState};
 use crate::cluster::ConnectionManager;
 use crate::error::{Error, Result as CdrsResult};
+use crate::metrics::MetricsRecorder;
 use crate::retry::{ReconnectionPolicy, ReconnectionSchedule};
 use crate::transport::CdrsTransport;
 
@@ -132,6 +133,7 @@ pub(crate) struct ConnectionPoolFactory<
     connection_manager: Arc<CM>,
     keyspace_receiver: Receiver<Option<String>>,
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     _transport: PhantomData<T>,
 }
 
@@ -142,6 +144,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
         connection_manager: CM,
         keyspace_receiver: Receiver<Option<String>>,
         reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     ) -> Self {
         ConnectionPoolFactory {
             config,
@@ -149,6 +152,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
             connection_manager: Arc::new(connection_manager),
             keyspace_receiver,
             reconnection_policy,
+            metrics_recorder,
             _transport: Default::default(),
         }
     }
@@ -165,10 +169,10 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
         node: Weak<Node<T, CM>>,
     ) -> CdrsResult<Arc<ConnectionPool<T, CM>>> {
         let (error_sender, error_receiver) =
-            mpsc::channel(if node_distance == NodeDistance::Local {
-                self.config.local_size
-            } else {
+            mpsc::channel(if node_distance == NodeDistance::Remote {
                 self.config.remote_size
+            } else {
+                self.config.local_size
             });
 
         let pool = Arc::new(
@@ -178,6 +182,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
                 node_distance,
                 self.config,
                 error_sender,
+                self.metrics_recorder.clone(),
             )
             .await?,
         );
@@ -324,6 +329,8 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
                     {
                         // check if the node is down (no active connections)
                         if let Some(pool) = pool.upgrade() {
+                            pool.record_open_connections().await;
+
                             if Self::are_all_connections_down(pool.deref()).await {
                                 debug!(
                                     ?broadcast_rpc_address,
@@ -436,7 +443,13 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> ConnectionP
                 Some(pool) => pool,
             };
 
-            match pool.reconnect_broken().await {
+            pool.metrics_recorder
+                .record_reconnection_attempt(pool.broadcast_rpc_address);
+
+            let result = pool.reconnect_broken().await;
+            pool.record_open_connections().await;
+
+            match result {
                 Ok(all_reconnected) if all_reconnected => return ReconnectionState::NotRunning,
                 Err(Error::InvalidProtocol(_)) => return ReconnectionState::Disabled,
                 _ => {}
@@ -456,6 +469,7 @@ pub(crate) struct ConnectionPool<T: CdrsTransport, CM: ConnectionManager<T>> {
     desired_size: usize,
     current_index: AtomicUsize,
     error_sender: mpsc::Sender<Error>,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
 }
 
 impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM> {
@@ -465,11 +479,12 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
         node_distance: NodeDistance,
         config: ConnectionPoolConfig,
         error_sender: mpsc::Sender<Error>,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     ) -> CdrsResult<Self> {
-        let desired_size = if node_distance == NodeDistance::Local {
-            config.local_size
-        } else {
+        let desired_size = if node_distance == NodeDistance::Remote {
             config.remote_size
+        } else {
+            config.local_size
         };
 
         // initialize the pool
@@ -503,6 +518,8 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
             }
         }
 
+        metrics_recorder.record_open_connections(broadcast_rpc_address, pool.len());
+
         Ok(ConnectionPool {
             connection_manager: Arc::downgrade(connection_manager),
             broadcast_rpc_address,
@@ -511,6 +528,7 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
             desired_size,
             current_index: AtomicUsize::new(0),
             error_sender,
+            metrics_recorder,
         })
     }
 
@@ -531,18 +549,27 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
 
         let mut index = self.current_index.fetch_add(1, Ordering::Relaxed) % pool_len;
         let first_index = index;
+        let mut is_any_busy = false;
 
         loop {
             let connection = &pool[index];
             if !connection.is_broken() {
-                return Ok(connection.clone());
+                if !connection.is_busy() {
+                    return Ok(connection.clone());
+                }
+
+                is_any_busy = true;
             }
 
             index = (index + 1) % pool_len;
 
             if index == first_index {
-                // we've checked the whole pool and everything's down
-                return Err(create_no_connections_error(self.broadcast_rpc_address));
+                // we've checked the whole pool and everything's either down or busy
+                return Err(if is_any_busy {
+                    Error::ConnectionBusy(self.broadcast_rpc_address)
+                } else {
+                    create_no_connections_error(self.broadcast_rpc_address)
+                });
             }
         }
     }
@@ -558,6 +585,19 @@ impl<T: CdrsTransport + 'static, CM: ConnectionManager<T>> ConnectionPool<T, CM>
         false
     }
 
+    async fn record_open_connections(&self) {
+        let open_connections = self
+            .pool
+            .read()
+            .await
+            .iter()
+            .filter(|connection| !connection.is_broken())
+            .count();
+
+        self.metrics_recorder
+            .record_open_connections(self.broadcast_rpc_address, open_connections);
+    }
+
     async fn reconnect_broken(&self) -> CdrsResult<bool> {
         if let Some(connection_manager) = self.connection_manager.upgrade() {
             let mut pool = self.pool.write().await;
@@ -9,6 +9,7 @@ use tracing::*;
 use crate::cluster::topology::Node;
 use crate::cluster::{ClusterMetadataManager, ConnectionManager, SessionContext};
 use crate::load_balancing::LoadBalancingStrategy;
+use crate::metrics::MetricsRecorder;
 use crate::retry::{ReconnectionPolicy, ReconnectionSchedule};
 use crate::transport::CdrsTransport;
 use cassandra_protocol::events::{ServerEvent, SimpleServerEvent};
@@ -18,6 +19,7 @@ const DEFAULT_RECONNECT_DELAY: Duration = Duration::from_secs(10);
 const EVENT_CHANNEL_CAPACITY: usize = 32;
 
 #[derive(Constructor)]
+#[allow(clippy::too_many_arguments)]
 pub(crate) struct ControlConnection<
     T: CdrsTransport + 'static,
     CM: ConnectionManager<T> + 'static,
@@ -29,6 +31,7 @@ pub(crate) struct ControlConnection<
     cluster_metadata_manager: Arc<ClusterMetadataManager<T, CM>>,
     event_sender: Sender<ServerEvent>,
     session_context: Arc<SessionContext<T>>,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     version: Version,
 }
 
@@ -116,6 +119,12 @@ impl<
                     }
 
                     for node in nodes {
+                        // the initial connection is not a reconnection
+                        if init_complete_sender.is_none() {
+                            self.metrics_recorder
+                                .record_reconnection_attempt(node.connect_address());
+                        }
+
                         if let Ok(connection) = node
                             .new_connection(
                                 Some(event_envelope_sender.clone()),
@@ -1,24 +1,91 @@
+use futures::future::join_all;
 use fxhash::{FxHashMap, FxHashSet};
 use std::collections::hash_map::Entry;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing::*;
+use uuid::Uuid;
 
+use crate::address_translator::AddressTranslator;
 use crate::cluster::connection_pool::ConnectionPoolFactory;
 use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState};
-use crate::cluster::{ClusterMetadata, ConnectionManager, NodeInfo};
+use crate::cluster::{ClusterMetadata, ConnectionManager, NodeInfo, Partitioner};
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::transport::CdrsTransport;
 
+// returns the address to connect to given node, falling back to its broadcast RPC address
+pub(crate) async fn translate_address(
+    node_info: &NodeInfo,
+    address_translator: Option<&(dyn AddressTranslator + Send + Sync)>,
+) -> SocketAddr {
+    match address_translator {
+        Some(address_translator) => address_translator
+            .translate_address(node_info)
+            .await
+            .unwrap_or_else(|error| {
+                warn!(%error, broadcast_rpc_address = %node_info.broadcast_rpc_address, "Error translating node address - using broadcast RPC address.");
+                node_info.broadcast_rpc_address
+            }),
+        None => node_info.broadcast_rpc_address,
+    }
+}
+
+// returns addresses to connect to given nodes, by host id; translation happens before building
+// metadata, since it might need to resolve host names and metadata might be built more than once
+pub(crate) async fn translate_addresses(
+    node_infos: &[NodeInfo],
+    address_translator: Option<&(dyn AddressTranslator + Send + Sync)>,
+) -> FxHashMap<Uuid, SocketAddr> {
+    if address_translator.is_none() {
+        return Default::default();
+    }
+
+    let addresses = join_all(
+        node_infos
+            .iter()
+            .map(|node_info| translate_address(node_info, address_translator)),
+    )
+    .await;
+
+    let mut connect_addresses =
+        FxHashMap::with_capacity_and_hasher(node_infos.len(), Default::default());
+    for (node_info, address) in node_infos.iter().zip(addresses) {
+        // keep the first entry, like the metadata builders do for duplicate peers
+        connect_addresses
+            .entry(node_info.host_id)
+            .or_insert(address);
+    }
+
+    connect_addresses
+}
+
+#[inline]
+fn connect_address(
+    node_info: &NodeInfo,
+    connect_addresses: &FxHashMap<Uuid, SocketAddr>,
+) -> SocketAddr {
+    connect_addresses
+        .get(&node_info.host_id)
+        .copied()
+        .unwrap_or(node_info.broadcast_rpc_address)
+}
+
 pub(crate) fn build_initial_metadata<T: CdrsTransport, CM: ConnectionManager<T>>(
     node_infos: Vec<NodeInfo>,
-    keyspaces: FxHashMap<String, KeyspaceMetadata>,
+    keyspaces: FxHashMap<String, Arc<KeyspaceMetadata>>,
+    partitioner: Partitioner,
     contact_points: &[Arc<Node<T, CM>>],
     connection_pool_factory: &Arc<ConnectionPoolFactory<T, CM>>,
     node_distance_evaluator: &(dyn NodeDistanceEvaluator + Send + Sync),
+    connect_addresses: &FxHashMap<Uuid, SocketAddr>,
 ) -> ClusterMetadata<T, CM> {
     let mut nodes = FxHashMap::with_capacity_and_hasher(node_infos.len(), Default::default());
     for node_info in node_infos {
         if let Entry::Vacant(entry) = nodes.entry(node_info.host_id) {
+            let connect_address = connect_address(&node_info, connect_addresses);
+
+            // translated addresses can be shared by many nodes, so only broadcast RPC addresses
+            // identify contact points
             let contact_point = contact_points.iter().find(|contact_point| {
                 contact_point.broadcast_rpc_address() == node_info.broadcast_rpc_address
             });
@@ -28,17 +95,20 @@ pub(crate) fn build_initial_metadata<T: CdrsTransport, CM: ConnectionManager<T>>
                 Arc::new(contact_point.clone_as_contact_point(node_info))
             } else {
                 debug!(?node_info, "Adding new node.");
-                Arc::new(Node::new_with_state(
-                    connection_pool_factory.clone(),
-                    node_info.broadcast_rpc_address,
-                    node_info.broadcast_address,
-                    Some(node_info.host_id),
-                    node_distance_evaluator.compute_distance(&node_info),
-                    NodeState::Up,
-                    node_info.tokens.clone(),
-                    node_info.rack,
-                    node_info.datacenter,
-                ))
+                Arc::new(
+                    Node::new_with_state(
+                        connection_pool_factory.clone(),
+                        node_info.broadcast_rpc_address,
+                        node_info.broadcast_address,
+                        Some(node_info.host_id),
+                        node_distance_evaluator.compute_distance(&node_info),
+                        NodeState::Up,
+                        node_info.tokens.clone(),
+                        node_info.rack,
+                        node_info.datacenter,
+                    )
+                    .with_connect_address(connect_address),
+                )
             };
 
             entry.insert(node);
@@ -50,14 +120,16 @@ pub(crate) fn build_initial_metadata<T: CdrsTransport, CM: ConnectionManager<T>>
         }
     }
 
-    ClusterMetadata::new(nodes, keyspaces)
+    ClusterMetadata::new_with_partitioner(nodes, keyspaces, partitioner)
 }
 
 pub(crate) fn refresh_metadata<T: CdrsTransport, CM: ConnectionManager<T>>(
     node_infos: &[NodeInfo],
+    partitioner: Partitioner,
     old_metadata: &ClusterMetadata<T, CM>,
     connection_pool_factory: &Arc<ConnectionPoolFactory<T, CM>>,
     node_distance_evaluator: &dyn NodeDistanceEvaluator,
+    connect_addresses: &FxHashMap<Uuid, SocketAddr>,
 ) -> ClusterMetadata<T, CM> {
     let old_nodes = old_metadata.nodes();
 
@@ -73,33 +145,41 @@ pub(crate) fn refresh_metadata<T: CdrsTransport, CM: ConnectionManager<T>>(
         } else {
             seen_hosts.insert(node_info.host_id);
 
+            let connect_address = connect_address(node_info, connect_addresses);
             let old_node = old_nodes.get(&node_info.host_id);
             if let Some(old_node) = old_node {
                 debug!(?node_info, "Updating old node.");
                 added_or_updated.insert(
                     node_info.host_id,
-                    Arc::new(old_node.clone_with_node_info(node_info.clone())),
+                    Arc::new(old_node.clone_with_node_info(node_info.clone(), connect_address)),
                 );
             } else {
                 debug!(?node_info, "Adding new node.");
 
-                let node = Arc::new(Node::new(
-                    connection_pool_factory.clone(),
-                    node_info.broadcast_rpc_address,
-                    node_info.broadcast_address,
-                    Some(node_info.host_id),
-                    node_distance_evaluator.compute_distance(node_info),
-                    node_info.tokens.clone(),
-                    node_info.rack.clone(),
-                    node_info.datacenter.clone(),
-                ));
+                let node = Arc::new(
+                    Node::new(
+                        connection_pool_factory.clone(),
+                        node_info.broadcast_rpc_address,
+                        node_info.broadcast_address,
+                        Some(node_info.host_id),
+                        node_distance_evaluator.compute_distance(node_info),
+                        node_info.tokens.clone(),
+                        node_info.rack.clone(),
+                        node_info.datacenter.clone(),
+                    )
+                    .with_connect_address(connect_address),
+                );
 
                 added_or_updated.insert(node_info.host_id, node);
             }
         }
     }
 
-    ClusterMetadata::new(added_or_updated, old_metadata.keyspaces().clone())
+    ClusterMetadata::new_with_partitioner(
+        added_or_updated,
+        old_metadata.keyspaces().clone(),
+        partitioner,
+    )
 }
 
 pub(crate) fn add_new_node<T: CdrsTransport, CM: ConnectionManager<T>>(
@@ -107,31 +187,40 @@ pub(crate) fn add_new_node<T: CdrsTransport, CM: ConnectionManager<T>>(
     old_metadata: &ClusterMetadata<T, CM>,
     connection_pool_factory: &Arc<ConnectionPoolFactory<T, CM>>,
     state: NodeState,
+    connect_address: SocketAddr,
 ) -> ClusterMetadata<T, CM> {
     let old_node = old_metadata.find_node_by_host_id(&node_info.host_id);
     if let Some(old_node) = old_node {
         // If a node is restarted after changing its broadcast RPC address, Cassandra considers that
         // an addition, even though the host_id hasn't changed :(
-        if old_node.broadcast_rpc_address() == node_info.broadcast_rpc_address {
+        if old_node.broadcast_rpc_address() == node_info.broadcast_rpc_address
+            && old_node.connect_address() == connect_address
+        {
             debug!(?old_node, "Ignoring adding an existing node.");
             return old_metadata.clone_with_node(old_node.clone_with_node_state(state));
         }
 
         debug!(?old_node, "Updating old node with new info.");
-        return old_metadata
-            .clone_with_node(old_node.clone_with_node_info_and_state(node_info, state));
+        return old_metadata.clone_with_node(old_node.clone_with_node_info_and_state(
+            node_info,
+            connect_address,
+            state,
+        ));
     }
 
-    old_metadata.clone_with_node(Node::with_state(
-        connection_pool_factory.clone(),
-        node_info.broadcast_rpc_address,
-        node_info.broadcast_address,
-        Some(node_info.host_id),
-        state,
-        node_info.tokens,
-        node_info.rack,
-        node_info.datacenter,
-    ))
+    old_metadata.clone_with_node(
+        Node::with_state(
+            connection_pool_factory.clone(),
+            node_info.broadcast_rpc_address,
+            node_info.broadcast_address,
+            Some(node_info.host_id),
+            state,
+            node_info.tokens,
+            node_info.rack,
+            node_info.datacenter,
+        )
+        .with_connect_address(connect_address),
+    )
 }
 
 //noinspection DuplicatedCode
@@ -143,15 +232,17 @@ mod tests {
     use tokio::sync::watch;
     use uuid::Uuid;
 
+    use crate::address_translator::StaticAddressTranslator;
     use crate::cluster::connection_manager::MockConnectionManager;
     use crate::cluster::connection_pool::ConnectionPoolFactory;
     use crate::cluster::metadata_builder::{
-        add_new_node, build_initial_metadata, refresh_metadata,
+        add_new_node, build_initial_metadata, refresh_metadata, translate_addresses,
     };
     use crate::cluster::topology::NodeMap;
     use crate::cluster::topology::{Node, NodeDistance, NodeState};
     use crate::cluster::{ClusterMetadata, NodeInfo};
     use crate::load_balancing::node_distance_evaluator::MockNodeDistanceEvaluator;
+    use crate::metrics::NoopMetricsRecorder;
     use crate::retry::MockReconnectionPolicy;
     use crate::transport::MockCdrsTransport;
 
@@ -167,6 +258,7 @@ mod tests {
             connection_manager,
             keyspace_receiver,
             Arc::new(reconnection_policy),
+            Arc::new(NoopMetricsRecorder),
         );
 
         Arc::new(connection_pool_factory)
@@ -193,9 +285,11 @@ mod tests {
         let metadata = build_initial_metadata(
             node_infos.clone(),
             Default::default(),
+            Default::default(),
             &[],
             &connection_pool_factory,
             &node_distance_evaluator,
+            &Default::default(),
         );
 
         let nodes = metadata.nodes();
@@ -241,9 +335,11 @@ mod tests {
         let metadata = build_initial_metadata(
             node_infos.clone(),
             Default::default(),
+            Default::default(),
             &contact_points,
             &connection_pool_factory,
             &node_distance_evaluator,
+            &Default::default(),
         );
 
         let nodes = metadata.nodes();
@@ -293,9 +389,11 @@ mod tests {
 
         let metadata = refresh_metadata(
             &node_infos,
+            Default::default(),
             &old_metadata,
             &connection_pool_factory,
             &node_distance_evaluator,
+            &Default::default(),
         );
 
         let nodes = metadata.nodes();
@@ -343,9 +441,11 @@ mod tests {
 
         let metadata = refresh_metadata(
             &node_infos,
+            Default::default(),
             &old_metadata,
             &connection_pool_factory,
             &node_distance_evaluator,
+            &Default::default(),
         );
 
         let nodes = metadata.nodes();
@@ -392,6 +492,7 @@ mod tests {
             &old_metadata,
             &connection_pool_factory,
             NodeState::Up,
+            node_info.broadcast_rpc_address,
         );
 
         let nodes = metadata.nodes();
@@ -446,6 +547,7 @@ mod tests {
             &old_metadata,
             &connection_pool_factory,
             NodeState::Up,
+            node_info.broadcast_rpc_address,
         );
 
         let nodes = metadata.nodes();
@@ -484,6 +586,7 @@ mod tests {
             &old_metadata,
             &connection_pool_factory,
             NodeState::Up,
+            node_info.broadcast_rpc_address,
         );
 
         let nodes = metadata.nodes();
@@ -501,4 +604,127 @@ mod tests {
         );
         assert!(nodes.get(&node_info.host_id).unwrap().distance().is_none());
     }
+
+    #[test]
+    fn should_not_copy_contact_point_with_same_connect_address() {
+        let connection_pool_factory = create_connection_pool_factory();
+
+        let mut node_distance_evaluator = MockNodeDistanceEvaluator::new();
+        node_distance_evaluator
+            .expect_compute_distance()
+            .return_const(None);
+
+        let node_infos = vec![
+            NodeInfo::new(
+                Uuid::new_v4(),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 9042),
+                None,
+                "".into(),
+                Default::default(),
+                "".into(),
+            ),
+            NodeInfo::new(
+                Uuid::new_v4(),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2)), 9042),
+                None,
+                "".into(),
+                Default::default(),
+                "".into(),
+            ),
+        ];
+
+        // a proxy exposing all nodes on the same address
+        let proxy_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+        let contact_points = [Arc::new(Node::new_with_state(
+            connection_pool_factory.clone(),
+            proxy_address,
+            None,
+            None,
+            Some(NodeDistance::Local),
+            NodeState::Up,
+            Default::default(),
+            "".into(),
+            "".into(),
+        ))];
+
+        let connect_addresses = node_infos
+            .iter()
+            .map(|node_info| (node_info.host_id, proxy_address))
+            .collect();
+
+        let metadata = build_initial_metadata(
+            node_infos.clone(),
+            Default::default(),
+            Default::default(),
+            &contact_points,
+            &connection_pool_factory,
+            &node_distance_evaluator,
+            &connect_addresses,
+        );
+
+        for node_info in &node_infos {
+            let node = metadata.find_node_by_host_id(&node_info.host_id).unwrap();
+            assert_eq!(
+                node.broadcast_rpc_address(),
+                node_info.broadcast_rpc_address
+            );
+            assert_eq!(node.connect_address(), proxy_address);
+            assert!(node.distance().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_translate_addresses() {
+        let node_infos = vec![NodeInfo::new(
+            Uuid::new_v4(),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 9042),
+            None,
+            "".into(),
+            Default::default(),
+            "".into(),
+        )];
+
+        let connect_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 19042);
+        let address_translator =
+            StaticAddressTranslator::new([(node_infos[0].broadcast_rpc_address, connect_address)]);
+
+        let connect_addresses = translate_addresses(&node_infos, Some(&address_translator)).await;
+        assert_eq!(connect_addresses.len(), 1);
+        assert_eq!(connect_addresses[&node_infos[0].host_id], connect_address);
+
+        assert!(translate_addresses(&node_infos, None).await.is_empty());
+    }
+
+    #[test]
+    fn should_add_new_node_with_translated_address() {
+        let connection_pool_factory = create_connection_pool_factory();
+
+        let node_info = NodeInfo::new(
+            Uuid::new_v4(),
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 9042),
+            None,
+            "".into(),
+            Default::default(),
+            "".into(),
+        );
+
+        let connect_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 19042);
+
+        let old_metadata = ClusterMetadata::new(Default::default(), Default::default());
+
+        let metadata = add_new_node(
+            node_info.clone(),
+            &old_metadata,
+            &connection_pool_factory,
+            NodeState::Up,
+            connect_address,
+        );
+
+        let node = metadata.find_node_by_host_id(&node_info.host_id).unwrap();
+        assert_eq!(
+            node.broadcast_rpc_address(),
+            node_info.broadcast_rpc_address
+        );
+        assert_eq!(node.connect_address(), connect_address);
+    }
 }
@@ -3,7 +3,7 @@ use derive_more::Constructor;
 use std::net::SocketAddr;
 use uuid::Uuid;
 
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 
 /// Information about a node.
 #[derive(Constructor, Clone, Derivative)]
@@ -14,6 +14,6 @@ pub struct NodeInfo {
     pub broadcast_address: Option<SocketAddr>,
     pub datacenter: String,
     #[derivative(Debug = "ignore")]
-    pub tokens: Vec<Murmur3Token>,
+    pub tokens: Vec<Token>,
     pub rack: String,
 }
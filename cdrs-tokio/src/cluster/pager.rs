@@ -1,14 +1,14 @@
 use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
 use cassandra_protocol::frame::message_result::RowsMetadataFlags;
-use cassandra_protocol::query::{PreparedQuery, QueryParams, QueryParamsBuilder, QueryValues};
+use cassandra_protocol::query::PreparedQuery;
 use cassandra_protocol::types::rows::Row;
 use cassandra_protocol::types::CBytes;
 
 use crate::cluster::session::Session;
 use crate::cluster::ConnectionManager;
 use crate::load_balancing::LoadBalancingStrategy;
-use crate::statement::StatementParamsBuilder;
+use crate::statement::{StatementParams, StatementParamsBuilder};
 use crate::transport::CdrsTransport;
 
 pub struct SessionPager<
@@ -40,14 +40,16 @@ impl<
     where
         Q: ToString,
     {
-        self.query_with_pager_state_params(query, state, Default::default())
+        self.query_with_pager_state_params(query, state, StatementParams::default())
     }
 
+    /// Creates a query pager starting at given state. All statement parameters are used for
+    /// each page, except page size and paging state, which are managed by the pager.
     pub fn query_with_pager_state_params<Q>(
         &'a mut self,
         query: Q,
         state: PagerState,
-        qp: QueryParams,
+        params: impl Into<StatementParams>,
     ) -> QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
     where
         Q: ToString,
@@ -56,8 +58,7 @@ impl<
             pager: self,
             pager_state: state,
             query,
-            qv: qp.values,
-            consistency: qp.consistency,
+            params: params.into(),
         }
     }
 
@@ -67,44 +68,58 @@ impl<
     {
         self.query_with_params(
             query,
-            QueryParamsBuilder::new()
+            StatementParamsBuilder::new()
                 .with_consistency(Consistency::One)
                 .build(),
         )
     }
 
+    /// Creates a query pager. All statement parameters are used for each page, except page size
+    /// and paging state, which are managed by the pager.
     pub fn query_with_params<Q>(
         &'a mut self,
         query: Q,
-        qp: QueryParams,
+        params: impl Into<StatementParams>,
     ) -> QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
     where
         Q: ToString,
     {
-        self.query_with_pager_state_params(query, PagerState::new(), qp)
+        self.query_with_pager_state_params(query, PagerState::new(), params)
     }
 
     #[deprecated(note = "Please use query_with_params() instead.")]
     pub fn query_with_param<Q>(
         &'a mut self,
         query: Q,
-        qp: QueryParams,
+        params: impl Into<StatementParams>,
     ) -> QueryPager<'a, Q, SessionPager<'a, T, CM, LB>>
     where
         Q: ToString,
     {
-        self.query_with_params(query, qp)
+        self.query_with_params(query, params)
     }
 
     pub fn exec_with_pager_state(
         &'a mut self,
         query: &'a PreparedQuery,
         state: PagerState,
+    ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
+        self.exec_with_pager_state_params(query, state, StatementParams::default())
+    }
+
+    /// Creates a prepared query pager starting at given state. All statement parameters are used
+    /// for each page, except page size and paging state, which are managed by the pager.
+    pub fn exec_with_pager_state_params(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        state: PagerState,
+        params: impl Into<StatementParams>,
     ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
         ExecPager {
             pager: self,
             pager_state: state,
             query,
+            params: params.into(),
         }
     }
 
@@ -114,14 +129,30 @@ impl<
     ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
         self.exec_with_pager_state(query, PagerState::new())
     }
+
+    /// Creates a prepared query pager. All statement parameters are used for each page, except
+    /// page size and paging state, which are managed by the pager.
+    pub fn exec_with_params(
+        &'a mut self,
+        query: &'a PreparedQuery,
+        params: impl Into<StatementParams>,
+    ) -> ExecPager<'a, SessionPager<'a, T, CM, LB>> {
+        self.exec_with_pager_state_params(query, PagerState::new(), params)
+    }
+}
+
+fn page_params(params: &StatementParams, page_size: i32, state: &PagerState) -> StatementParams {
+    let mut params = params.clone();
+    params.query_params.page_size = Some(page_size);
+    params.query_params.paging_state.clone_from(&state.cursor);
+    params
 }
 
 pub struct QueryPager<'a, Q: ToString, P: 'a> {
     pager: &'a mut P,
     pager_state: PagerState,
     query: Q,
-    qv: Option<QueryValues>,
-    consistency: Consistency,
+    params: StatementParams,
 }
 
 impl<
@@ -137,24 +168,15 @@ impl<
     }
 
     pub async fn next(&mut self) -> error::Result<Vec<Row>> {
-        let mut params = StatementParamsBuilder::new()
-            .with_consistency(self.consistency)
-            .with_page_size(self.pager.page_size);
-
-        if let Some(qv) = &self.qv {
-            params = params.with_values(qv.clone());
-        }
-        if let Some(cursor) = &self.pager_state.cursor {
-            params = params.with_paging_state(cursor.clone());
-        }
+        let params = page_params(&self.params, self.pager.page_size, &self.pager_state);
         let query = self.query.to_string();
 
         let body = self
             .pager
             .session
-            .query_with_params(query, params.build())
+            .query_with_params(query, params)
             .await
-            .and_then(|envelope| envelope.response_body())?;
+            .and_then(|result| result.response_body())?;
 
         let metadata = body
             .as_rows_metadata()
@@ -183,6 +205,7 @@ pub struct ExecPager<'a, P: 'a> {
     pager: &'a mut P,
     pager_state: PagerState,
     query: &'a PreparedQuery,
+    params: StatementParams,
 }
 
 impl<
@@ -197,17 +220,14 @@ impl<
     }
 
     pub async fn next(&mut self) -> error::Result<Vec<Row>> {
-        let mut params = StatementParamsBuilder::new().with_page_size(self.pager.page_size);
-        if let Some(cursor) = &self.pager_state.cursor {
-            params = params.with_paging_state(cursor.clone());
-        }
+        let params = page_params(&self.params, self.pager.page_size, &self.pager_state);
 
         let body = self
             .pager
             .session
-            .exec_with_params(self.query, &params.build())
+            .exec_with_params(self.query, &params)
             .await
-            .and_then(|envelope| envelope.response_body())?;
+            .and_then(|result| result.response_body())?;
 
         let metadata = body
             .as_rows_metadata()
@@ -284,3 +304,43 @@ impl PagerState {
         self.cursor
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::types::CBytes;
+    use std::time::Duration;
+
+    use crate::cluster::pager::{page_params, PagerState};
+    use crate::statement::StatementParamsBuilder;
+
+    #[test]
+    fn should_set_page_size_and_paging_state() {
+        let params = StatementParamsBuilder::new()
+            .with_consistency(Consistency::Two)
+            .with_keyspace("ks".into())
+            .with_request_timeout(Duration::from_secs(1))
+            .idempotent(true)
+            .build();
+
+        let first_page = page_params(&params, 10, &PagerState::new());
+        assert_eq!(first_page.query_params.page_size, Some(10));
+        assert_eq!(first_page.query_params.paging_state, None);
+
+        let cursor = CBytes::new(vec![1, 2, 3]);
+        let next_page = page_params(
+            &params,
+            10,
+            &PagerState::new_with_cursor_and_more_flag(cursor.clone(), true),
+        );
+        assert_eq!(next_page.query_params.page_size, Some(10));
+        assert_eq!(next_page.query_params.paging_state, Some(cursor));
+
+        for page in [first_page, next_page] {
+            assert_eq!(page.query_params.consistency, Consistency::Two);
+            assert_eq!(page.keyspace.as_deref(), Some("ks"));
+            assert_eq!(page.request_timeout, Some(Duration::from_secs(1)));
+            assert!(page.is_idempotent);
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use cassandra_protocol::error;
+use cassandra_protocol::events::{SchemaChange, ServerEvent};
+use cassandra_protocol::frame::events::SchemaChangeOptions;
+use cassandra_protocol::frame::{Envelope, Flags, Version};
+use cassandra_protocol::query::PreparedQuery;
+use futures::{stream, StreamExt};
+use itertools::Itertools;
+use lru::LruCache;
+use std::future::Future;
+use std::iter;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::sync::OnceCell;
+use tracing::*;
+
+use crate::cluster::send_envelope::{send_envelope, RequestContext};
+use crate::cluster::topology::Node;
+use crate::cluster::ConnectionManager;
+use crate::retry::{FallthroughRetryPolicy, RetryPolicy};
+use crate::transport::CdrsTransport;
+
+// maximum number of statements re-prepared concurrently on a single node
+const MAX_CONCURRENT_REPREPARES: usize = 8;
+
+// an entry is created before the statement is prepared, so concurrent callers can wait for the
+// same prepare
+type CacheEntry = Arc<OnceCell<Arc<PreparedQuery>>>;
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+struct CacheKey {
+    query: String,
+    keyspace: Option<String>,
+}
+
+/// LRU cache of prepared statements, keyed by query string and keyspace.
+pub(crate) struct PreparedStatementCache {
+    // no cache if capacity is 0
+    entries: Option<Mutex<LruCache<CacheKey, CacheEntry>>>,
+}
+
+impl PreparedStatementCache {
+    pub fn new(capacity: usize) -> Self {
+        PreparedStatementCache {
+            entries: NonZeroUsize::new(capacity)
+                .map(|capacity| Mutex::new(LruCache::new(capacity))),
+        }
+    }
+
+    /// Returns a cached statement or prepares it using given function. Concurrent calls for the
+    /// same statement share a single prepare. Failed prepares are not cached.
+    pub async fn get_or_prepare<F, Fut>(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        prepare: F,
+    ) -> error::Result<Arc<PreparedQuery>>
+    where
+        F: FnOnce(String, Option<String>) -> Fut,
+        Fut: Future<Output = error::Result<PreparedQuery>>,
+    {
+        let entries = match &self.entries {
+            Some(entries) => entries,
+            None => return prepare(query, keyspace).await.map(Arc::new),
+        };
+
+        let key = CacheKey { query, keyspace };
+        let entry = entries
+            .lock()
+            .unwrap()
+            .get_or_insert(key.clone(), Default::default)
+            .clone();
+
+        let result = entry
+            .get_or_try_init(|| async {
+                prepare(key.query.clone(), key.keyspace.clone())
+                    .await
+                    .map(Arc::new)
+            })
+            .await
+            .cloned();
+
+        if result.is_err() {
+            let mut entries = entries.lock().unwrap();
+            let is_failed_entry = entries
+                .peek(&key)
+                .map(|current| Arc::ptr_eq(current, &entry) && !current.initialized())
+                .unwrap_or(false);
+
+            if is_failed_entry {
+                entries.pop(&key);
+            }
+        }
+
+        result
+    }
+
+    pub fn listen_to_events(self: &Arc<Self>, mut event_receiver: Receiver<ServerEvent>) {
+        if self.entries.is_none() {
+            return;
+        }
+
+        let cache = Arc::downgrade(self);
+        tokio::spawn(async move {
+            loop {
+                let event = event_receiver.recv().await;
+                match event {
+                    Ok(event) => {
+                        if let Some(cache) = cache.upgrade() {
+                            cache.process_event(event);
+                        } else {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(n)) => {
+                        // we might have missed a schema change
+                        warn!("Skipped {} events - clearing prepared statement cache.", n);
+
+                        if let Some(cache) = cache.upgrade() {
+                            cache.clear();
+                        } else {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// Prepares all cached statements on nodes which became up or were added to the cluster, so
+    /// they don't need to be re-prepared on first execution. Each node is handled by a separate
+    /// task, so a slow node doesn't delay others.
+    pub fn reprepare_on_node_up<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
+        self: &Arc<Self>,
+        mut node_receiver: UnboundedReceiver<Arc<Node<T, CM>>>,
+        version: Version,
+        beta_protocol: bool,
+        request_timeout: Option<Duration>,
+    ) {
+        if self.entries.is_none() {
+            return;
+        }
+
+        let cache = Arc::downgrade(self);
+        tokio::spawn(async move {
+            while let Some(node) = node_receiver.recv().await {
+                if let Some(cache) = cache.upgrade() {
+                    tokio::spawn(async move {
+                        cache
+                            .reprepare(&node, version, beta_protocol, request_timeout)
+                            .await;
+                    });
+                } else {
+                    break;
+                }
+            }
+        });
+    }
+
+    async fn reprepare<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
+        &self,
+        node: &Arc<Node<T, CM>>,
+        version: Version,
+        beta_protocol: bool,
+        request_timeout: Option<Duration>,
+    ) {
+        let statements = self.prepared_statements();
+        if statements.is_empty() {
+            return;
+        }
+
+        let broadcast_rpc_address = node.broadcast_rpc_address();
+        debug!(
+            %broadcast_rpc_address,
+            count = statements.len(),
+            "Re-preparing statements on node."
+        );
+
+        let flags = if beta_protocol {
+            Flags::BETA
+        } else {
+            Flags::empty()
+        };
+
+        let context = RequestContext {
+            request_timeout,
+            ..Default::default()
+        };
+
+        // statements are sent like any other request, but only a few at a time, to leave room for
+        // regular requests; cached statements are prepared without custom payloads, so there are
+        // none to send
+        let results: Vec<_> = stream::iter(statements)
+            .map(|(query, keyspace)| async move {
+                let envelope = Envelope::new_req_prepare(query, keyspace, flags, version);
+                send_envelope(
+                    iter::once(node.clone()),
+                    &envelope,
+                    true,
+                    FallthroughRetryPolicy.new_session(),
+                    context,
+                )
+                .await
+                .unwrap_or_else(|| Err("No response for re-prepare statement!".into()))
+                .and_then(|response| response.response_body())
+            })
+            .buffer_unordered(MAX_CONCURRENT_REPREPARES)
+            .collect()
+            .await;
+
+        for error in results.into_iter().filter_map(|result| result.err()) {
+            warn!(%error, %broadcast_rpc_address, "Error re-preparing statement.");
+        }
+    }
+
+    fn prepared_statements(&self) -> Vec<(String, Option<String>)> {
+        self.entries
+            .as_ref()
+            .map(|entries| {
+                entries
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|(_, entry)| entry.initialized())
+                    .map(|(key, _)| (key.query.clone(), key.keyspace.clone()))
+                    .collect_vec()
+            })
+            .unwrap_or_default()
+    }
+
+    fn process_event(&self, event: ServerEvent) {
+        if let ServerEvent::SchemaChange(SchemaChange { options, .. }) = event {
+            match &options {
+                SchemaChangeOptions::Keyspace(keyspace)
+                | SchemaChangeOptions::TableType(keyspace, _)
+                | SchemaChangeOptions::FunctionAggregate(keyspace, _, _) => {
+                    self.remove_keyspace(keyspace)
+                }
+                // unknown change - assume everything might be affected
+                _ => self.clear(),
+            }
+        }
+    }
+
+    fn remove_keyspace(&self, keyspace: &str) {
+        if let Some(entries) = &self.entries {
+            let mut entries = entries.lock().unwrap();
+
+            // statements still being prepared or with unknown keyspace might also be affected
+            let removed_keys = entries
+                .iter()
+                .filter(|(key, entry)| match entry.get() {
+                    Some(prepared) => prepared
+                        .keyspace
+                        .as_deref()
+                        .or(key.keyspace.as_deref())
+                        .map(|statement_keyspace| statement_keyspace == keyspace)
+                        .unwrap_or(true),
+                    None => true,
+                })
+                .map(|(key, _)| key.clone())
+                .collect_vec();
+
+            debug!(
+                keyspace,
+                count = removed_keys.len(),
+                "Removing prepared statements after schema change."
+            );
+
+            for key in removed_keys {
+                entries.pop(&key);
+            }
+        }
+    }
+
+    fn clear(&self) {
+        if let Some(entries) = &self.entries {
+            entries.lock().unwrap().clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arc_swap::ArcSwapOption;
+    use cassandra_protocol::events::{SchemaChange, ServerEvent};
+    use cassandra_protocol::frame::events::{
+        SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType,
+    };
+    use cassandra_protocol::frame::message_request::RequestBody;
+    use cassandra_protocol::frame::message_result::ResultKind;
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Version};
+    use cassandra_protocol::query::PreparedQuery;
+    use cassandra_protocol::types::{CBytesShort, CInt};
+    use futures::FutureExt;
+    use itertools::Itertools;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio::sync::watch;
+    use tokio::task::yield_now;
+    use tokio::time::{sleep, timeout};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::connection_pool::ConnectionPoolFactory;
+    use crate::cluster::prepared_statement_cache::{
+        PreparedStatementCache, MAX_CONCURRENT_REPREPARES,
+    };
+    use crate::cluster::topology::{Node, NodeDistance, NodeState};
+    use crate::metrics::NoopMetricsRecorder;
+    use crate::retry::MockReconnectionPolicy;
+    use crate::transport::MockCdrsTransport;
+
+    fn create_prepared(query: String, keyspace: Option<String>) -> PreparedQuery {
+        PreparedQuery {
+            id: CBytesShort::new(query.as_bytes().to_vec()),
+            query,
+            keyspace,
+            pk_indexes: vec![],
+            result_metadata_id: ArcSwapOption::empty(),
+        }
+    }
+
+    async fn prepare_counted(
+        cache: &PreparedStatementCache,
+        prepare_count: &AtomicUsize,
+        query: &str,
+        keyspace: Option<&str>,
+    ) -> Arc<PreparedQuery> {
+        cache
+            .get_or_prepare(
+                query.into(),
+                keyspace.map(|keyspace| keyspace.into()),
+                |query, keyspace| async move {
+                    prepare_count.fetch_add(1, Ordering::Relaxed);
+                    yield_now().await;
+                    Ok(create_prepared(query, keyspace))
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_deduplicate_concurrent_prepares() {
+        let cache = PreparedStatementCache::new(10);
+        let prepare_count = AtomicUsize::new(0);
+
+        let (first, second) = tokio::join!(
+            prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks")),
+            prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks")),
+        );
+
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("other")).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn should_evict_least_recently_used() {
+        let cache = PreparedStatementCache::new(2);
+        let prepare_count = AtomicUsize::new(0);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", None).await;
+        prepare_counted(&cache, &prepare_count, "SELECT 2", None).await;
+        prepare_counted(&cache, &prepare_count, "SELECT 1", None).await;
+        prepare_counted(&cache, &prepare_count, "SELECT 3", None).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 3);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", None).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 3);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 2", None).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test]
+    async fn should_not_cache_failed_prepares() {
+        let cache = PreparedStatementCache::new(10);
+
+        assert!(cache
+            .get_or_prepare("SELECT 1".into(), None, |_, _| async { Err("test".into()) })
+            .await
+            .is_err());
+
+        let prepare_count = AtomicUsize::new(0);
+        prepare_counted(&cache, &prepare_count, "SELECT 1", None).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn should_remove_statements_on_schema_change() {
+        let cache = PreparedStatementCache::new(10);
+        let prepare_count = AtomicUsize::new(0);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks1")).await;
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks2")).await;
+
+        cache.process_event(ServerEvent::SchemaChange(SchemaChange {
+            change_type: SchemaChangeType::Updated,
+            target: SchemaChangeTarget::Table,
+            options: SchemaChangeOptions::TableType("ks1".into(), "table".into()),
+        }));
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks2")).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 2);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks1")).await;
+        assert_eq!(prepare_count.load(Ordering::Relaxed), 3);
+    }
+
+    // creates a node which responds to all requests after given delay, recording their envelopes
+    // and the highest number of requests in flight
+    fn create_node(
+        delay: Duration,
+        requests: Arc<Mutex<Vec<Envelope>>>,
+        max_in_flight: Arc<AtomicUsize>,
+    ) -> Arc<Node<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>> {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let requests = requests.clone();
+                let in_flight = in_flight.clone();
+                let max_in_flight = max_in_flight.clone();
+
+                let mut transport = MockCdrsTransport::new();
+                transport
+                    .expect_write_envelope()
+                    .returning(move |envelope, _| {
+                        requests.lock().unwrap().push(envelope.clone());
+
+                        let in_flight = in_flight.clone();
+                        let current = in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+                        max_in_flight.fetch_max(current, Ordering::Relaxed);
+
+                        async move {
+                            sleep(delay).await;
+                            in_flight.fetch_sub(1, Ordering::Relaxed);
+
+                            Ok(Envelope::new(
+                                Version::V4,
+                                Direction::Response,
+                                Flags::empty(),
+                                Opcode::Result,
+                                0,
+                                CInt::from(ResultKind::Void).to_be_bytes().to_vec(),
+                                None,
+                                vec![],
+                            ))
+                        }
+                        .boxed()
+                    });
+                transport.expect_is_broken().return_const(false);
+                transport.expect_address().return_const(addr);
+
+                async move { Ok(transport) }.boxed()
+            });
+
+        let (_, keyspace_receiver) = watch::channel(None);
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Version::V4,
+            connection_manager,
+            keyspace_receiver,
+            Arc::new(MockReconnectionPolicy::new()),
+            Arc::new(NoopMetricsRecorder),
+        ));
+
+        Arc::new(Node::new_with_state(
+            connection_pool_factory,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042),
+            None,
+            None,
+            Some(NodeDistance::Local),
+            NodeState::Up,
+            Default::default(),
+            "".into(),
+            "".into(),
+        ))
+    }
+
+    fn prepared_queries(requests: &Mutex<Vec<Envelope>>) -> Vec<String> {
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|envelope| match envelope.request_body().unwrap() {
+                RequestBody::Prepare(body) => body.query,
+                body => panic!("Unexpected request: {:?}", body),
+            })
+            .sorted()
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn should_reprepare_on_node_up() {
+        let cache = Arc::new(PreparedStatementCache::new(10));
+        let prepare_count = AtomicUsize::new(0);
+
+        prepare_counted(&cache, &prepare_count, "SELECT 1", Some("ks")).await;
+        prepare_counted(&cache, &prepare_count, "SELECT 2", None).await;
+
+        let requests = Arc::new(Mutex::new(vec![]));
+        let node = create_node(Duration::ZERO, requests.clone(), Default::default());
+
+        let (node_sender, node_receiver) = unbounded_channel();
+        cache.reprepare_on_node_up(node_receiver, Version::V4, false, None);
+        node_sender.send(node).unwrap();
+
+        timeout(Duration::from_secs(5), async {
+            while requests.lock().unwrap().len() < 2 {
+                sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(prepared_queries(&requests), vec!["SELECT 1", "SELECT 2"]);
+    }
+
+    #[tokio::test]
+    async fn should_limit_concurrent_reprepares() {
+        let cache = PreparedStatementCache::new(100);
+        let prepare_count = AtomicUsize::new(0);
+        let statement_count = MAX_CONCURRENT_REPREPARES * 3;
+
+        for index in 0..statement_count {
+            prepare_counted(&cache, &prepare_count, &format!("SELECT {index}"), None).await;
+        }
+
+        let requests = Arc::new(Mutex::new(vec![]));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let node = create_node(
+            Duration::from_millis(10),
+            requests.clone(),
+            max_in_flight.clone(),
+        );
+
+        cache.reprepare(&node, Version::V4, false, None).await;
+
+        assert_eq!(requests.lock().unwrap().len(), statement_count);
+        assert_eq!(
+            max_in_flight.load(Ordering::Relaxed),
+            MAX_CONCURRENT_REPREPARES
+        );
+        assert_eq!(node.in_flight_requests(), 0);
+    }
+}
@@ -0,0 +1,279 @@
+use cassandra_protocol::error;
+use cassandra_protocol::frame::message_response::ResponseBody;
+use cassandra_protocol::frame::message_result::{ColSpec, RowsMetadataFlags};
+use cassandra_protocol::frame::{Envelope, TryFromRow};
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::{CBytes, IntoRustByName};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use uuid::Uuid;
+
+const APPLIED_COLUMN: &str = "[applied]";
+
+/// Result of a statement sent to the cluster. Wraps the response envelope along with the address
+/// of the coordinator node, which sent it. Note: methods accessing the response body parse it on
+/// every call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    envelope: Envelope,
+    coordinator: SocketAddr,
+    schema_agreement: Option<bool>,
+}
+
+impl QueryResult {
+    pub fn new(envelope: Envelope, coordinator: SocketAddr) -> Self {
+        QueryResult {
+            envelope,
+            coordinator,
+            schema_agreement: None,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn with_schema_agreement(mut self, schema_agreement: bool) -> Self {
+        self.schema_agreement = Some(schema_agreement);
+        self
+    }
+
+    /// Returns the raw response envelope.
+    #[inline]
+    pub fn envelope(&self) -> &Envelope {
+        &self.envelope
+    }
+
+    /// Converts this result into the raw response envelope.
+    #[inline]
+    pub fn into_envelope(self) -> Envelope {
+        self.envelope
+    }
+
+    /// Returns the broadcast RPC address of the coordinator node.
+    #[inline]
+    pub fn coordinator(&self) -> SocketAddr {
+        self.coordinator
+    }
+
+    /// Parses the response body.
+    #[inline]
+    pub fn response_body(&self) -> error::Result<ResponseBody> {
+        self.envelope.response_body()
+    }
+
+    /// Returns warnings sent by the server, if warnings were enabled for the statement.
+    #[inline]
+    pub fn warnings(&self) -> &[String] {
+        &self.envelope.warnings
+    }
+
+    /// Returns custom payload sent by the server, if any.
+    #[inline]
+    pub fn custom_payload(&self) -> &HashMap<String, Vec<u8>> {
+        &self.envelope.custom_payload
+    }
+
+    /// Returns tracing id, if tracing was enabled for the statement.
+    #[inline]
+    pub fn tracing_id(&self) -> Option<Uuid> {
+        self.envelope.tracing_id
+    }
+
+    /// Returns whether schema agreement was reached after a schema change, if the session waited
+    /// for it.
+    #[inline]
+    pub fn schema_agreement(&self) -> Option<bool> {
+        self.schema_agreement
+    }
+
+    /// Returns resulting rows. Fails if the result doesn't contain rows.
+    pub fn rows(&self) -> error::Result<Vec<Row>> {
+        self.response_body()?
+            .into_rows()
+            .ok_or_else(|| "Result does not contain rows!".into())
+    }
+
+    /// Returns resulting rows converted to given type.
+    pub fn rows_typed<R: TryFromRow>(&self) -> error::Result<Vec<R>> {
+        self.rows()?.into_iter().map(R::try_from_row).collect()
+    }
+
+    /// Returns the first resulting row, if present.
+    pub fn first_row(&self) -> error::Result<Option<Row>> {
+        self.rows().map(|rows| rows.into_iter().next())
+    }
+
+    /// Returns the only resulting row. Fails if there isn't exactly one row.
+    pub fn single_row(&self) -> error::Result<Row> {
+        let mut rows = self.rows()?;
+        if rows.len() != 1 {
+            return Err(format!("Expected a single row, got {}!", rows.len()).into());
+        }
+
+        Ok(rows.remove(0))
+    }
+
+    /// Returns specifications of resulting columns. Fails if the result doesn't contain rows.
+    pub fn column_specs(&self) -> error::Result<Vec<ColSpec>> {
+        self.response_body()?
+            .as_rows_metadata()
+            .map(|metadata| metadata.col_specs.clone())
+            .ok_or_else(|| "Result does not contain rows!".into())
+    }
+
+    /// Returns paging state for fetching the next page, if there are more pages. Fails if the
+    /// result doesn't contain rows.
+    pub fn paging_state(&self) -> error::Result<Option<CBytes>> {
+        self.response_body()?
+            .as_rows_metadata()
+            .map(|metadata| {
+                if metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES) {
+                    metadata.paging_state.clone()
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| "Result does not contain rows!".into())
+    }
+
+    /// Returns whether a lightweight transaction was applied. Statements which are not
+    /// conditional are always applied.
+    pub fn was_applied(&self) -> error::Result<bool> {
+        let row = match self.response_body()?.into_rows() {
+            Some(rows) => rows.into_iter().next(),
+            None => return Ok(true),
+        };
+
+        match row {
+            Some(row) if row.contains_column(APPLIED_COLUMN) => row
+                .get_by_name(APPLIED_COLUMN)
+                .map(|applied: Option<bool>| applied.unwrap_or(false)),
+            _ => Ok(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, ResResultBody, RowsMetadata,
+        RowsMetadataFlags, TableSpec,
+    };
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+    use cassandra_protocol::types::CBytes;
+    use std::net::SocketAddr;
+
+    use crate::cluster::QueryResult;
+
+    fn create_result(body: ResResultBody) -> QueryResult {
+        let envelope = Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec(Version::V4),
+            None,
+            vec![],
+        );
+
+        QueryResult::new(envelope, SocketAddr::from(([127, 0, 0, 1], 9042)))
+    }
+
+    fn create_rows_result(
+        column: &str,
+        col_type: ColType,
+        values: Vec<CBytes>,
+        paging_state: Option<CBytes>,
+    ) -> QueryResult {
+        let flags = if paging_state.is_some() {
+            RowsMetadataFlags::HAS_MORE_PAGES
+        } else {
+            RowsMetadataFlags::empty()
+        };
+
+        create_result(ResResultBody::Rows(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags,
+                columns_count: 1,
+                paging_state,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![ColSpec {
+                    table_spec: Some(TableSpec {
+                        ks_name: "ks".into(),
+                        table_name: "table".into(),
+                    }),
+                    name: column.into(),
+                    col_type: ColTypeOption {
+                        id: col_type,
+                        value: None,
+                    },
+                }],
+            },
+            rows_count: values.len() as i32,
+            rows_content: values.into_iter().map(|value| vec![value]).collect(),
+            protocol_version: Version::V4,
+        }))
+    }
+
+    fn create_int_rows_result(ids: &[i32]) -> QueryResult {
+        create_rows_result(
+            "id",
+            ColType::Int,
+            ids.iter()
+                .map(|id| CBytes::new(id.to_be_bytes().to_vec()))
+                .collect(),
+            None,
+        )
+    }
+
+    #[test]
+    fn should_return_rows() {
+        let result = create_int_rows_result(&[1, 2]);
+
+        assert_eq!(result.rows().unwrap().len(), 2);
+        assert!(result.first_row().unwrap().is_some());
+        assert!(result.single_row().is_err());
+        assert_eq!(result.column_specs().unwrap()[0].name, "id");
+        assert_eq!(result.paging_state().unwrap(), None);
+
+        assert!(create_int_rows_result(&[1]).single_row().is_ok());
+        assert!(create_int_rows_result(&[]).first_row().unwrap().is_none());
+    }
+
+    #[test]
+    fn should_return_paging_state() {
+        let paging_state = CBytes::new(vec![1, 2, 3]);
+        let result = create_rows_result("id", ColType::Int, vec![], Some(paging_state.clone()));
+
+        assert_eq!(result.paging_state().unwrap(), Some(paging_state));
+    }
+
+    #[test]
+    fn should_not_return_rows_for_void() {
+        let result = create_result(ResResultBody::Void);
+
+        assert!(result.rows().is_err());
+        assert!(result.was_applied().unwrap());
+    }
+
+    #[test]
+    fn should_check_if_applied() {
+        let applied = create_rows_result(
+            "[applied]",
+            ColType::Boolean,
+            vec![CBytes::new(vec![1])],
+            None,
+        );
+        assert!(applied.was_applied().unwrap());
+
+        let not_applied = create_rows_result(
+            "[applied]",
+            ColType::Boolean,
+            vec![CBytes::new(vec![0])],
+            None,
+        );
+        assert!(!not_applied.was_applied().unwrap());
+
+        assert!(create_int_rows_result(&[1]).was_applied().unwrap());
+    }
+}
@@ -0,0 +1,297 @@
+use cassandra_protocol::consistency::Consistency;
+use cassandra_protocol::error;
+use cassandra_protocol::types::map::Map;
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::{AsRustType, IntoRustByName};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub const DEFAULT_TRACE_FETCH_ATTEMPTS: usize = 5;
+pub const DEFAULT_TRACE_FETCH_INTERVAL: Duration = Duration::from_millis(3);
+
+/// Configuration for fetching query traces. Traces are written asynchronously by the server, so
+/// they might not be complete right after the traced query returns. Fetching is retried until the
+/// trace is complete or the maximum number of attempts is reached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceFetchConfig {
+    /// Maximum number of attempts to fetch a complete trace.
+    pub max_attempts: usize,
+    /// Delay between consecutive attempts.
+    pub retry_interval: Duration,
+    /// Consistency used when querying trace tables.
+    pub consistency: Consistency,
+}
+
+impl Default for TraceFetchConfig {
+    fn default() -> Self {
+        TraceFetchConfig {
+            max_attempts: DEFAULT_TRACE_FETCH_ATTEMPTS,
+            retry_interval: DEFAULT_TRACE_FETCH_INTERVAL,
+            consistency: Consistency::One,
+        }
+    }
+}
+
+/// Single event recorded in a query trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEvent {
+    pub event_id: Uuid,
+    /// Description of the activity.
+    pub activity: String,
+    /// Node which recorded the event.
+    pub source: IpAddr,
+    /// Time elapsed on the source node since the beginning of processing the request.
+    pub source_elapsed: Duration,
+    /// Name of the thread which recorded the event.
+    pub thread: String,
+}
+
+impl TraceEvent {
+    pub(crate) fn try_from_row(row: &Row) -> error::Result<Self> {
+        let source_elapsed: Option<i32> = row.get_by_name("source_elapsed")?;
+        Ok(TraceEvent {
+            event_id: row.get_r_by_name("event_id")?,
+            activity: row.get_by_name("activity")?.unwrap_or_default(),
+            source: row.get_r_by_name("source")?,
+            source_elapsed: micros_to_duration(source_elapsed.unwrap_or_default()),
+            thread: row.get_by_name("thread")?.unwrap_or_default(),
+        })
+    }
+}
+
+/// Tracing information about a query, recorded by the server when tracing is enabled for a
+/// statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTrace {
+    pub tracing_id: Uuid,
+    /// Type of request, e.g. "Execute CQL3 query".
+    pub request: String,
+    /// Coordinator node of the query.
+    pub coordinator: IpAddr,
+    /// Client address, as seen by the coordinator.
+    pub client: Option<IpAddr>,
+    /// Total time of processing the request on the coordinator.
+    pub duration: Duration,
+    /// Request parameters, e.g. the query string and consistency level.
+    pub parameters: HashMap<String, String>,
+    /// Trace events in order of occurrence.
+    pub events: Vec<TraceEvent>,
+}
+
+impl QueryTrace {
+    /// Creates a trace from `system_traces.sessions` and `system_traces.events` rows. Returns
+    /// `None` if the trace is not complete yet.
+    pub(crate) fn try_from_rows(
+        tracing_id: Uuid,
+        session_row: &Row,
+        event_rows: &[Row],
+    ) -> error::Result<Option<Self>> {
+        let duration: Option<i32> = session_row.get_by_name("duration")?;
+        let duration = match duration {
+            Some(duration) => micros_to_duration(duration),
+            None => return Ok(None),
+        };
+
+        let parameters: Option<Map> = session_row.get_by_name("parameters")?;
+        let parameters = parameters
+            .map(|parameters| parameters.as_r_type())
+            .transpose()?
+            .unwrap_or_default();
+
+        let events = event_rows
+            .iter()
+            .map(TraceEvent::try_from_row)
+            .collect::<error::Result<_>>()?;
+
+        Ok(Some(QueryTrace {
+            tracing_id,
+            request: session_row.get_by_name("request")?.unwrap_or_default(),
+            coordinator: session_row.get_r_by_name("coordinator")?,
+            client: session_row.get_by_name("client")?,
+            duration,
+            parameters,
+            events,
+        }))
+    }
+}
+
+#[inline]
+fn micros_to_duration(micros: i32) -> Duration {
+    Duration::from_micros(micros.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, ColTypeOptionValue, RowsMetadata,
+        RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::Version;
+    use cassandra_protocol::types::rows::Row;
+    use cassandra_protocol::types::CBytes;
+    use std::net::{IpAddr, Ipv4Addr};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    use crate::cluster::query_trace::QueryTrace;
+
+    fn col_type(id: ColType) -> ColTypeOption {
+        ColTypeOption { id, value: None }
+    }
+
+    fn create_row(columns: Vec<(&str, ColTypeOption, CBytes)>) -> Row {
+        let (col_specs, values): (Vec<_>, Vec<_>) = columns
+            .into_iter()
+            .map(|(name, col_type, value)| {
+                (
+                    ColSpec {
+                        table_spec: None,
+                        name: name.into(),
+                        col_type,
+                    },
+                    value,
+                )
+            })
+            .unzip();
+
+        let metadata = RowsMetadata {
+            flags: RowsMetadataFlags::empty(),
+            columns_count: col_specs.len() as i32,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: None,
+            col_specs,
+        };
+
+        Row::from_body(BodyResResultRows {
+            metadata,
+            rows_count: 1,
+            rows_content: vec![values],
+            protocol_version: Version::V4,
+        })
+        .remove(0)
+    }
+
+    fn encode_map(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = (entries.len() as i32).to_be_bytes().to_vec();
+        for value in entries.iter().flat_map(|(key, value)| [key, value]) {
+            bytes.extend_from_slice(&(value.len() as i32).to_be_bytes());
+            bytes.extend_from_slice(value.as_bytes());
+        }
+
+        bytes
+    }
+
+    fn create_session_row(duration: Option<i32>) -> Row {
+        let parameters_type = ColTypeOption {
+            id: ColType::Map,
+            value: Some(ColTypeOptionValue::CMap(
+                Box::new(col_type(ColType::Varchar)),
+                Box::new(col_type(ColType::Varchar)),
+            )),
+        };
+
+        create_row(vec![
+            (
+                "coordinator",
+                col_type(ColType::Inet),
+                CBytes::new(vec![127, 0, 0, 1]),
+            ),
+            ("client", col_type(ColType::Inet), CBytes::new_null()),
+            (
+                "duration",
+                col_type(ColType::Int),
+                duration
+                    .map(|duration| CBytes::new(duration.to_be_bytes().to_vec()))
+                    .unwrap_or_else(CBytes::new_null),
+            ),
+            (
+                "parameters",
+                parameters_type,
+                CBytes::new(encode_map(&[("query", "SELECT 1")])),
+            ),
+            (
+                "request",
+                col_type(ColType::Varchar),
+                CBytes::new(b"Execute CQL3 query".to_vec()),
+            ),
+        ])
+    }
+
+    fn create_event_row(event_id: Uuid, activity: &str, source_elapsed: i32) -> Row {
+        create_row(vec![
+            (
+                "event_id",
+                col_type(ColType::Timeuuid),
+                CBytes::new(event_id.as_bytes().to_vec()),
+            ),
+            (
+                "activity",
+                col_type(ColType::Varchar),
+                CBytes::new(activity.as_bytes().to_vec()),
+            ),
+            (
+                "source",
+                col_type(ColType::Inet),
+                CBytes::new(vec![127, 0, 0, 2]),
+            ),
+            (
+                "source_elapsed",
+                col_type(ColType::Int),
+                CBytes::new(source_elapsed.to_be_bytes().to_vec()),
+            ),
+            (
+                "thread",
+                col_type(ColType::Varchar),
+                CBytes::new(b"Native-Transport-Requests-1".to_vec()),
+            ),
+        ])
+    }
+
+    #[test]
+    fn should_create_trace_from_rows() {
+        let tracing_id = Uuid::new_v4();
+        let first_event_id = Uuid::new_v4();
+        let second_event_id = Uuid::new_v4();
+
+        let trace = QueryTrace::try_from_rows(
+            tracing_id,
+            &create_session_row(Some(1500)),
+            &[
+                create_event_row(first_event_id, "Parsing", 10),
+                create_event_row(second_event_id, "Executing", 20),
+            ],
+        )
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(trace.tracing_id, tracing_id);
+        assert_eq!(trace.request, "Execute CQL3 query");
+        assert_eq!(trace.coordinator, IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(trace.client, None);
+        assert_eq!(trace.duration, Duration::from_micros(1500));
+        assert_eq!(trace.parameters.get("query").unwrap(), "SELECT 1");
+
+        assert_eq!(trace.events.len(), 2);
+        assert_eq!(trace.events[0].event_id, first_event_id);
+        assert_eq!(trace.events[0].activity, "Parsing");
+        assert_eq!(
+            trace.events[0].source,
+            IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2))
+        );
+        assert_eq!(trace.events[0].source_elapsed, Duration::from_micros(10));
+        assert_eq!(trace.events[0].thread, "Native-Transport-Requests-1");
+        assert_eq!(trace.events[1].event_id, second_event_id);
+    }
+
+    #[test]
+    fn should_not_create_incomplete_trace() {
+        assert!(
+            QueryTrace::try_from_rows(Uuid::new_v4(), &create_session_row(None), &[])
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,299 @@
+use cassandra_protocol::error;
+use cassandra_protocol::frame::message_result::RowsMetadataFlags;
+use cassandra_protocol::frame::{Envelope, TryFromRow};
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::CBytes;
+use futures::task::{Context, Poll};
+use futures::Stream;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::vec::IntoIter;
+use tokio::select;
+use tokio::sync::mpsc;
+
+use crate::future::BoxFuture;
+use crate::statement::StatementParams;
+
+/// Default number of pages fetched ahead of the page being consumed.
+pub const DEFAULT_PREFETCH_DEPTH: usize = 1;
+
+type PageFetcher =
+    Box<dyn FnMut(StatementParams) -> BoxFuture<'static, error::Result<Page>> + Send>;
+
+/// Single page of rows along with paging state for the next one, if there's more to fetch.
+pub(crate) struct Page {
+    rows: Vec<Row>,
+    paging_state: Option<CBytes>,
+}
+
+impl Page {
+    pub(crate) fn from_envelope(envelope: Envelope) -> error::Result<Self> {
+        let body = envelope.response_body()?;
+
+        let paging_state = body
+            .as_rows_metadata()
+            .ok_or("Paged query should yield a vector of rows")
+            .map(|metadata| {
+                if metadata.flags.contains(RowsMetadataFlags::HAS_MORE_PAGES) {
+                    metadata.paging_state.clone()
+                } else {
+                    None
+                }
+            })?;
+
+        let rows = body
+            .into_rows()
+            .ok_or("Paged query should yield a vector of rows")?;
+
+        Ok(Page { rows, paging_state })
+    }
+}
+
+/// Stream of rows resulting from a paged statement. Pages are fetched by a background task, which
+/// stays up to the prefetch depth pages ahead of the page being consumed. The stream doesn't
+/// borrow the session, so it can be moved into spawned tasks. Dropping the stream stops fetching.
+///
+/// The stream ends after the first error.
+pub struct RowStream {
+    fetcher: Option<(PageFetcher, StatementParams)>,
+    prefetch_depth: usize,
+    receiver: Option<mpsc::Receiver<error::Result<Vec<Row>>>>,
+    rows: IntoIter<Row>,
+}
+
+impl RowStream {
+    pub(crate) fn new(
+        parameters: StatementParams,
+        fetcher: impl FnMut(StatementParams) -> BoxFuture<'static, error::Result<Page>> + Send + 'static,
+    ) -> Self {
+        RowStream {
+            fetcher: Some((Box::new(fetcher), parameters)),
+            prefetch_depth: DEFAULT_PREFETCH_DEPTH,
+            receiver: None,
+            rows: Vec::new().into_iter(),
+        }
+    }
+
+    /// Sets the number of pages fetched ahead of the page being consumed. The minimum is 1. Has no
+    /// effect after the stream has been polled.
+    #[must_use]
+    pub fn with_prefetch_depth(mut self, prefetch_depth: usize) -> Self {
+        self.prefetch_depth = prefetch_depth.max(1);
+        self
+    }
+
+    /// Converts this stream into a stream of typed values.
+    pub fn into_typed<R: TryFromRow>(self) -> TypedRowStream<R> {
+        TypedRowStream {
+            inner: self,
+            _row: Default::default(),
+        }
+    }
+
+    fn start_fetching(
+        &mut self,
+        (fetcher, parameters): (PageFetcher, StatementParams),
+    ) -> mpsc::Receiver<error::Result<Vec<Row>>> {
+        let (sender, receiver) = mpsc::channel(self.prefetch_depth);
+        tokio::spawn(fetch_pages(fetcher, parameters, sender));
+        receiver
+    }
+}
+
+impl Stream for RowStream {
+    type Item = error::Result<Row>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Poll::Ready(Some(Ok(row)));
+            }
+
+            if self.receiver.is_none() {
+                match self.fetcher.take() {
+                    Some(fetcher) => self.receiver = Some(self.start_fetching(fetcher)),
+                    None => return Poll::Ready(None),
+                }
+            }
+
+            let page = match self.receiver.as_mut() {
+                Some(receiver) => match receiver.poll_recv(cx) {
+                    Poll::Ready(page) => page,
+                    Poll::Pending => return Poll::Pending,
+                },
+                None => return Poll::Ready(None),
+            };
+
+            match page {
+                Some(Ok(rows)) => self.rows = rows.into_iter(),
+                Some(Err(error)) => {
+                    self.receiver = None;
+                    return Poll::Ready(Some(Err(error)));
+                }
+                None => {
+                    self.receiver = None;
+                    return Poll::Ready(None);
+                }
+            }
+        }
+    }
+}
+
+/// Stream of rows converted to given type. See [`RowStream`].
+pub struct TypedRowStream<R: TryFromRow> {
+    inner: RowStream,
+    _row: PhantomData<fn() -> R>,
+}
+
+impl<R: TryFromRow> Stream for TypedRowStream<R> {
+    type Item = error::Result<R>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(cx)
+            .map(|row| row.map(|row| row.and_then(R::try_from_row)))
+    }
+}
+
+async fn fetch_pages(
+    mut fetcher: PageFetcher,
+    mut parameters: StatementParams,
+    sender: mpsc::Sender<error::Result<Vec<Row>>>,
+) {
+    loop {
+        let page = select! {
+            page = fetcher(parameters.clone()) => page,
+            // the stream has been dropped
+            _ = sender.closed() => break,
+        };
+
+        match page {
+            Ok(Page { rows, paging_state }) => {
+                if sender.send(Ok(rows)).await.is_err() {
+                    break;
+                }
+
+                match paging_state {
+                    Some(paging_state) => parameters.query_params.paging_state = Some(paging_state),
+                    None => break,
+                }
+            }
+            Err(error) => {
+                let _ = sender.send(Err(error)).await;
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error;
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultRows, ColSpec, ColType, ColTypeOption, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{TryFromRow, Version};
+    use cassandra_protocol::types::rows::Row;
+    use cassandra_protocol::types::{CBytes, IntoRustByName};
+    use futures::{FutureExt, StreamExt, TryStreamExt};
+    use std::convert::TryInto;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use crate::cluster::row_stream::{Page, RowStream};
+    use crate::statement::StatementParams;
+
+    struct Id(i32);
+
+    impl TryFromRow for Id {
+        fn try_from_row(row: Row) -> error::Result<Self> {
+            row.get_r_by_name("id").map(Id)
+        }
+    }
+
+    fn create_page(ids: &[i32], paging_state: Option<CBytes>) -> Page {
+        let metadata = RowsMetadata {
+            flags: RowsMetadataFlags::empty(),
+            columns_count: 1,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: None,
+            col_specs: vec![ColSpec {
+                table_spec: None,
+                name: "id".into(),
+                col_type: ColTypeOption {
+                    id: ColType::Int,
+                    value: None,
+                },
+            }],
+        };
+
+        let rows = Row::from_body(BodyResResultRows {
+            metadata,
+            rows_count: ids.len() as i32,
+            rows_content: ids
+                .iter()
+                .map(|id| vec![CBytes::new(id.to_be_bytes().to_vec())])
+                .collect(),
+            protocol_version: Version::V4,
+        });
+
+        Page { rows, paging_state }
+    }
+
+    // returns pages of two consecutive ids, using the first id of the page as paging state
+    fn create_stream(page_count: i32, fetch_count: Arc<AtomicUsize>) -> RowStream {
+        RowStream::new(StatementParams::default(), move |parameters| {
+            fetch_count.fetch_add(1, Ordering::Relaxed);
+
+            let first_id = parameters
+                .query_params
+                .paging_state
+                .and_then(|paging_state| paging_state.into_bytes())
+                .map(|bytes| i32::from_be_bytes(bytes.try_into().unwrap()))
+                .unwrap_or(0);
+
+            let next_id = first_id + 2;
+            let paging_state = if next_id < page_count * 2 {
+                Some(CBytes::new(next_id.to_be_bytes().to_vec()))
+            } else {
+                None
+            };
+
+            async move { Ok(create_page(&[first_id, first_id + 1], paging_state)) }.boxed()
+        })
+    }
+
+    #[tokio::test]
+    async fn should_stream_all_pages() {
+        let fetch_count = Arc::new(AtomicUsize::new(0));
+        let ids: Vec<i32> = create_stream(3, fetch_count.clone())
+            .into_typed::<Id>()
+            .map_ok(|id| id.0)
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(ids, vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(fetch_count.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn should_stream_from_spawned_task() {
+        let stream = create_stream(2, Default::default()).with_prefetch_depth(2);
+        let count = tokio::spawn(stream.count()).await.unwrap();
+
+        assert_eq!(count, 4);
+    }
+
+    #[tokio::test]
+    async fn should_end_stream_after_error() {
+        let stream = RowStream::new(StatementParams::default(), |_| {
+            async { Err("test".into()) }.boxed()
+        });
+
+        let results: Vec<_> = stream.collect().await;
+        assert_eq!(results.len(), 1);
+        assert!(results[0].is_err());
+    }
+}
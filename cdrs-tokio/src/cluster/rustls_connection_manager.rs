@@ -4,6 +4,7 @@ use crate::cluster::HttpProxyConfig;
 use crate::cluster::KeyspaceHolder;
 use crate::frame_encoding::FrameEncodingFactory;
 use crate::future::BoxFuture;
+use crate::metrics::MetricsRecorder;
 use crate::transport::TransportRustls;
 #[cfg(feature = "http-proxy")]
 use async_http_proxy::{http_connect_tokio, http_connect_tokio_with_basic_auth};
@@ -31,6 +32,8 @@ pub struct RustlsConnectionManager {
     frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
     compression: Compression,
     buffer_size: usize,
+    max_in_flight_requests: usize,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     tcp_nodelay: bool,
     version: Version,
     #[cfg(feature = "http-proxy")]
@@ -60,6 +63,8 @@ impl RustlsConnectionManager {
         frame_encoder_factory: Box<dyn FrameEncodingFactory + Send + Sync>,
         compression: Compression,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
         tcp_nodelay: bool,
         version: Version,
         #[cfg(feature = "http-proxy")] http_proxy: Option<HttpProxyConfig>,
@@ -72,6 +77,8 @@ impl RustlsConnectionManager {
             frame_encoder_factory,
             compression,
             buffer_size,
+            max_in_flight_requests,
+            metrics_recorder,
             tcp_nodelay,
             version,
             #[cfg(feature = "http-proxy")]
@@ -79,6 +86,12 @@ impl RustlsConnectionManager {
         }
     }
 
+    // returns the same manager establishing connections with given protocol version
+    pub(crate) fn with_version(mut self, version: Version) -> Self {
+        self.version = version;
+        self
+    }
+
     //noinspection DuplicatedCode
     #[cfg(feature = "http-proxy")]
     async fn create_transport(
@@ -121,6 +134,8 @@ impl RustlsConnectionManager {
                 self.frame_encoder_factory
                     .create_decoder(self.version, self.compression),
                 self.buffer_size,
+                self.max_in_flight_requests,
+                self.metrics_recorder.clone(),
             )
             .await
         } else {
@@ -137,6 +152,8 @@ impl RustlsConnectionManager {
                 self.frame_encoder_factory
                     .create_decoder(self.version, self.compression),
                 self.buffer_size,
+                self.max_in_flight_requests,
+                self.metrics_recorder.clone(),
                 self.tcp_nodelay,
             )
             .await
@@ -164,6 +181,8 @@ impl RustlsConnectionManager {
             self.frame_encoder_factory
                 .create_decoder(self.version, self.compression),
             self.buffer_size,
+            self.max_in_flight_requests,
+            self.metrics_recorder.clone(),
             self.tcp_nodelay,
         )
         .await
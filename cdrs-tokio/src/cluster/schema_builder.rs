@@ -0,0 +1,830 @@
+use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::message_result::ColType;
+use cassandra_protocol::types::rows::Row;
+use cassandra_protocol::types::IntoRustByName;
+use fxhash::FxHashMap;
+use itertools::Itertools;
+use serde_json::{Map, Value as JsonValue};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use tracing::*;
+use uuid::Uuid;
+
+use crate::cluster::topology::{
+    AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FieldMetadata,
+    FunctionMetadata, IndexKind, IndexMetadata, KeyspaceMetadata, ReplicationStrategy,
+    TableMetadata, UserTypeMetadata, ViewMetadata,
+};
+
+/// A row of a `system_schema` table, selected as JSON.
+pub(crate) type JsonRow = Map<String, JsonValue>;
+
+// columns of system_schema.tables and system_schema.views, which are not table options
+const TABLE_NON_OPTIONS: &[&str] = &["keyspace_name", "table_name", "id", "flags", "extensions"];
+const VIEW_NON_OPTIONS: &[&str] = &[
+    "keyspace_name",
+    "view_name",
+    "id",
+    "base_table_id",
+    "base_table_name",
+    "include_all_columns",
+    "where_clause",
+    "extensions",
+];
+
+/// Rows of `system_schema` tables describing keyspaces and their elements.
+#[derive(Default, Debug)]
+pub(crate) struct SchemaRows {
+    pub(crate) keyspaces: Vec<JsonRow>,
+    pub(crate) tables: Vec<JsonRow>,
+    pub(crate) columns: Vec<JsonRow>,
+    pub(crate) views: Vec<JsonRow>,
+    pub(crate) indexes: Vec<JsonRow>,
+    pub(crate) types: Vec<JsonRow>,
+    pub(crate) functions: Vec<JsonRow>,
+    pub(crate) aggregates: Vec<JsonRow>,
+}
+
+pub(crate) fn build_json_row(row: &Row) -> Result<JsonRow> {
+    let json: String = row.get_r_by_name("[json]")?;
+    match serde_json::from_str(&json) {
+        Ok(JsonValue::Object(row)) => Ok(row),
+        Ok(_) => Err("Invalid schema row format!".into()),
+        Err(error) => Err(format!("Error parsing schema row: {error}").into()),
+    }
+}
+
+/// Builds keyspaces along with all their elements. Keyspaces and elements which cannot be built
+/// are logged and skipped.
+pub(crate) fn build_keyspaces(mut rows: SchemaRows) -> FxHashMap<String, KeyspaceMetadata> {
+    let mut keyspaces = rows
+        .keyspaces
+        .iter()
+        .filter_map(|row| {
+            build_keyspace(row)
+                .map_err(|error| {
+                    warn!(%error, "Skipping invalid keyspace metadata.");
+                })
+                .ok()
+        })
+        .collect();
+
+    rows.keyspaces.clear();
+    add_schema_elements(&mut keyspaces, rows);
+
+    keyspaces
+}
+
+/// Adds tables, views, user types, functions and aggregates to given keyspaces, replacing the ones
+/// with the same names. Elements of unknown keyspaces are ignored, as are keyspace rows. Elements
+/// which cannot be built are logged and skipped, so a single unexpected row doesn't prevent
+/// refreshing the rest of the schema.
+pub(crate) fn add_schema_elements(
+    keyspaces: &mut FxHashMap<String, KeyspaceMetadata>,
+    rows: SchemaRows,
+) {
+    let mut columns = build_columns(&rows.columns);
+    let mut indexes = build_indexes(&rows.indexes);
+
+    for row in &rows.tables {
+        let key = match element_key(row, "table_name") {
+            Ok(key) => key,
+            Err(error) => {
+                warn!(%error, "Skipping invalid table metadata.");
+                continue;
+            }
+        };
+
+        if let Some(keyspace) = keyspaces.get_mut(&key.0) {
+            let table = TableMetadata {
+                keyspace: key.0.clone(),
+                name: key.1.clone(),
+                id: get_uuid(row, "id"),
+                columns: columns.remove(&key).unwrap_or_default(),
+                options: build_options(row, TABLE_NON_OPTIONS),
+                indexes: indexes.remove(&key).unwrap_or_default(),
+            };
+
+            keyspace.tables.insert(key.1, table);
+        }
+    }
+
+    for row in &rows.views {
+        let key = match element_key(row, "view_name") {
+            Ok(key) => key,
+            Err(error) => {
+                warn!(%error, "Skipping invalid view metadata.");
+                continue;
+            }
+        };
+
+        if let Some(keyspace) = keyspaces.get_mut(&key.0) {
+            match build_view(row, &key, &mut columns) {
+                Ok(view) => {
+                    keyspace.views.insert(key.1, view);
+                }
+                Err(error) => {
+                    warn!(%error, keyspace = %key.0, view = %key.1, "Skipping invalid view metadata.");
+                }
+            }
+        }
+    }
+
+    for row in &rows.types {
+        if let Some(keyspace) = element_keyspace(keyspaces, row) {
+            match build_user_type(row) {
+                Ok(user_type) => {
+                    keyspace
+                        .user_types
+                        .insert(user_type.name.clone(), user_type);
+                }
+                Err(error) => {
+                    warn!(%error, keyspace = %keyspace.name, "Skipping invalid user type metadata.");
+                }
+            }
+        }
+    }
+
+    for row in &rows.functions {
+        if let Some(keyspace) = element_keyspace(keyspaces, row) {
+            match build_function(row) {
+                Ok(function) => {
+                    keyspace.functions.retain(|other| {
+                        other.name != function.name
+                            || other.argument_types != function.argument_types
+                    });
+                    keyspace.functions.push(function);
+                }
+                Err(error) => {
+                    warn!(%error, keyspace = %keyspace.name, "Skipping invalid function metadata.");
+                }
+            }
+        }
+    }
+
+    for row in &rows.aggregates {
+        if let Some(keyspace) = element_keyspace(keyspaces, row) {
+            match build_aggregate(row) {
+                Ok(aggregate) => {
+                    keyspace.aggregates.retain(|other| {
+                        other.name != aggregate.name
+                            || other.argument_types != aggregate.argument_types
+                    });
+                    keyspace.aggregates.push(aggregate);
+                }
+                Err(error) => {
+                    warn!(%error, keyspace = %keyspace.name, "Skipping invalid aggregate metadata.");
+                }
+            }
+        }
+    }
+}
+
+// returns the keyspace and name of a schema element
+fn element_key(row: &JsonRow, name_column: &str) -> Result<(String, String)> {
+    Ok((
+        get_string(row, "keyspace_name")?,
+        get_string(row, name_column)?,
+    ))
+}
+
+// returns the keyspace given element belongs to, if known
+fn element_keyspace<'a>(
+    keyspaces: &'a mut FxHashMap<String, KeyspaceMetadata>,
+    row: &JsonRow,
+) -> Option<&'a mut KeyspaceMetadata> {
+    match get_str(row, "keyspace_name") {
+        Ok(keyspace) => keyspaces.get_mut(keyspace),
+        Err(error) => {
+            warn!(%error, "Skipping schema element without keyspace.");
+            None
+        }
+    }
+}
+
+fn build_keyspace(row: &JsonRow) -> Result<(String, KeyspaceMetadata)> {
+    let keyspace_name = get_string(row, "keyspace_name")?;
+
+    let (replication_strategy, replication) = match row.get("replication") {
+        Some(JsonValue::Object(properties)) => (
+            build_replication_strategy(properties.clone())?,
+            properties
+                .iter()
+                .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+                .collect(),
+        ),
+        _ => {
+            return Err(Error::InvalidReplicationFormat {
+                keyspace: keyspace_name,
+            })
+        }
+    };
+
+    let mut keyspace = KeyspaceMetadata::new(keyspace_name.clone(), replication_strategy);
+    keyspace.replication = replication;
+    keyspace.durable_writes = row
+        .get("durable_writes")
+        .and_then(JsonValue::as_bool)
+        .unwrap_or(true);
+
+    Ok((keyspace_name, keyspace))
+}
+
+fn build_replication_strategy(
+    mut properties: Map<String, JsonValue>,
+) -> Result<ReplicationStrategy> {
+    match properties.remove("class") {
+        Some(JsonValue::String(class)) => Ok(match class.as_str() {
+            "org.apache.cassandra.locator.SimpleStrategy" | "SimpleStrategy" => {
+                ReplicationStrategy::SimpleStrategy {
+                    replication_factor: extract_replication_factor(
+                        properties.get("replication_factor"),
+                    )?,
+                }
+            }
+            "org.apache.cassandra.locator.NetworkTopologyStrategy" | "NetworkTopologyStrategy" => {
+                ReplicationStrategy::NetworkTopologyStrategy {
+                    datacenter_replication_factor: extract_datacenter_replication_factor(
+                        properties,
+                    )?,
+                }
+            }
+            _ => ReplicationStrategy::Other,
+        }),
+        _ => Err("Missing replication strategy class!".into()),
+    }
+}
+
+fn extract_datacenter_replication_factor(
+    properties: Map<String, JsonValue>,
+) -> Result<FxHashMap<String, usize>> {
+    properties
+        .into_iter()
+        .map(|(key, replication_factor)| {
+            extract_replication_factor(Some(&replication_factor))
+                .map(move |replication_factor| (key, replication_factor))
+        })
+        .try_collect()
+}
+
+fn extract_replication_factor(value: Option<&JsonValue>) -> Result<usize> {
+    match value {
+        Some(JsonValue::String(replication_factor)) => {
+            let result = if let Some(slash) = replication_factor.find('/') {
+                usize::from_str(&replication_factor[..slash])
+            } else {
+                usize::from_str(replication_factor)
+            };
+
+            result.map_err(|error| {
+                format!("Failed to parse ('{replication_factor}'): {error}").into()
+            })
+        }
+        _ => Err("Missing replication factor!".into()),
+    }
+}
+
+// groups columns by keyspace and table, in the order of TableMetadata::columns; invalid columns
+// are skipped
+fn build_columns(rows: &[JsonRow]) -> FxHashMap<(String, String), Vec<ColumnMetadata>> {
+    let mut columns: FxHashMap<(String, String), Vec<ColumnMetadata>> = Default::default();
+    for row in rows {
+        match element_key(row, "table_name").and_then(|key| Ok((key, build_column(row)?))) {
+            Ok((key, column)) => columns.entry(key).or_default().push(column),
+            Err(error) => warn!(%error, "Skipping invalid column metadata."),
+        }
+    }
+
+    for columns in columns.values_mut() {
+        columns.sort_by(|a, b| {
+            let order = |column: &ColumnMetadata| match column.kind {
+                ColumnKind::PartitionKey => 0,
+                ColumnKind::Clustering => 1,
+                _ => 2,
+            };
+
+            order(a)
+                .cmp(&order(b))
+                .then(a.position.cmp(&b.position))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+
+    columns
+}
+
+fn build_column(row: &JsonRow) -> Result<ColumnMetadata> {
+    let kind = match get_str(row, "kind")? {
+        "partition_key" => ColumnKind::PartitionKey,
+        "clustering" => ColumnKind::Clustering,
+        "static" => ColumnKind::Static,
+        _ => ColumnKind::Regular,
+    };
+
+    let clustering_order = match row.get("clustering_order").and_then(JsonValue::as_str) {
+        Some("asc") => ClusteringOrder::Asc,
+        Some("desc") => ClusteringOrder::Desc,
+        _ => ClusteringOrder::None,
+    };
+
+    let cql_type = get_string(row, "type")?;
+
+    Ok(ColumnMetadata {
+        name: get_string(row, "column_name")?,
+        kind,
+        position: row
+            .get("position")
+            .and_then(JsonValue::as_i64)
+            .unwrap_or(-1) as i32,
+        clustering_order,
+        col_type: parse_col_type(&cql_type),
+        cql_type,
+    })
+}
+
+// groups indexes by keyspace and table; invalid indexes are skipped
+fn build_indexes(
+    rows: &[JsonRow],
+) -> FxHashMap<(String, String), FxHashMap<String, IndexMetadata>> {
+    let mut indexes: FxHashMap<(String, String), FxHashMap<String, IndexMetadata>> =
+        Default::default();
+    for row in rows {
+        match element_key(row, "table_name").and_then(|key| Ok((build_index(row, &key)?, key))) {
+            Ok((index, key)) => {
+                indexes
+                    .entry(key)
+                    .or_default()
+                    .insert(index.name.clone(), index);
+            }
+            Err(error) => warn!(%error, "Skipping invalid index metadata."),
+        }
+    }
+
+    indexes
+}
+
+fn build_index(row: &JsonRow, key: &(String, String)) -> Result<IndexMetadata> {
+    let options: BTreeMap<String, String> = match row.get("options") {
+        Some(JsonValue::Object(options)) => options
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), value.as_str()?.to_string())))
+            .collect(),
+        _ => Default::default(),
+    };
+
+    let kind = match get_str(row, "kind")? {
+        "KEYS" => IndexKind::Keys,
+        "CUSTOM" => IndexKind::Custom,
+        _ => IndexKind::Composites,
+    };
+
+    Ok(IndexMetadata {
+        keyspace: key.0.clone(),
+        table: key.1.clone(),
+        name: get_string(row, "index_name")?,
+        kind,
+        target: options.get("target").cloned().unwrap_or_default(),
+        class_name: options.get("class_name").cloned(),
+        options,
+    })
+}
+
+fn build_view(
+    row: &JsonRow,
+    key: &(String, String),
+    columns: &mut FxHashMap<(String, String), Vec<ColumnMetadata>>,
+) -> Result<ViewMetadata> {
+    Ok(ViewMetadata {
+        keyspace: key.0.clone(),
+        name: key.1.clone(),
+        id: get_uuid(row, "id"),
+        base_table: get_string(row, "base_table_name")?,
+        include_all_columns: get_bool(row, "include_all_columns"),
+        where_clause: get_string(row, "where_clause")?,
+        columns: columns.remove(key).unwrap_or_default(),
+        options: build_options(row, VIEW_NON_OPTIONS),
+    })
+}
+
+fn build_user_type(row: &JsonRow) -> Result<UserTypeMetadata> {
+    let fields = get_string_list(row, "field_names")
+        .into_iter()
+        .zip(get_string_list(row, "field_types"))
+        .map(|(name, cql_type)| FieldMetadata {
+            name,
+            col_type: parse_col_type(&cql_type),
+            cql_type,
+        })
+        .collect();
+
+    Ok(UserTypeMetadata {
+        keyspace: get_string(row, "keyspace_name")?,
+        name: get_string(row, "type_name")?,
+        fields,
+    })
+}
+
+fn build_function(row: &JsonRow) -> Result<FunctionMetadata> {
+    Ok(FunctionMetadata {
+        keyspace: get_string(row, "keyspace_name")?,
+        name: get_string(row, "function_name")?,
+        argument_names: get_string_list(row, "argument_names"),
+        argument_types: get_string_list(row, "argument_types"),
+        return_type: get_string(row, "return_type")?,
+        language: get_string(row, "language")?,
+        body: get_string(row, "body")?,
+        called_on_null_input: get_bool(row, "called_on_null_input"),
+    })
+}
+
+fn build_aggregate(row: &JsonRow) -> Result<AggregateMetadata> {
+    Ok(AggregateMetadata {
+        keyspace: get_string(row, "keyspace_name")?,
+        name: get_string(row, "aggregate_name")?,
+        argument_types: get_string_list(row, "argument_types"),
+        state_func: get_string(row, "state_func")?,
+        state_type: get_string(row, "state_type")?,
+        final_func: get_optional_string(row, "final_func"),
+        initcond: get_optional_string(row, "initcond"),
+        return_type: get_string(row, "return_type")?,
+    })
+}
+
+fn build_options(row: &JsonRow, non_options: &[&str]) -> BTreeMap<String, String> {
+    row.iter()
+        .filter(|(name, value)| !value.is_null() && !non_options.contains(&name.as_str()))
+        .map(|(name, value)| (name.clone(), build_cql_literal(value)))
+        .collect()
+}
+
+fn build_cql_literal(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(value) => format!("'{}'", value.replace('\'', "''")),
+        JsonValue::Array(values) => {
+            format!("[{}]", values.iter().map(build_cql_literal).join(", "))
+        }
+        JsonValue::Object(values) => format!(
+            "{{{}}}",
+            values
+                .iter()
+                .map(|(key, value)| format!(
+                    "'{}': {}",
+                    key.replace('\'', "''"),
+                    build_cql_literal(value)
+                ))
+                .join(", ")
+        ),
+        value => value.to_string(),
+    }
+}
+
+fn parse_col_type(cql_type: &str) -> ColType {
+    let cql_type = cql_type.trim();
+
+    // custom types are represented by quoted class names
+    if cql_type.starts_with('\'') {
+        return ColType::Custom;
+    }
+
+    let (name, parameters) = match cql_type.find('<') {
+        Some(start) => (
+            &cql_type[..start],
+            cql_type[start + 1..].strip_suffix('>').unwrap_or_default(),
+        ),
+        None => (cql_type, ""),
+    };
+
+    match name.trim().to_lowercase().as_str() {
+        "frozen" => parse_col_type(parameters),
+        "ascii" => ColType::Ascii,
+        "bigint" => ColType::Bigint,
+        "blob" => ColType::Blob,
+        "boolean" => ColType::Boolean,
+        "counter" => ColType::Counter,
+        "decimal" => ColType::Decimal,
+        "double" => ColType::Double,
+        "float" => ColType::Float,
+        "int" => ColType::Int,
+        "timestamp" => ColType::Timestamp,
+        "uuid" => ColType::Uuid,
+        "text" | "varchar" => ColType::Varchar,
+        "varint" => ColType::Varint,
+        "timeuuid" => ColType::Timeuuid,
+        "inet" => ColType::Inet,
+        "date" => ColType::Date,
+        "time" => ColType::Time,
+        "smallint" => ColType::Smallint,
+        "tinyint" => ColType::Tinyint,
+        "duration" => ColType::Duration,
+        "list" => ColType::List,
+        "map" => ColType::Map,
+        "set" => ColType::Set,
+        "tuple" => ColType::Tuple,
+        "vector" => ColType::Custom,
+        _ => ColType::Udt,
+    }
+}
+
+#[inline]
+fn get_str<'a>(row: &'a JsonRow, name: &str) -> Result<&'a str> {
+    row.get(name)
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| format!("Missing schema column: {name}!").into())
+}
+
+#[inline]
+fn get_string(row: &JsonRow, name: &str) -> Result<String> {
+    get_str(row, name).map(|value| value.to_string())
+}
+
+#[inline]
+fn get_optional_string(row: &JsonRow, name: &str) -> Option<String> {
+    row.get(name)
+        .and_then(JsonValue::as_str)
+        .map(|value| value.to_string())
+}
+
+#[inline]
+fn get_bool(row: &JsonRow, name: &str) -> bool {
+    row.get(name)
+        .and_then(JsonValue::as_bool)
+        .unwrap_or_default()
+}
+
+#[inline]
+fn get_uuid(row: &JsonRow, name: &str) -> Option<Uuid> {
+    row.get(name)
+        .and_then(JsonValue::as_str)
+        .and_then(|value| Uuid::parse_str(value).ok())
+}
+
+fn get_string_list(row: &JsonRow, name: &str) -> Vec<String> {
+    match row.get(name) {
+        Some(JsonValue::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(|value| value.to_string()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::ColType;
+    use serde_json::json;
+
+    use crate::cluster::schema_builder::{
+        add_schema_elements, build_keyspaces, parse_col_type, JsonRow, SchemaRows,
+    };
+    use crate::cluster::topology::{ClusteringOrder, ColumnKind, IndexKind, ReplicationStrategy};
+
+    fn json_row(value: serde_json::Value) -> JsonRow {
+        value.as_object().unwrap().clone()
+    }
+
+    fn keyspace_rows() -> Vec<JsonRow> {
+        vec![json_row(json!({
+            "keyspace_name": "ks",
+            "durable_writes": false,
+            "replication": {
+                "class": "org.apache.cassandra.locator.SimpleStrategy",
+                "replication_factor": "3"
+            }
+        }))]
+    }
+
+    fn column_row(table: &str, name: &str, kind: &str, position: i32, ty: &str) -> JsonRow {
+        json_row(json!({
+            "keyspace_name": "ks",
+            "table_name": table,
+            "column_name": name,
+            "clustering_order": if kind == "clustering" { "desc" } else { "none" },
+            "column_name_bytes": "0x00",
+            "kind": kind,
+            "position": position,
+            "type": ty
+        }))
+    }
+
+    #[test]
+    fn should_build_tables() {
+        let keyspaces = build_keyspaces(SchemaRows {
+            keyspaces: keyspace_rows(),
+            tables: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "table_name": "users",
+                "id": "5a1c395e-b41f-11e5-9f22-ba0be0483c18",
+                "comment": "it's a table",
+                "gc_grace_seconds": 864000,
+                "caching": {"keys": "ALL", "rows_per_partition": "NONE"},
+                "flags": ["compound"]
+            }))],
+            columns: vec![
+                column_row("users", "name", "regular", -1, "text"),
+                column_row("users", "created", "clustering", 0, "timestamp"),
+                column_row("users", "id", "partition_key", 0, "uuid"),
+                column_row("users", "address", "regular", -1, "frozen<address>"),
+                column_row("users", "group", "partition_key", 1, "int"),
+            ],
+            indexes: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "table_name": "users",
+                "index_name": "users_name_idx",
+                "kind": "COMPOSITES",
+                "options": {"target": "name"}
+            }))],
+            ..Default::default()
+        });
+
+        let keyspace = keyspaces.get("ks").unwrap();
+        assert!(!keyspace.durable_writes);
+        assert_eq!(
+            keyspace.replication_strategy,
+            ReplicationStrategy::SimpleStrategy {
+                replication_factor: 3
+            }
+        );
+
+        let table = keyspace.table("users").unwrap();
+        assert!(table.id.is_some());
+        assert_eq!(
+            table
+                .columns
+                .iter()
+                .map(|column| &column.name)
+                .collect::<Vec<_>>(),
+            vec!["id", "group", "created", "address", "name"]
+        );
+        assert_eq!(
+            table
+                .partition_key()
+                .map(|column| &column.name)
+                .collect::<Vec<_>>(),
+            vec!["id", "group"]
+        );
+
+        let created = table.clustering_key().next().unwrap();
+        assert_eq!(created.kind, ColumnKind::Clustering);
+        assert_eq!(created.clustering_order, ClusteringOrder::Desc);
+        assert_eq!(created.col_type, ColType::Timestamp);
+        assert_eq!(table.column("address").unwrap().col_type, ColType::Udt);
+
+        assert_eq!(table.options.get("comment").unwrap(), "'it''s a table'");
+        assert_eq!(table.options.get("gc_grace_seconds").unwrap(), "864000");
+        assert_eq!(
+            table.options.get("caching").unwrap(),
+            "{'keys': 'ALL', 'rows_per_partition': 'NONE'}"
+        );
+        assert!(!table.options.contains_key("flags"));
+
+        let index = table.indexes.get("users_name_idx").unwrap();
+        assert_eq!(index.kind, IndexKind::Composites);
+        assert_eq!(index.target, "name");
+    }
+
+    #[test]
+    fn should_build_other_elements() {
+        let keyspaces = build_keyspaces(SchemaRows {
+            keyspaces: keyspace_rows(),
+            views: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "view_name": "users_by_name",
+                "base_table_name": "users",
+                "include_all_columns": true,
+                "where_clause": "name IS NOT NULL",
+                "comment": ""
+            }))],
+            columns: vec![column_row(
+                "users_by_name",
+                "name",
+                "partition_key",
+                0,
+                "text",
+            )],
+            types: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "type_name": "address",
+                "field_names": ["street", "tags"],
+                "field_types": ["text", "frozen<set<text>>"]
+            }))],
+            functions: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "function_name": "plus",
+                "argument_names": ["a", "b"],
+                "argument_types": ["int", "int"],
+                "body": "return a + b;",
+                "called_on_null_input": false,
+                "language": "java",
+                "return_type": "int"
+            }))],
+            aggregates: vec![json_row(json!({
+                "keyspace_name": "ks",
+                "aggregate_name": "sum",
+                "argument_types": ["int"],
+                "final_func": null,
+                "initcond": "0",
+                "return_type": "int",
+                "state_func": "plus",
+                "state_type": "int"
+            }))],
+            ..Default::default()
+        });
+
+        let keyspace = keyspaces.get("ks").unwrap();
+
+        let view = keyspace.view("users_by_name").unwrap();
+        assert_eq!(view.base_table, "users");
+        assert!(view.include_all_columns);
+        assert_eq!(view.columns[0].name, "name");
+
+        let user_type = keyspace.user_type("address").unwrap();
+        assert_eq!(user_type.fields[1].name, "tags");
+        assert_eq!(user_type.fields[1].col_type, ColType::Set);
+
+        assert_eq!(keyspace.functions[0].argument_types, vec!["int", "int"]);
+        assert_eq!(keyspace.aggregates[0].final_func, None);
+        assert_eq!(keyspace.aggregates[0].initcond.as_deref(), Some("0"));
+    }
+
+    #[test]
+    fn should_replace_elements() {
+        let mut keyspaces = build_keyspaces(SchemaRows {
+            keyspaces: keyspace_rows(),
+            tables: vec![json_row(json!({"keyspace_name": "ks", "table_name": "t"}))],
+            ..Default::default()
+        });
+
+        add_schema_elements(
+            &mut keyspaces,
+            SchemaRows {
+                tables: vec![
+                    json_row(json!({"keyspace_name": "ks", "table_name": "t", "comment": "new"})),
+                    json_row(json!({"keyspace_name": "other", "table_name": "t"})),
+                ],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(keyspaces.len(), 1);
+
+        let keyspace = keyspaces.get("ks").unwrap();
+        assert_eq!(keyspace.tables.len(), 1);
+        assert_eq!(
+            keyspace.table("t").unwrap().options.get("comment").unwrap(),
+            "'new'"
+        );
+    }
+
+    #[test]
+    fn should_skip_invalid_elements() {
+        let mut keyspace_rows = keyspace_rows();
+        keyspace_rows.push(json_row(json!({"keyspace_name": "broken"})));
+
+        let keyspaces = build_keyspaces(SchemaRows {
+            keyspaces: keyspace_rows,
+            tables: vec![
+                json_row(json!({"keyspace_name": "ks"})),
+                json_row(json!({"keyspace_name": "ks", "table_name": "t"})),
+            ],
+            columns: vec![
+                json_row(json!({"keyspace_name": "ks", "table_name": "t", "kind": "regular"})),
+                column_row("t", "id", "partition_key", 0, "int"),
+            ],
+            views: vec![json_row(json!({"keyspace_name": "ks", "view_name": "v"}))],
+            types: vec![json_row(json!({"type_name": "address"}))],
+            functions: vec![json_row(
+                json!({"keyspace_name": "ks", "function_name": "f"}),
+            )],
+            aggregates: vec![json_row(
+                json!({"keyspace_name": "ks", "aggregate_name": "a"}),
+            )],
+            ..Default::default()
+        });
+
+        assert_eq!(keyspaces.len(), 1);
+
+        let keyspace = keyspaces.get("ks").unwrap();
+        assert_eq!(keyspace.tables.len(), 1);
+
+        let table = keyspace.table("t").unwrap();
+        assert_eq!(table.columns.len(), 1);
+        assert_eq!(table.columns[0].name, "id");
+
+        assert!(keyspace.views.is_empty());
+        assert!(keyspace.user_types.is_empty());
+        assert!(keyspace.functions.is_empty());
+        assert!(keyspace.aggregates.is_empty());
+    }
+
+    #[test]
+    fn should_parse_col_types() {
+        assert_eq!(parse_col_type("text"), ColType::Varchar);
+        assert_eq!(parse_col_type("frozen<list<int>>"), ColType::List);
+        assert_eq!(parse_col_type("map<text, frozen<set<int>>>"), ColType::Map);
+        assert_eq!(parse_col_type("frozen<address>"), ColType::Udt);
+        assert_eq!(
+            parse_col_type("'org.apache.cassandra.db.marshal.BytesType'"),
+            ColType::Custom
+        );
+    }
+}
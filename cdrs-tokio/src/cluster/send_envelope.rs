@@ -1,46 +1,171 @@
+use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error;
-use cassandra_protocol::frame::Envelope;
+use cassandra_protocol::frame::message_request::RequestBody;
+use cassandra_protocol::frame::{Direction, Envelope, Serialize};
+use derivative::Derivative;
+use itertools::Either;
+use std::borrow::Cow;
+use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::time::{sleep, timeout};
+use tracing::*;
 
 use crate::cluster::topology::Node;
-use crate::cluster::ConnectionManager;
+use crate::cluster::{ConnectionManager, QueryResult};
+use crate::interceptor::RequestInterceptor;
+use crate::load_balancing::LoadBalancingStrategy;
+use crate::metrics::MetricsRecorder;
+use crate::request_tracker::{AttemptInfo, RequestAttempts};
 use crate::retry::{QueryInfo, RetryDecision, RetrySession};
 use crate::transport::CdrsTransport;
 
+/// Per-request context of [`send_envelope`]. All fields are optional, so a default context sends
+/// the envelope without a timeout, reporting or interceptors.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Copy(bound = ""), Default(bound = ""))]
+pub struct RequestContext<'a, T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
+    /// Consistency set in the envelope, if any. When the retry session decides to retry with a
+    /// different consistency, the envelope is rebuilt with it for subsequent attempts.
+    pub consistency: Option<Consistency>,
+    /// Limit of each attempt to get a response from a node. An expired attempt results in
+    /// [`Error::Timeout`](error::Error::Timeout), which is passed to the retry session like any
+    /// other error.
+    pub request_timeout: Option<Duration>,
+    /// Strategy which response times of nodes are reported to.
+    pub load_balancing: Option<&'a (dyn LoadBalancingStrategy<T, CM> + Send + Sync)>,
+    /// Recorder of every attempt and retry.
+    pub metrics_recorder: Option<&'a (dyn MetricsRecorder + Send + Sync)>,
+    /// Interceptors called before each attempt, which can replace the response of the node. Such
+    /// attempts don't connect to the node and aren't reported to the strategy and recorder.
+    pub request_interceptors: &'a [Arc<dyn RequestInterceptor + Send + Sync>],
+    /// Collector of every attempt which got a response.
+    pub attempts: Option<&'a RequestAttempts>,
+}
+
 /// Mid-level interface for sending envelopes to the cluster. Uses a query plan to route envelope to
 /// appropriate node, and retry policy for error handling. Returns `None` if no nodes were present
-/// in the query plan.
+/// in the query plan. The result contains the address of the node which sent the response.
+///
+/// Each attempt is wrapped in a `cassandra.attempt` span, containing the coordinator address,
+/// stream id, error and retry decision.
 pub async fn send_envelope<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
     query_plan: impl Iterator<Item = Arc<Node<T, CM>>>,
     envelope: &Envelope,
     is_idempotent: bool,
     mut retry_session: Box<dyn RetrySession + Send + Sync>,
-) -> Option<error::Result<Envelope>> {
+    context: RequestContext<'_, T, CM>,
+) -> Option<error::Result<QueryResult>> {
     let mut result = None;
+    let mut envelope = Cow::Borrowed(envelope);
+    let mut consistency = context.consistency;
+    let mut attempt = 0;
 
     'next_node: for node in query_plan {
         loop {
-            let transport = node.persistent_connection().await;
-            match transport {
-                Ok(transport) => match transport.write_envelope(envelope, false).await {
-                    Ok(envelope) => return Some(Ok(envelope)),
+            // interceptors can respond without a connection to the node
+            let target = match intercept_attempt(
+                context.request_interceptors,
+                &envelope,
+                node.connect_address(),
+                attempt + 1,
+            ) {
+                Some(response) => Either::Right(response),
+                None => match node.persistent_connection().await {
+                    Ok(transport) => Either::Left(transport),
+                    // save the error, but keep trying, since another node might be up
                     Err(error) => {
-                        let query_info = QueryInfo {
-                            error: &error,
-                            is_idempotent,
-                        };
-
-                        match retry_session.decide(query_info) {
-                            RetryDecision::RetrySameNode => continue,
-                            RetryDecision::RetryNextNode => continue 'next_node,
-                            RetryDecision::DontRetry => return Some(Err(error)),
-                        }
+                        result = Some(Err(error));
+                        continue 'next_node;
                     }
                 },
-                // save the error, but keep trying, since another node might be up
+            };
+
+            attempt += 1;
+
+            let attempt_span = info_span!(
+                "cassandra.attempt",
+                db.cassandra.attempt = attempt,
+                db.cassandra.coordinator.address = %node.connect_address(),
+                db.cassandra.coordinator.dc = node.datacenter(),
+                db.cassandra.stream_id = field::Empty,
+                db.cassandra.retry_decision = field::Empty,
+                error = field::Empty,
+            );
+
+            let (response, latency) = match target {
+                Either::Left(transport) => {
+                    write_to_node(&node, transport.as_ref(), &envelope, context)
+                        .instrument(attempt_span.clone())
+                        .await
+                }
+                // the node wasn't involved, so there's nothing to report
+                Either::Right(response) => (response, Duration::ZERO),
+            };
+
+            match response {
+                Ok(envelope) => {
+                    if let Some(attempts) = context.attempts {
+                        attempts.record(AttemptInfo {
+                            node: node.connect_address(),
+                            latency,
+                            error: None,
+                            retry_decision: None,
+                        });
+                    }
+
+                    return Some(Ok(QueryResult::new(envelope, node.broadcast_rpc_address())));
+                }
                 Err(error) => {
-                    result = Some(Err(error));
-                    continue 'next_node;
+                    let query_info = QueryInfo {
+                        error: &error,
+                        is_idempotent,
+                        consistency,
+                        attempt,
+                        node_address: node.broadcast_rpc_address(),
+                    };
+
+                    let decision = retry_session.decide(query_info);
+                    if let Some(metrics_recorder) = context.metrics_recorder {
+                        if decision != RetryDecision::DontRetry {
+                            metrics_recorder.record_retry(node.connect_address());
+                        }
+                    }
+
+                    if let Some(attempts) = context.attempts {
+                        attempts.record(AttemptInfo {
+                            node: node.connect_address(),
+                            latency,
+                            error: Some(error.clone()),
+                            retry_decision: Some(decision),
+                        });
+                    }
+
+                    attempt_span.record("error", field::display(&error));
+                    attempt_span.record("db.cassandra.retry_decision", field::display(decision));
+
+                    match decision {
+                        RetryDecision::RetrySameNode => continue,
+                        RetryDecision::RetryNextNode => continue 'next_node,
+                        RetryDecision::DontRetry => return Some(Err(error)),
+                        RetryDecision::RetryWithConsistency(new_consistency) => {
+                            match with_consistency(&envelope, new_consistency) {
+                                Ok(new_envelope) => {
+                                    envelope = Cow::Owned(new_envelope);
+                                    consistency = Some(new_consistency);
+                                    continue;
+                                }
+                                Err(rebuild_error) => {
+                                    warn!(%rebuild_error, %new_consistency, "Cannot change request consistency!");
+                                    return Some(Err(error));
+                                }
+                            }
+                        }
+                        RetryDecision::RetryAfter(delay) => {
+                            sleep(delay).await;
+                            continue;
+                        }
+                    }
                 }
             }
         }
@@ -48,3 +173,160 @@ pub async fn send_envelope<T: CdrsTransport + 'static, CM: ConnectionManager<T>
 
     result
 }
+
+// returns the first response given by interceptors, if any
+fn intercept_attempt(
+    request_interceptors: &[Arc<dyn RequestInterceptor + Send + Sync>],
+    envelope: &Envelope,
+    node: SocketAddr,
+    attempt: usize,
+) -> Option<error::Result<Envelope>> {
+    request_interceptors
+        .iter()
+        .find_map(|interceptor| interceptor.before_attempt(envelope, node, attempt))
+}
+
+// sends given envelope to the node and reports the outcome, returning the response with its latency
+async fn write_to_node<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>(
+    node: &Node<T, CM>,
+    transport: &T,
+    envelope: &Envelope,
+    context: RequestContext<'_, T, CM>,
+) -> (error::Result<Envelope>, Duration) {
+    let start = Instant::now();
+    let in_flight_request = node.start_request();
+    let response = write_envelope(transport, envelope, context.request_timeout)
+        .await
+        .map_err(|error| match error {
+            // connections use translated addresses, which don't have to identify nodes
+            error::Error::Server { body, .. } => error::Error::Server {
+                body,
+                addr: node.broadcast_rpc_address(),
+            },
+            error => error,
+        });
+
+    drop(in_flight_request);
+    node.report_response(&response);
+
+    let latency = start.elapsed();
+    if let Some(load_balancing) = context.load_balancing {
+        if matches!(response, Ok(_) | Err(error::Error::Timeout(_))) {
+            load_balancing.report_latency(node, latency);
+        }
+    }
+
+    if let Some(metrics_recorder) = context.metrics_recorder {
+        metrics_recorder.record_node_request(
+            node.connect_address(),
+            latency,
+            response.as_ref().err(),
+        );
+    }
+
+    (response, latency)
+}
+
+// rebuilds given request envelope with a different consistency
+fn with_consistency(envelope: &Envelope, consistency: Consistency) -> error::Result<Envelope> {
+    let body = match envelope.request_body()? {
+        RequestBody::Query(mut body) => {
+            body.query_params.consistency = consistency;
+            RequestBody::Query(body)
+        }
+        RequestBody::Execute(mut body) => {
+            body.query_parameters.consistency = consistency;
+            RequestBody::Execute(body)
+        }
+        RequestBody::Batch(mut body) => {
+            body.consistency = consistency;
+            RequestBody::Batch(body)
+        }
+        _ => {
+            return Err(format!(
+                "Cannot change consistency of {:?} request!",
+                envelope.opcode
+            )
+            .into())
+        }
+    };
+
+    Ok(Envelope::new(
+        envelope.version,
+        Direction::Request,
+        envelope.flags,
+        envelope.opcode,
+        envelope.stream_id,
+        body.serialize_to_vec(envelope.version),
+        envelope.tracing_id,
+        envelope.warnings.clone(),
+    )
+    .with_custom_payload(envelope.custom_payload.clone()))
+}
+
+async fn write_envelope<T: CdrsTransport>(
+    transport: &T,
+    envelope: &Envelope,
+    request_timeout: Option<Duration>,
+) -> error::Result<Envelope> {
+    if let Some(request_timeout) = request_timeout {
+        // dropping the pending write on timeout releases its stream id in the transport
+        timeout(request_timeout, transport.write_envelope(envelope, false))
+            .await
+            .map_err(|_| {
+                error::Error::Timeout(format!(
+                    "Timeout waiting for response from: {}",
+                    transport.address()
+                ))
+            })
+            .and_then(|result| result)
+    } else {
+        transport.write_envelope(envelope, false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::{Error, Result};
+    use cassandra_protocol::frame::{Envelope, Version};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+
+    use crate::cluster::send_envelope::intercept_attempt;
+    use crate::interceptor::RequestInterceptor;
+
+    struct NoopInterceptor;
+
+    impl RequestInterceptor for NoopInterceptor {}
+
+    struct FailingInterceptor(&'static str);
+
+    impl RequestInterceptor for FailingInterceptor {
+        fn before_attempt(
+            &self,
+            _envelope: &Envelope,
+            _node: SocketAddr,
+            attempt: usize,
+        ) -> Option<Result<Envelope>> {
+            (attempt == 1).then(|| Err(Error::General(self.0.into())))
+        }
+    }
+
+    #[test]
+    fn should_return_first_intercepted_response() {
+        let envelope = Envelope::new_req_options(Version::V4);
+        let node = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+        let interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>> = vec![
+            Arc::new(NoopInterceptor),
+            Arc::new(FailingInterceptor("first")),
+            Arc::new(FailingInterceptor("second")),
+        ];
+
+        assert!(matches!(
+            intercept_attempt(&interceptors, &envelope, node, 1),
+            Some(Err(Error::General(message))) if message == "first"
+        ));
+        assert!(intercept_attempt(&interceptors, &envelope, node, 2).is_none());
+        assert!(intercept_attempt(&[], &envelope, node, 1).is_none());
+    }
+}
@@ -6,59 +6,78 @@ use cassandra_protocol::events::ServerEvent;
 use cassandra_protocol::frame::message_error::ErrorType;
 use cassandra_protocol::frame::message_query::BodyReqQuery;
 use cassandra_protocol::frame::message_response::ResponseBody;
-use cassandra_protocol::frame::message_result::{BodyResResultPrepared, TableSpec};
-use cassandra_protocol::frame::{Envelope, Flags, Serialize, Version};
-use cassandra_protocol::query::{PreparedQuery, QueryBatch, QueryValues};
+use cassandra_protocol::frame::message_result::{BodyResResultPrepared, ResultKind, TableSpec};
+use cassandra_protocol::frame::{Envelope, Flags, FromBytes, Opcode, Serialize, Version};
+use cassandra_protocol::query::{PreparedQuery, QueryBatch, QueryParams, QueryValues};
 use cassandra_protocol::types::value::Value;
-use cassandra_protocol::types::{CIntShort, SHORT_LEN};
+use cassandra_protocol::types::{CIntShort, CLong, INT_LEN, SHORT_LEN};
 use derivative::Derivative;
+use futures::future::join_all;
 use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
 use itertools::Itertools;
+use std::borrow::Cow;
 use std::io::{Cursor, Write};
+use std::iter;
 use std::marker::PhantomData;
+use std::mem;
 use std::net::SocketAddr;
 use std::sync::{Arc, LazyLock, Mutex};
+use std::time::Duration;
 use thiserror::Error;
 use tokio::sync::broadcast::{channel, Receiver, Sender};
+use tokio::sync::mpsc::unbounded_channel;
 use tokio::sync::watch;
 use tokio::task::JoinHandle;
-use tokio::time::sleep;
+use tokio::time::{sleep, Instant};
 use tokio::{pin, select};
 use tracing::*;
+use uuid::Uuid;
 
+use crate::address_translator::AddressTranslator;
 use crate::cluster::connection_manager::ConnectionManager;
 use crate::cluster::connection_pool::{ConnectionPoolConfig, ConnectionPoolFactory};
 use crate::cluster::control_connection::ControlConnection;
+use crate::cluster::prepared_statement_cache::PreparedStatementCache;
+use crate::cluster::query_trace::{QueryTrace, TraceFetchConfig};
+use crate::cluster::row_stream::Page;
 #[cfg(feature = "rust-tls")]
 use crate::cluster::rustls_connection_manager::RustlsConnectionManager;
-use crate::cluster::send_envelope::send_envelope;
+use crate::cluster::send_envelope::{send_envelope, RequestContext};
 use crate::cluster::tcp_connection_manager::TcpConnectionManager;
 use crate::cluster::topology::{Node, NodeDistance, NodeState};
-use crate::cluster::Murmur3Token;
+use crate::cluster::version_negotiation::negotiate_version;
 #[cfg(feature = "rust-tls")]
 use crate::cluster::NodeRustlsConfig;
+use crate::cluster::Token;
 use crate::cluster::{ClusterMetadata, ClusterMetadataManager, SessionContext};
 use crate::cluster::{GenericClusterConfig, KeyspaceHolder};
-use crate::cluster::{NodeTcpConfig, SessionPager};
+use crate::cluster::{NodeTcpConfig, QueryResult, RowStream, SessionPager};
 use crate::frame_encoding::{FrameEncodingFactory, ProtocolFrameEncodingFactory};
 use crate::future::BoxFuture;
+use crate::interceptor::{RequestInfo, RequestInterceptor, RequestKind};
 use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
 use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
 use crate::load_balancing::{
     InitializingWrapperLoadBalancingStrategy, LoadBalancingStrategy, QueryPlan, Request,
 };
+use crate::metrics::{Metrics, MetricsRecorder, NoopMetricsRecorder};
+use crate::request_tracker::{RequestAttempts, RequestReport, RequestTracker};
 use crate::retry::{
     DefaultRetryPolicy, ExponentialReconnectionPolicy, ReconnectionPolicy, RetryPolicy,
 };
 use crate::speculative_execution::{Context, SpeculativeExecutionPolicy};
 use crate::statement::{StatementParams, StatementParamsBuilder};
+use crate::timestamp_generator::TimestampGenerator;
 #[cfg(feature = "rust-tls")]
 use crate::transport::TransportRustls;
-use crate::transport::{CdrsTransport, TransportTcp};
+use crate::transport::{CdrsTransport, TransportTcp, MAX_IN_FLIGHT_REQUESTS};
 
 pub const DEFAULT_TRANSPORT_BUFFER_SIZE: usize = 1024;
+pub const DEFAULT_MAX_IN_FLIGHT_REQUESTS: usize = MAX_IN_FLIGHT_REQUESTS;
+pub const DEFAULT_PREPARED_STATEMENT_CACHE_SIZE: usize = 1024;
 const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 128;
+const SCHEMA_AGREEMENT_INTERVAL: Duration = Duration::from_millis(200);
 
 static DEFAULT_STATEMENT_PARAMETERS: LazyLock<StatementParams> = LazyLock::new(Default::default);
 
@@ -68,6 +87,16 @@ fn convert_to_prepared(body: ResponseBody) -> error::Result<BodyResResultPrepare
         .ok_or_else(|| "Cannot convert envelope into prepare response!".into())
 }
 
+#[inline]
+fn is_schema_change(envelope: &Envelope) -> bool {
+    envelope.opcode == Opcode::Result
+        && envelope
+            .body
+            .get(..INT_LEN)
+            .and_then(|kind| ResultKind::from_bytes(kind).ok())
+            == Some(ResultKind::SchemaChange)
+}
+
 #[inline]
 fn prepare_flags(with_tracing: bool, with_warnings: bool, beta_protocol: bool) -> Flags {
     let mut flags = Flags::empty();
@@ -196,6 +225,19 @@ pub struct Session<
     retry_policy: Box<dyn RetryPolicy + Send + Sync>,
     #[derivative(Debug = "ignore")]
     speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+    request_timeout: Option<Duration>,
+    schema_agreement_timeout: Option<Duration>,
+    #[derivative(Debug = "ignore")]
+    timestamp_generator: Option<Box<dyn TimestampGenerator + Send + Sync>>,
+    #[derivative(Debug = "ignore")]
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    #[derivative(Debug = "ignore")]
+    request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+    #[derivative(Debug = "ignore")]
+    request_tracker: Option<Arc<dyn RequestTracker + Send + Sync>>,
+    #[derivative(Debug = "ignore")]
+    prepared_statement_cache: Arc<PreparedStatementCache>,
+    prepare_on_all_nodes: bool,
     control_connection_handle: JoinHandle<()>,
     event_sender: Sender<ServerEvent>,
     #[derivative(Debug = "ignore")]
@@ -230,11 +272,55 @@ impl<
     }
 
     /// Executes given prepared query with query parameters.
+    #[instrument(
+        name = "cassandra.request",
+        skip_all,
+        fields(
+            otel.kind = "client",
+            db.system = "cassandra",
+            db.operation = "EXECUTE",
+            db.statement = %prepared.query,
+            db.cassandra.keyspace = self.span_keyspace(
+                prepared.keyspace.as_deref().or(parameters.keyspace.as_deref())
+            ),
+            db.cassandra.consistency_level = %parameters.query_params.consistency,
+            db.cassandra.idempotence = parameters.is_idempotent,
+            error = field::Empty,
+        )
+    )]
     pub async fn exec_with_params(
         &self,
         prepared: &PreparedQuery,
         parameters: &StatementParams,
-    ) -> error::Result<Envelope> {
+    ) -> error::Result<QueryResult> {
+        let request = RequestInfo {
+            kind: RequestKind::Execute,
+            query: Some(&prepared.query),
+        };
+
+        let start = Instant::now();
+        let attempts = self.new_request_attempts();
+
+        let mut parameters = Cow::Borrowed(parameters);
+        let result = match self.intercept_request(&request, &mut parameters) {
+            Some(result) => result,
+            None => {
+                self.send_exec(prepared, &parameters, attempts.as_ref())
+                    .await
+            }
+        };
+
+        self.intercept_response(&request, &result);
+        self.track_request(&request, &parameters, attempts, start, &result);
+        result
+    }
+
+    async fn send_exec(
+        &self,
+        prepared: &PreparedQuery,
+        parameters: &StatementParams,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
         let consistency = parameters.query_params.consistency;
         let flags = prepare_flags(
             parameters.tracing,
@@ -248,13 +334,25 @@ impl<
             .as_ref()
             .map(|metadata| (**metadata).clone());
 
+        let query_params = match self.generate_timestamp(
+            parameters.query_params.timestamp,
+            parameters.skip_timestamp_generator,
+        ) {
+            Some(timestamp) => Cow::Owned(QueryParams {
+                timestamp: Some(timestamp),
+                ..parameters.query_params.clone()
+            }),
+            None => Cow::Borrowed(&parameters.query_params),
+        };
+
         let envelope = Envelope::new_req_execute(
             &prepared.id,
             result_metadata_id.as_ref(),
-            &parameters.query_params,
+            &query_params,
             flags,
             self.version,
-        );
+        )
+        .with_custom_payload(parameters.custom_payload.clone());
 
         let keyspace = prepared
             .keyspace
@@ -283,11 +381,13 @@ impl<
                 envelope,
                 parameters.is_idempotent,
                 keyspace,
-                parameters.token,
+                parameters.token.clone(),
                 routing_key.as_deref(),
                 Some(consistency),
                 parameters.speculative_execution_policy.as_ref(),
                 parameters.retry_policy.as_ref(),
+                parameters.request_timeout,
+                attempts,
             )
             .await;
 
@@ -311,7 +411,8 @@ impl<
                     keyspace.map(|keyspace| keyspace.to_string()),
                     flags,
                     self.version,
-                );
+                )
+                .with_custom_payload(parameters.custom_payload.clone());
 
                 let retry_policy = self.effective_retry_policy(parameters.retry_policy.as_ref());
                 let prepare_result = send_envelope(
@@ -319,7 +420,16 @@ impl<
                     &prepare_envelope,
                     true,
                     retry_policy.new_session(),
+                    self.request_context(
+                        None,
+                        self.effective_request_timeout(parameters.request_timeout),
+                        attempts,
+                    ),
                 )
+                .instrument(info_span!(
+                    "cassandra.reprepare",
+                    db.cassandra.coordinator.address = %addr,
+                ))
                 .await
                 .unwrap_or_else(|| Err("No response for re-prepare statement!".into()))
                 .and_then(|response| response.response_body())
@@ -347,18 +457,21 @@ impl<
                         &parameters.query_params,
                         flags,
                         self.version,
-                    );
+                    )
+                    .with_custom_payload(parameters.custom_payload.clone());
 
                     result = self
                         .send_envelope(
                             envelope,
                             parameters.is_idempotent,
                             keyspace,
-                            parameters.token,
+                            parameters.token.clone(),
                             routing_key.as_deref(),
                             Some(consistency),
                             parameters.speculative_execution_policy.as_ref(),
                             parameters.retry_policy.as_ref(),
+                            parameters.request_timeout,
+                            attempts,
                         )
                         .await;
                 }
@@ -390,7 +503,7 @@ impl<
         &self,
         prepared: &PreparedQuery,
         values: V,
-    ) -> error::Result<Envelope> {
+    ) -> error::Result<QueryResult> {
         self.exec_with_params(
             prepared,
             &StatementParamsBuilder::new()
@@ -402,15 +515,48 @@ impl<
 
     /// Executes given prepared query.
     #[inline]
-    pub async fn exec(&self, prepared: &PreparedQuery) -> error::Result<Envelope> {
+    pub async fn exec(&self, prepared: &PreparedQuery) -> error::Result<QueryResult> {
         self.exec_with_params(prepared, &DEFAULT_STATEMENT_PARAMETERS)
             .await
     }
 
+    /// Executes a query as a prepared statement, taken from the prepared statement cache or
+    /// prepared on first use. The statement is cached for the keyspace from parameters or the
+    /// current global keyspace. The custom payload from parameters is sent only with the execute,
+    /// not with the prepare.
+    pub async fn exec_cached<Q: ToString>(
+        &self,
+        query: Q,
+        parameters: &StatementParams,
+    ) -> error::Result<QueryResult> {
+        let keyspace = parameters
+            .keyspace
+            .clone()
+            .or_else(|| self.current_keyspace().map(|keyspace| (*keyspace).clone()));
+
+        let prepared = self
+            .prepare_cached_tw(query.to_string(), keyspace, parameters.beta_protocol)
+            .await?;
+
+        self.exec_with_params(&prepared, parameters).await
+    }
+
     /// Prepares a query for execution. Along with query itself, the
     /// method takes `with_tracing` and `with_warnings` flags to get
     /// tracing information and warnings. Returns the raw prepared
     /// query result.
+    #[instrument(
+        name = "cassandra.request",
+        skip_all,
+        fields(
+            otel.kind = "client",
+            db.system = "cassandra",
+            db.operation = "PREPARE",
+            db.statement = %query.to_string(),
+            db.cassandra.keyspace = self.span_keyspace(keyspace.as_deref()),
+            error = field::Empty,
+        )
+    )]
     pub async fn prepare_raw_tw<Q: ToString>(
         &self,
         query: Q,
@@ -419,16 +565,115 @@ impl<
         with_warnings: bool,
         beta_protocol: bool,
     ) -> error::Result<BodyResResultPrepared> {
-        let flags = prepare_flags(with_tracing, with_warnings, beta_protocol);
+        let query = query.to_string();
+        let request = RequestInfo {
+            kind: RequestKind::Prepare,
+            query: Some(&query),
+        };
 
-        let envelope = Envelope::new_req_prepare(query.to_string(), keyspace, flags, self.version);
+        let start = Instant::now();
+        let attempts = self.new_request_attempts();
 
-        self.send_envelope(envelope, true, None, None, None, None, None, None)
-            .await
+        let mut parameters = Cow::Owned(StatementParams {
+            keyspace,
+            tracing: with_tracing,
+            warnings: with_warnings,
+            beta_protocol,
+            ..Default::default()
+        });
+
+        let result = match self.intercept_request(&request, &mut parameters) {
+            Some(result) => result,
+            None => {
+                self.send_prepare(query.clone(), &parameters, attempts.as_ref())
+                    .await
+            }
+        };
+
+        self.intercept_response(&request, &result);
+        self.track_request(&request, &parameters, attempts, start, &result);
+
+        result
             .and_then(|response| response.response_body())
             .and_then(convert_to_prepared)
     }
 
+    async fn send_prepare(
+        &self,
+        query: String,
+        parameters: &StatementParams,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
+        let flags = prepare_flags(
+            parameters.tracing,
+            parameters.warnings,
+            parameters.beta_protocol,
+        );
+
+        let envelope =
+            Envelope::new_req_prepare(query, parameters.keyspace.clone(), flags, self.version)
+                .with_custom_payload(parameters.custom_payload.clone());
+
+        if self.prepare_on_all_nodes {
+            if let Some(result) = self.prepare_on_up_nodes(&envelope, attempts).await {
+                return result;
+            }
+        }
+
+        self.send_envelope(
+            envelope, true, None, None, None, None, None, None, None, attempts,
+        )
+        .await
+    }
+
+    // Sends given prepare envelope to all up nodes. Returns the first successful result or the
+    // last error, or None if there are no up nodes.
+    async fn prepare_on_up_nodes(
+        &self,
+        envelope: &Envelope,
+        attempts: Option<&RequestAttempts>,
+    ) -> Option<error::Result<QueryResult>> {
+        let nodes = self
+            .cluster_metadata()
+            .unignored_nodes()
+            .into_iter()
+            .filter(|node| node.state() == NodeState::Up)
+            .collect_vec();
+
+        let results = join_all(nodes.into_iter().map(|node| async move {
+            let broadcast_rpc_address = node.broadcast_rpc_address();
+            let result = send_envelope(
+                iter::once(node),
+                envelope,
+                true,
+                self.retry_policy.new_session(),
+                self.request_context(None, self.request_timeout, attempts),
+            )
+            .await
+            .unwrap_or_else(|| Err("No response for prepare statement!".into()))
+            .and_then(|response| {
+                response
+                    .response_body()
+                    .and_then(convert_to_prepared)
+                    .map(|_| response)
+            });
+
+            if let Err(error) = &result {
+                warn!(%error, %broadcast_rpc_address, "Error preparing statement on node.");
+            }
+
+            result
+        }))
+        .await;
+
+        results
+            .into_iter()
+            .fold(None, |result, node_result| match result {
+                Some(Ok(_)) => result,
+                _ => Some(node_result),
+            })
+    }
+
     /// Prepares query without additional tracing information and warnings.
     /// Returns the raw prepared query result.
     #[inline]
@@ -470,19 +715,67 @@ impl<
         self.prepare_tw(query, None, false, false, false).await
     }
 
+    /// Returns a prepared query from the prepared statement cache, preparing it if it's not
+    /// cached for the current global keyspace. Concurrent calls for the same query share a single
+    /// prepare request. Cached statements are dropped when the schema of their keyspace changes.
+    pub async fn prepare_cached<Q: ToString>(&self, query: Q) -> error::Result<Arc<PreparedQuery>> {
+        let keyspace = self.current_keyspace().map(|keyspace| (*keyspace).clone());
+
+        self.prepare_cached_tw(query.to_string(), keyspace, false)
+            .await
+    }
+
     /// Executes batch query.
     #[inline]
-    pub async fn batch(&self, batch: QueryBatch) -> error::Result<Envelope> {
+    pub async fn batch(&self, batch: QueryBatch) -> error::Result<QueryResult> {
         self.batch_with_params(batch, &DEFAULT_STATEMENT_PARAMETERS)
             .await
     }
 
     /// Executes batch query with parameters.
+    #[instrument(
+        name = "cassandra.request",
+        skip_all,
+        fields(
+            otel.kind = "client",
+            db.system = "cassandra",
+            db.operation = "BATCH",
+            db.cassandra.keyspace = self.span_keyspace(parameters.keyspace.as_deref()),
+            db.cassandra.consistency_level = %batch.consistency,
+            db.cassandra.idempotence = parameters.is_idempotent,
+            error = field::Empty,
+        )
+    )]
     pub async fn batch_with_params(
         &self,
         batch: QueryBatch,
         parameters: &StatementParams,
-    ) -> error::Result<Envelope> {
+    ) -> error::Result<QueryResult> {
+        let request = RequestInfo {
+            kind: RequestKind::Batch,
+            query: None,
+        };
+
+        let start = Instant::now();
+        let attempts = self.new_request_attempts();
+
+        let mut parameters = Cow::Borrowed(parameters);
+        let result = match self.intercept_request(&request, &mut parameters) {
+            Some(result) => result,
+            None => self.send_batch(batch, &parameters, attempts.as_ref()).await,
+        };
+
+        self.intercept_response(&request, &result);
+        self.track_request(&request, &parameters, attempts, start, &result);
+        result
+    }
+
+    async fn send_batch(
+        &self,
+        mut batch: QueryBatch,
+        parameters: &StatementParams,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
         let flags = prepare_flags(
             parameters.tracing,
             parameters.warnings,
@@ -491,7 +784,14 @@ impl<
 
         let consistency = batch.consistency;
 
-        let envelope = Envelope::new_req_batch(batch, flags, self.version);
+        if let Some(timestamp) =
+            self.generate_timestamp(batch.timestamp, parameters.skip_timestamp_generator)
+        {
+            batch.timestamp = Some(timestamp);
+        }
+
+        let envelope = Envelope::new_req_batch(batch, flags, self.version)
+            .with_custom_payload(parameters.custom_payload.clone());
 
         self.send_envelope(
             envelope,
@@ -502,13 +802,15 @@ impl<
             Some(consistency),
             parameters.speculative_execution_policy.as_ref(),
             parameters.retry_policy.as_ref(),
+            parameters.request_timeout,
+            attempts,
         )
         .await
     }
 
     /// Executes a query.
     #[inline]
-    pub async fn query<Q: ToString>(&self, query: Q) -> error::Result<Envelope> {
+    pub async fn query<Q: ToString>(&self, query: Q) -> error::Result<QueryResult> {
         self.query_with_params(query, DEFAULT_STATEMENT_PARAMETERS.clone())
             .await
     }
@@ -519,7 +821,7 @@ impl<
         &self,
         query: Q,
         values: V,
-    ) -> error::Result<Envelope> {
+    ) -> error::Result<QueryResult> {
         self.query_with_params(
             query,
             StatementParamsBuilder::new()
@@ -530,11 +832,65 @@ impl<
     }
 
     /// Executes a query with query parameters.
+    #[instrument(
+        name = "cassandra.request",
+        skip_all,
+        fields(
+            otel.kind = "client",
+            db.system = "cassandra",
+            db.operation = "QUERY",
+            db.statement = %query.to_string(),
+            db.cassandra.keyspace = self.span_keyspace(parameters.keyspace.as_deref()),
+            db.cassandra.consistency_level = %parameters.query_params.consistency,
+            db.cassandra.idempotence = parameters.is_idempotent,
+            error = field::Empty,
+        )
+    )]
     pub async fn query_with_params<Q: ToString>(
         &self,
         query: Q,
         parameters: StatementParams,
-    ) -> error::Result<Envelope> {
+    ) -> error::Result<QueryResult> {
+        let query = query.to_string();
+        let request = RequestInfo {
+            kind: RequestKind::Query,
+            query: Some(&query),
+        };
+
+        let start = Instant::now();
+        let attempts = self.new_request_attempts();
+
+        let mut parameters = Cow::Owned(parameters);
+        let result = match self.intercept_request(&request, &mut parameters) {
+            Some(result) => result,
+            None => {
+                // parameters are only needed afterwards for tracking
+                let send_parameters = if attempts.is_some() {
+                    parameters.clone()
+                } else {
+                    mem::take(&mut parameters)
+                };
+
+                self.send_query(
+                    query.clone(),
+                    send_parameters.into_owned(),
+                    attempts.as_ref(),
+                )
+                .await
+            }
+        };
+
+        self.intercept_response(&request, &result);
+        self.track_request(&request, &parameters, attempts, start, &result);
+        result
+    }
+
+    async fn send_query(
+        &self,
+        query: String,
+        parameters: StatementParams,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
         let is_idempotent = parameters.is_idempotent;
         let consistency = parameters.query_params.consistency;
         let keyspace = parameters.keyspace;
@@ -544,9 +900,16 @@ impl<
             .as_ref()
             .map(|values| serialize_routing_key(values, self.version));
 
+        let mut query_params = parameters.query_params;
+        if let Some(timestamp) =
+            self.generate_timestamp(query_params.timestamp, parameters.skip_timestamp_generator)
+        {
+            query_params.timestamp = Some(timestamp);
+        }
+
         let query = BodyReqQuery {
-            query: query.to_string(),
-            query_params: parameters.query_params,
+            query,
+            query_params,
         };
 
         let flags = prepare_flags(
@@ -555,7 +918,8 @@ impl<
             parameters.beta_protocol,
         );
 
-        let envelope = Envelope::new_query(query, flags, self.version);
+        let envelope = Envelope::new_query(query, flags, self.version)
+            .with_custom_payload(parameters.custom_payload);
 
         self.send_envelope(
             envelope,
@@ -566,10 +930,145 @@ impl<
             Some(consistency),
             parameters.speculative_execution_policy.as_ref(),
             parameters.retry_policy.as_ref(),
+            parameters.request_timeout,
+            attempts,
         )
         .await
     }
 
+    /// Executes a query and returns a stream of resulting rows, fetching consecutive pages in the
+    /// background. Page size is taken from given parameters. See [`RowStream`] for details.
+    pub fn query_stream<Q: ToString>(
+        self: &Arc<Self>,
+        query: Q,
+        parameters: StatementParams,
+    ) -> RowStream {
+        let session = self.clone();
+        let query = query.to_string();
+
+        RowStream::new(parameters, move |parameters| {
+            let session = session.clone();
+            let query = query.clone();
+
+            async move {
+                session
+                    .query_with_params(query, parameters)
+                    .await
+                    .map(QueryResult::into_envelope)
+                    .and_then(Page::from_envelope)
+            }
+            .boxed()
+        })
+    }
+
+    /// Executes given prepared query and returns a stream of resulting rows, fetching consecutive
+    /// pages in the background. Page size is taken from given parameters. See [`RowStream`] for
+    /// details.
+    pub fn exec_stream(
+        self: &Arc<Self>,
+        prepared: Arc<PreparedQuery>,
+        parameters: StatementParams,
+    ) -> RowStream {
+        let session = self.clone();
+
+        RowStream::new(parameters, move |parameters| {
+            let session = session.clone();
+            let prepared = prepared.clone();
+
+            async move {
+                session
+                    .exec_with_params(&prepared, &parameters)
+                    .await
+                    .map(QueryResult::into_envelope)
+                    .and_then(Page::from_envelope)
+            }
+            .boxed()
+        })
+    }
+
+    /// Fetches the trace of a query executed with tracing enabled, using default
+    /// [`TraceFetchConfig`].
+    #[inline]
+    pub async fn fetch_trace(&self, tracing_id: Uuid) -> error::Result<QueryTrace> {
+        self.fetch_trace_with_config(tracing_id, &TraceFetchConfig::default())
+            .await
+    }
+
+    /// Fetches the trace of a query executed with tracing enabled. Since traces are written
+    /// asynchronously, fetching is retried after the configured interval until the trace is
+    /// complete. Returns an error if the trace is still incomplete after the last attempt.
+    pub async fn fetch_trace_with_config(
+        &self,
+        tracing_id: Uuid,
+        config: &TraceFetchConfig,
+    ) -> error::Result<QueryTrace> {
+        let parameters = StatementParamsBuilder::new()
+            .with_consistency(config.consistency)
+            .with_values(QueryValues::SimpleValues(vec![tracing_id.into()]))
+            .idempotent(true)
+            .build();
+
+        for attempt in 0..config.max_attempts {
+            if attempt > 0 {
+                sleep(config.retry_interval).await;
+            }
+
+            let session_row = self
+                .query_with_params(
+                    "SELECT * FROM system_traces.sessions WHERE session_id = ?",
+                    parameters.clone(),
+                )
+                .await?
+                .first_row()?;
+
+            let session_row = match session_row {
+                Some(session_row) => session_row,
+                None => continue,
+            };
+
+            let event_rows = self
+                .query_with_params(
+                    "SELECT * FROM system_traces.events WHERE session_id = ?",
+                    parameters.clone(),
+                )
+                .await?
+                .rows()?;
+
+            if let Some(trace) = QueryTrace::try_from_rows(tracing_id, &session_row, &event_rows)? {
+                return Ok(trace);
+            }
+        }
+
+        Err(error::Error::General(format!(
+            "Trace {} is incomplete after {} attempts",
+            tracing_id, config.max_attempts
+        )))
+    }
+
+    /// Waits until all up nodes agree on the schema version, checking periodically via the control
+    /// connection. Returns `false` if the agreement was not reached within given timeout.
+    pub async fn wait_for_schema_agreement(&self, timeout: Duration) -> error::Result<bool> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if self
+                .cluster_metadata_manager
+                .check_schema_agreement()
+                .await?
+            {
+                return Ok(true);
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                debug!("Schema agreement not reached before timeout.");
+                return Ok(false);
+            }
+
+            sleep(SCHEMA_AGREEMENT_INTERVAL.min(deadline - now)).await;
+        }
+    }
+
     /// Returns currently set global keyspace.
     #[inline]
     pub fn current_keyspace(&self) -> Option<Arc<String>> {
@@ -602,18 +1101,84 @@ impl<
         self.retry_policy.as_ref()
     }
 
+    /// Returns a snapshot of driver metrics, if the configured metrics recorder keeps them, e.g.
+    /// [`InMemoryMetricsRecorder`](crate::metrics::InMemoryMetricsRecorder).
+    #[inline]
+    pub fn metrics(&self) -> Option<Metrics> {
+        self.metrics_recorder.metrics()
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn send_envelope(
         &self,
         envelope: Envelope,
         is_idempotent: bool,
         keyspace: Option<&str>,
-        token: Option<Murmur3Token>,
+        token: Option<Token>,
+        routing_key: Option<&[u8]>,
+        consistency: Option<Consistency>,
+        speculative_execution_policy: Option<&Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+        retry_policy: Option<&Arc<dyn RetryPolicy + Send + Sync>>,
+        request_timeout: Option<Duration>,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
+        let start = Instant::now();
+        let result = self
+            .send_envelope_to_nodes(
+                envelope,
+                is_idempotent,
+                keyspace,
+                token,
+                routing_key,
+                consistency,
+                speculative_execution_policy,
+                retry_policy,
+                request_timeout,
+                attempts,
+            )
+            .await;
+
+        self.metrics_recorder
+            .record_request(start.elapsed(), result.as_ref().err());
+
+        if let Err(error) = &result {
+            Span::current().record("error", field::display(error));
+        }
+
+        let result = result?;
+
+        if let Some(schema_agreement_timeout) = self.schema_agreement_timeout {
+            if is_schema_change(result.envelope()) {
+                // the schema has already been changed, so don't fail the request
+                let schema_agreement = self
+                    .wait_for_schema_agreement(schema_agreement_timeout)
+                    .await
+                    .unwrap_or_else(|error| {
+                        warn!(%error, "Error waiting for schema agreement.");
+                        false
+                    });
+
+                return Ok(result.with_schema_agreement(schema_agreement));
+            }
+        }
+
+        Ok(result)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn send_envelope_to_nodes(
+        &self,
+        envelope: Envelope,
+        is_idempotent: bool,
+        keyspace: Option<&str>,
+        token: Option<Token>,
         routing_key: Option<&[u8]>,
         consistency: Option<Consistency>,
         speculative_execution_policy: Option<&Arc<dyn SpeculativeExecutionPolicy + Send + Sync>>,
         retry_policy: Option<&Arc<dyn RetryPolicy + Send + Sync>>,
-    ) -> error::Result<Envelope> {
+        request_timeout: Option<Duration>,
+        attempts: Option<&RequestAttempts>,
+    ) -> error::Result<QueryResult> {
         let current_keyspace = self.current_keyspace();
         let request = Request::new(
             keyspace.or_else(|| current_keyspace.as_ref().map(|keyspace| &***keyspace)),
@@ -663,6 +1228,7 @@ impl<
             .or(self.speculative_execution_policy.as_deref());
 
         let retry_policy = self.effective_retry_policy(retry_policy);
+        let request_timeout = self.effective_request_timeout(request_timeout);
 
         match speculative_execution_policy {
             Some(speculative_execution_policy) if is_idempotent => {
@@ -670,12 +1236,19 @@ impl<
 
                 let mut context = Context::new(1);
                 let mut async_tasks = FuturesUnordered::new();
-                async_tasks.push(send_envelope(
-                    &shared_query_plan,
-                    &envelope,
-                    is_idempotent,
-                    retry_policy.new_session(),
-                ));
+                async_tasks.push(
+                    send_envelope(
+                        &shared_query_plan,
+                        &envelope,
+                        is_idempotent,
+                        retry_policy.new_session(),
+                        self.request_context(consistency, request_timeout, attempts),
+                    )
+                    .instrument(info_span!(
+                        "cassandra.speculative_execution",
+                        db.cassandra.speculative_execution = 0,
+                    )),
+                );
 
                 let sleep_fut = sleep(
                     speculative_execution_policy
@@ -695,12 +1268,21 @@ impl<
                                 speculative_execution_policy.execution_interval(&context)
                             {
                                 context.running_executions += 1;
-                                async_tasks.push(send_envelope(
-                                    &shared_query_plan,
-                                    &envelope,
-                                    is_idempotent,
-                                    retry_policy.new_session(),
-                                ));
+                                self.metrics_recorder.record_speculative_execution();
+                                async_tasks.push(
+                                    send_envelope(
+                                        &shared_query_plan,
+                                        &envelope,
+                                        is_idempotent,
+                                        retry_policy.new_session(),
+                                        self.request_context(consistency, request_timeout, attempts),
+                                    )
+                                    .instrument(info_span!(
+                                        "cassandra.speculative_execution",
+                                        db.cassandra.speculative_execution =
+                                            context.running_executions - 1,
+                                    )),
+                                );
 
                                 sleep_fut.set(sleep(interval).fuse());
                             }
@@ -709,7 +1291,9 @@ impl<
                             match result {
                                 Some(result) => {
                                     match result {
-                                        Err(error::Error::Io(_)) | Err(error::Error::Timeout(_)) => {
+                                        Err(error::Error::Io(_))
+                                        | Err(error::Error::Timeout(_))
+                                        | Err(error::Error::ConnectionBusy(_)) => {
                                             last_error = Some(result);
                                         },
                                         _ => return result,
@@ -733,12 +1317,79 @@ impl<
                 &envelope,
                 is_idempotent,
                 retry_policy.new_session(),
+                self.request_context(consistency, request_timeout, attempts),
             )
             .await
             .unwrap_or_else(|| Err("No nodes available in query plan!".into())),
         }
     }
 
+    #[inline]
+    fn request_context<'a>(
+        &'a self,
+        consistency: Option<Consistency>,
+        request_timeout: Option<Duration>,
+        attempts: Option<&'a RequestAttempts>,
+    ) -> RequestContext<'a, T, CM> {
+        RequestContext {
+            consistency,
+            request_timeout,
+            load_balancing: Some(self.load_balancing.as_ref()),
+            metrics_recorder: Some(self.metrics_recorder.as_ref()),
+            request_interceptors: &self.request_interceptors,
+            attempts,
+        }
+    }
+
+    // returns the result of the first interceptor which short-circuits the request, if any
+    fn intercept_request(
+        &self,
+        request: &RequestInfo<'_>,
+        parameters: &mut Cow<'_, StatementParams>,
+    ) -> Option<error::Result<QueryResult>> {
+        if self.request_interceptors.is_empty() {
+            return None;
+        }
+
+        let parameters = parameters.to_mut();
+        self.request_interceptors
+            .iter()
+            .find_map(|interceptor| interceptor.before_request(request, parameters))
+    }
+
+    fn intercept_response(&self, request: &RequestInfo<'_>, result: &error::Result<QueryResult>) {
+        for interceptor in self.request_interceptors.iter().rev() {
+            interceptor.after_response(request, result);
+        }
+    }
+
+    // attempts are only collected when there's a tracker to report them to
+    #[inline]
+    fn new_request_attempts(&self) -> Option<RequestAttempts> {
+        self.request_tracker
+            .as_ref()
+            .map(|_| RequestAttempts::default())
+    }
+
+    fn track_request(
+        &self,
+        request: &RequestInfo<'_>,
+        parameters: &StatementParams,
+        attempts: Option<RequestAttempts>,
+        start: Instant,
+        result: &error::Result<QueryResult>,
+    ) {
+        if let (Some(request_tracker), Some(attempts)) = (&self.request_tracker, attempts) {
+            request_tracker.on_request_complete(&RequestReport {
+                request: *request,
+                parameters,
+                attempts: &attempts.into_attempts(),
+                latency: start.elapsed(),
+                result,
+            });
+        }
+    }
+
     #[inline]
     fn effective_retry_policy<'a, 'b: 'a>(
         &'a self,
@@ -749,6 +1400,47 @@ impl<
             .unwrap_or_else(|| self.retry_policy.as_ref())
     }
 
+    async fn prepare_cached_tw(
+        &self,
+        query: String,
+        keyspace: Option<String>,
+        beta_protocol: bool,
+    ) -> error::Result<Arc<PreparedQuery>> {
+        self.prepared_statement_cache
+            .get_or_prepare(query, keyspace, |query, keyspace| {
+                self.prepare_tw(query, keyspace, false, false, beta_protocol)
+            })
+            .await
+    }
+
+    // returns a new timestamp only if the statement doesn't have one and should use the generator
+    #[inline]
+    fn generate_timestamp(
+        &self,
+        timestamp: Option<CLong>,
+        skip_timestamp_generator: bool,
+    ) -> Option<CLong> {
+        if timestamp.is_some() || skip_timestamp_generator {
+            return None;
+        }
+
+        self.timestamp_generator
+            .as_ref()
+            .map(|timestamp_generator| timestamp_generator.next_timestamp())
+    }
+
+    // returns the keyspace recorded in request spans
+    fn span_keyspace(&self, keyspace: Option<&str>) -> Option<String> {
+        keyspace
+            .map(|keyspace| keyspace.to_string())
+            .or_else(|| self.current_keyspace().map(|keyspace| (*keyspace).clone()))
+    }
+
+    #[inline]
+    fn effective_request_timeout(&self, request_timeout: Option<Duration>) -> Option<Duration> {
+        request_timeout.or(self.request_timeout)
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn new(
         load_balancing: LB,
@@ -757,7 +1449,16 @@ impl<
         retry_policy: Box<dyn RetryPolicy + Send + Sync>,
         reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
         node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+        address_translator: Option<Box<dyn AddressTranslator + Send + Sync>>,
         speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+        request_timeout: Option<Duration>,
+        schema_agreement_timeout: Option<Duration>,
+        timestamp_generator: Option<Box<dyn TimestampGenerator + Send + Sync>>,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+        request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+        request_tracker: Option<Arc<dyn RequestTracker + Send + Sync>>,
+        prepared_statement_cache_size: usize,
+        prepare_on_all_nodes: bool,
         contact_points: Vec<SocketAddr>,
         connection_manager: CM,
         event_channel_capacity: usize,
@@ -771,6 +1472,7 @@ impl<
             connection_manager,
             keyspace_receiver,
             reconnection_policy.clone(),
+            metrics_recorder.clone(),
         ));
 
         let contact_points = contact_points
@@ -799,6 +1501,18 @@ impl<
 
         let (event_sender, event_receiver) = channel(event_channel_capacity);
 
+        let prepared_statement_cache =
+            Arc::new(PreparedStatementCache::new(prepared_statement_cache_size));
+        prepared_statement_cache.listen_to_events(event_sender.subscribe());
+
+        let (node_up_sender, node_up_receiver) = unbounded_channel();
+        prepared_statement_cache.reprepare_on_node_up(
+            node_up_receiver,
+            version,
+            beta_protocol,
+            request_timeout,
+        );
+
         let session_context = Arc::new(SessionContext::default());
 
         let cluster_metadata_manager = Arc::new(ClusterMetadataManager::new(
@@ -806,8 +1520,10 @@ impl<
             connection_pool_factory,
             session_context.clone(),
             node_distance_evaluator,
+            address_translator,
             version,
             beta_protocol,
+            node_up_sender,
         ));
 
         cluster_metadata_manager.listen_to_events(event_receiver);
@@ -819,6 +1535,7 @@ impl<
             cluster_metadata_manager.clone(),
             event_sender.clone(),
             session_context,
+            metrics_recorder.clone(),
             version,
         );
 
@@ -833,6 +1550,14 @@ impl<
             keyspace_holder,
             retry_policy,
             speculative_execution_policy,
+            request_timeout,
+            schema_agreement_timeout,
+            timestamp_generator,
+            metrics_recorder,
+            request_interceptors,
+            request_tracker,
+            prepared_statement_cache,
+            prepare_on_all_nodes,
             control_connection_handle,
             event_sender,
             cluster_metadata_manager,
@@ -892,7 +1617,16 @@ where
         retry_policy.0,
         reconnection_policy.0,
         node_distance_evaluator.0,
+        config.address_translator(),
         speculative_execution_policy.map(|policy| policy.0),
+        config.request_timeout(),
+        config.schema_agreement_timeout(),
+        config.timestamp_generator(),
+        config.metrics_recorder(),
+        config.request_interceptors(),
+        config.request_tracker(),
+        config.prepared_statement_cache_size(),
+        config.prepare_on_all_nodes(),
         initial_nodes.into_iter().collect(),
         connection_manager,
         config.event_channel_capacity(),
@@ -911,12 +1645,22 @@ struct SessionConfig<
 > {
     compression: Compression,
     transport_buffer_size: usize,
+    max_in_flight_requests: usize,
     tcp_nodelay: bool,
     load_balancing: LB,
     retry_policy: Box<dyn RetryPolicy + Send + Sync>,
     reconnection_policy: Arc<dyn ReconnectionPolicy + Send + Sync>,
     node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
+    address_translator: Option<Box<dyn AddressTranslator + Send + Sync>>,
     speculative_execution_policy: Option<Box<dyn SpeculativeExecutionPolicy + Send + Sync>>,
+    request_timeout: Option<Duration>,
+    schema_agreement_timeout: Option<Duration>,
+    timestamp_generator: Option<Box<dyn TimestampGenerator + Send + Sync>>,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+    request_tracker: Option<Arc<dyn RequestTracker + Send + Sync>>,
+    prepared_statement_cache_size: usize,
+    prepare_on_all_nodes: bool,
     event_channel_capacity: usize,
     connection_pool_config: ConnectionPoolConfig,
     keyspace: Option<String>,
@@ -934,12 +1678,22 @@ impl<
         SessionConfig {
             compression: Compression::None,
             transport_buffer_size: DEFAULT_TRANSPORT_BUFFER_SIZE,
+            max_in_flight_requests: DEFAULT_MAX_IN_FLIGHT_REQUESTS,
             tcp_nodelay: true,
             load_balancing,
             retry_policy: Box::<DefaultRetryPolicy>::default(),
             reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
             node_distance_evaluator: Box::<AllLocalNodeDistanceEvaluator>::default(),
+            address_translator: None,
             speculative_execution_policy: None,
+            request_timeout: None,
+            schema_agreement_timeout: None,
+            timestamp_generator: None,
+            metrics_recorder: Arc::new(NoopMetricsRecorder),
+            request_interceptors: Vec::new(),
+            request_tracker: None,
+            prepared_statement_cache_size: DEFAULT_PREPARED_STATEMENT_CACHE_SIZE,
+            prepare_on_all_nodes: false,
             event_channel_capacity: DEFAULT_EVENT_CHANNEL_CAPACITY,
             connection_pool_config: Default::default(),
             keyspace: None,
@@ -968,7 +1722,16 @@ impl<
             self.retry_policy,
             self.reconnection_policy,
             self.node_distance_evaluator,
+            self.address_translator,
             self.speculative_execution_policy,
+            self.request_timeout,
+            self.schema_agreement_timeout,
+            self.timestamp_generator,
+            self.metrics_recorder,
+            self.request_interceptors,
+            self.request_tracker,
+            self.prepared_statement_cache_size,
+            self.prepare_on_all_nodes,
             contact_points,
             connection_manager,
             self.event_channel_capacity,
@@ -987,6 +1750,8 @@ pub enum SessionBuildError {
     CompressionTypeNotSupported,
     #[error("Session control connection died before completing initialization")]
     SessionInitFailed,
+    #[error("Contact points don't support any protocol version supported by the driver!")]
+    ProtocolVersionNotSupported,
 }
 
 /// Builder for easy `Session` creation. Requires static `LoadBalancingStrategy`, but otherwise, other
@@ -1028,6 +1793,15 @@ pub trait SessionBuilder<
         node_distance_evaluator: Box<dyn NodeDistanceEvaluator + Send + Sync>,
     ) -> Self;
 
+    /// Sets address translator, which maps node addresses reported by the cluster to addresses
+    /// used for connecting to nodes, e.g. when the cluster is behind NAT. Contact points are not
+    /// translated. See [`AddressTranslator`].
+    #[must_use]
+    fn with_address_translator(
+        self,
+        address_translator: Box<dyn AddressTranslator + Send + Sync>,
+    ) -> Self;
+
     /// Sets new speculative execution policy.
     #[must_use]
     fn with_speculative_execution_policy(
@@ -1035,30 +1809,95 @@ pub trait SessionBuilder<
         speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
     ) -> Self;
 
-    /// Sets new transport buffer size. High values are recommended with large amounts of in flight
-    /// queries.
+    /// Sets default request timeout, which limits the time spent waiting for a response from a
+    /// single node. When the timeout expires, the retry policy decides what to do next. Can be
+    /// overridden by individual statements. By default, there is no timeout.
     #[must_use]
-    fn with_transport_buffer_size(self, transport_buffer_size: usize) -> Self;
+    fn with_request_timeout(self, request_timeout: Option<Duration>) -> Self;
 
-    /// Sets NODELAY for given session connections.
+    /// Enables waiting for schema agreement after each request which changes the schema, limited
+    /// by given timeout. Whether the agreement was reached is reported in
+    /// [`QueryResult::schema_agreement`]. By default, the session doesn't wait.
     #[must_use]
-    fn with_tcp_nodelay(self, tcp_nodelay: bool) -> Self;
+    fn with_schema_agreement_timeout(self, schema_agreement_timeout: Option<Duration>) -> Self;
 
-    /// Sets event channel capacity. If the driver receives more server events than the capacity,
-    /// some events might get dropped. This can result in the driver operating in a sub-optimal way.
+    /// Sets the generator of client-side timestamps for statements without an explicit timestamp.
+    /// By default, timestamps are assigned by coordinator nodes. See
+    /// [`MonotonicTimestampGenerator`](crate::timestamp_generator::MonotonicTimestampGenerator)
+    /// for the built-in generator.
     #[must_use]
-    fn with_event_channel_capacity(self, event_channel_capacity: usize) -> Self;
+    fn with_timestamp_generator(
+        self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self;
 
-    /// Sets node connection pool configuration for given session.
+    /// Sets the recorder of driver metrics. By default, metrics are not recorded. See
+    /// [`InMemoryMetricsRecorder`](crate::metrics::InMemoryMetricsRecorder) for the built-in
+    /// recorder, which can be read with [`Session::metrics`].
     #[must_use]
-    fn with_connection_pool_config(self, connection_pool_config: ConnectionPoolConfig) -> Self;
+    fn with_metrics_recorder(
+        self,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    ) -> Self;
 
-    /// Sets the keyspace to use. If not using a keyspace explicitly in queries, one should be set
-    /// either by calling this function, or by a `USE` statement. Due to the asynchronous nature of
-    /// the driver and the usage of connection pools, the effect of switching current keyspace via
-    /// `USE` might not propagate immediately to all active connections, resulting in queries
-    /// using a wrong keyspace. If one is known upfront, it's safer to set it while building
-    /// the [`Session`].
+    /// Adds a request interceptor. Multiple interceptors form a chain, called in the order of
+    /// registration before sending requests and in the reverse order after receiving responses.
+    /// See [`RequestInterceptor`].
+    #[must_use]
+    fn with_request_interceptor(
+        self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self;
+
+    /// Sets the tracker called with a report of every completed request, including attempts on
+    /// individual nodes. See [`RequestLogger`](crate::request_tracker::RequestLogger) for the
+    /// built-in slow and failed request logger.
+    #[must_use]
+    fn with_request_tracker(self, request_tracker: Arc<dyn RequestTracker + Send + Sync>) -> Self;
+
+    /// Sets the maximum number of statements kept in the prepared statement cache used by
+    /// [`Session::exec_cached`] and [`Session::prepare_cached`]. Least recently used statements
+    /// are evicted first. Setting 0 disables caching.
+    #[must_use]
+    fn with_prepared_statement_cache_size(self, prepared_statement_cache_size: usize) -> Self;
+
+    /// Prepares statements on all up nodes at once, instead of a single node chosen by the load
+    /// balancing strategy. This avoids re-preparing statements on other nodes during their first
+    /// execution, at the cost of more expensive preparation. Disabled by default.
+    #[must_use]
+    fn with_prepare_on_all_nodes(self, prepare_on_all_nodes: bool) -> Self;
+
+    /// Sets new transport buffer size. High values are recommended with large amounts of in flight
+    /// queries.
+    #[must_use]
+    fn with_transport_buffer_size(self, transport_buffer_size: usize) -> Self;
+
+    /// Sets the maximum number of requests which can be in flight on a single connection. When
+    /// all connections to a node reach the limit, new requests are sent to the next node in the
+    /// query plan. Values above [`MAX_IN_FLIGHT_REQUESTS`] are capped, since each request needs a
+    /// unique stream id.
+    #[must_use]
+    fn with_max_in_flight_requests(self, max_in_flight_requests: usize) -> Self;
+
+    /// Sets NODELAY for given session connections.
+    #[must_use]
+    fn with_tcp_nodelay(self, tcp_nodelay: bool) -> Self;
+
+    /// Sets event channel capacity. If the driver receives more server events than the capacity,
+    /// some events might get dropped. This can result in the driver operating in a sub-optimal way.
+    #[must_use]
+    fn with_event_channel_capacity(self, event_channel_capacity: usize) -> Self;
+
+    /// Sets node connection pool configuration for given session.
+    #[must_use]
+    fn with_connection_pool_config(self, connection_pool_config: ConnectionPoolConfig) -> Self;
+
+    /// Sets the keyspace to use. If not using a keyspace explicitly in queries, one should be set
+    /// either by calling this function, or by a `USE` statement. Due to the asynchronous nature of
+    /// the driver and the usage of connection pools, the effect of switching current keyspace via
+    /// `USE` might not propagate immediately to all active connections, resulting in queries
+    /// using a wrong keyspace. If one is known upfront, it's safer to set it while building
+    /// the [`Session`].
     #[must_use]
     fn with_keyspace(self, keyspace: String) -> Self;
 
@@ -1131,6 +1970,14 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         self
     }
 
+    fn with_address_translator(
+        mut self,
+        address_translator: Box<dyn AddressTranslator + Send + Sync>,
+    ) -> Self {
+        self.config.address_translator = Some(address_translator);
+        self
+    }
+
     fn with_speculative_execution_policy(
         mut self,
         speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
@@ -1139,11 +1986,68 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         self
     }
 
+    fn with_request_timeout(mut self, request_timeout: Option<Duration>) -> Self {
+        self.config.request_timeout = request_timeout;
+        self
+    }
+
+    fn with_schema_agreement_timeout(mut self, schema_agreement_timeout: Option<Duration>) -> Self {
+        self.config.schema_agreement_timeout = schema_agreement_timeout;
+        self
+    }
+
+    fn with_timestamp_generator(
+        mut self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self {
+        self.config.timestamp_generator = Some(timestamp_generator);
+        self
+    }
+
+    fn with_metrics_recorder(
+        mut self,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    ) -> Self {
+        self.config.metrics_recorder = metrics_recorder;
+        self
+    }
+
+    fn with_request_interceptor(
+        mut self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self {
+        self.config.request_interceptors.push(request_interceptor);
+        self
+    }
+
+    fn with_request_tracker(
+        mut self,
+        request_tracker: Arc<dyn RequestTracker + Send + Sync>,
+    ) -> Self {
+        self.config.request_tracker = Some(request_tracker);
+        self
+    }
+
+    fn with_prepared_statement_cache_size(mut self, prepared_statement_cache_size: usize) -> Self {
+        self.config.prepared_statement_cache_size = prepared_statement_cache_size;
+        self
+    }
+
+    fn with_prepare_on_all_nodes(mut self, prepare_on_all_nodes: bool) -> Self {
+        self.config.prepare_on_all_nodes = prepare_on_all_nodes;
+        self
+    }
+
     fn with_transport_buffer_size(mut self, transport_buffer_size: usize) -> Self {
         self.config.transport_buffer_size = transport_buffer_size;
         self
     }
 
+    fn with_max_in_flight_requests(mut self, max_in_flight_requests: usize) -> Self {
+        self.config.max_in_flight_requests = max_in_flight_requests;
+        self
+    }
+
     fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
         self.config.tcp_nodelay = tcp_nodelay;
         self
@@ -1176,37 +2080,47 @@ impl<LB: LoadBalancingStrategy<TransportTcp, TcpConnectionManager> + Send + Sync
         Result<Session<TransportTcp, TcpConnectionManager, LB>, SessionBuildError>,
     > {
         async move {
-            match verify_compression_configuration(
-                self.node_config.version,
+            let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+            let connection_manager = TcpConnectionManager::new(
+                self.node_config.authenticator_provider,
+                keyspace_holder.clone(),
+                self.frame_encoder_factory,
                 self.config.compression,
-            ) {
-                Ok(()) => {
-                    let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
-                    let connection_manager = TcpConnectionManager::new(
-                        self.node_config.authenticator_provider,
-                        keyspace_holder.clone(),
-                        self.frame_encoder_factory,
-                        self.config.compression,
-                        self.config.transport_buffer_size,
-                        self.config.tcp_nodelay,
-                        self.node_config.version,
-                        #[cfg(feature = "http-proxy")]
-                        self.node_config.http_proxy,
-                    );
-
-                    self.config
-                        .into_session(
-                            keyspace_holder,
-                            keyspace_receiver,
-                            self.node_config.contact_points,
-                            connection_manager,
-                            self.node_config.version,
-                            self.node_config.beta_protocol,
-                        )
-                        .await
-                }
-                Err(err) => Err(err),
-            }
+                self.config.transport_buffer_size,
+                self.config.max_in_flight_requests,
+                self.config.metrics_recorder.clone(),
+                self.config.tcp_nodelay,
+                self.node_config.version,
+                #[cfg(feature = "http-proxy")]
+                self.node_config.http_proxy,
+            );
+
+            let (connection_manager, version) = if self.node_config.version_negotiation {
+                negotiate_version(
+                    connection_manager,
+                    TcpConnectionManager::with_version,
+                    &self.node_config.contact_points,
+                    self.config.compression,
+                    self.node_config.version,
+                    self.node_config.beta_protocol,
+                )
+                .await?
+            } else {
+                (connection_manager, self.node_config.version)
+            };
+
+            verify_compression_configuration(version, self.config.compression)?;
+
+            self.config
+                .into_session(
+                    keyspace_holder,
+                    keyspace_receiver,
+                    self.node_config.contact_points,
+                    connection_manager,
+                    version,
+                    self.node_config.beta_protocol,
+                )
+                .await
         }
         .boxed()
     }
@@ -1276,6 +2190,14 @@ impl<
         self
     }
 
+    fn with_address_translator(
+        mut self,
+        address_translator: Box<dyn AddressTranslator + Send + Sync>,
+    ) -> Self {
+        self.config.address_translator = Some(address_translator);
+        self
+    }
+
     fn with_speculative_execution_policy(
         mut self,
         speculative_execution_policy: Box<dyn SpeculativeExecutionPolicy + Send + Sync>,
@@ -1284,11 +2206,68 @@ impl<
         self
     }
 
+    fn with_request_timeout(mut self, request_timeout: Option<Duration>) -> Self {
+        self.config.request_timeout = request_timeout;
+        self
+    }
+
+    fn with_schema_agreement_timeout(mut self, schema_agreement_timeout: Option<Duration>) -> Self {
+        self.config.schema_agreement_timeout = schema_agreement_timeout;
+        self
+    }
+
+    fn with_timestamp_generator(
+        mut self,
+        timestamp_generator: Box<dyn TimestampGenerator + Send + Sync>,
+    ) -> Self {
+        self.config.timestamp_generator = Some(timestamp_generator);
+        self
+    }
+
+    fn with_metrics_recorder(
+        mut self,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    ) -> Self {
+        self.config.metrics_recorder = metrics_recorder;
+        self
+    }
+
+    fn with_request_interceptor(
+        mut self,
+        request_interceptor: Arc<dyn RequestInterceptor + Send + Sync>,
+    ) -> Self {
+        self.config.request_interceptors.push(request_interceptor);
+        self
+    }
+
+    fn with_request_tracker(
+        mut self,
+        request_tracker: Arc<dyn RequestTracker + Send + Sync>,
+    ) -> Self {
+        self.config.request_tracker = Some(request_tracker);
+        self
+    }
+
+    fn with_prepared_statement_cache_size(mut self, prepared_statement_cache_size: usize) -> Self {
+        self.config.prepared_statement_cache_size = prepared_statement_cache_size;
+        self
+    }
+
+    fn with_prepare_on_all_nodes(mut self, prepare_on_all_nodes: bool) -> Self {
+        self.config.prepare_on_all_nodes = prepare_on_all_nodes;
+        self
+    }
+
     fn with_transport_buffer_size(mut self, transport_buffer_size: usize) -> Self {
         self.config.transport_buffer_size = transport_buffer_size;
         self
     }
 
+    fn with_max_in_flight_requests(mut self, max_in_flight_requests: usize) -> Self {
+        self.config.max_in_flight_requests = max_in_flight_requests;
+        self
+    }
+
     fn with_tcp_nodelay(mut self, tcp_nodelay: bool) -> Self {
         self.config.tcp_nodelay = tcp_nodelay;
         self
@@ -1321,39 +2300,49 @@ impl<
         Result<Session<TransportRustls, RustlsConnectionManager, LB>, SessionBuildError>,
     > {
         async move {
-            match verify_compression_configuration(
-                self.node_config.version,
+            let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+            let connection_manager = RustlsConnectionManager::new(
+                self.node_config.dns_name,
+                self.node_config.authenticator_provider,
+                self.node_config.config,
+                keyspace_holder.clone(),
+                self.frame_encoder_factory,
                 self.config.compression,
-            ) {
-                Ok(()) => {
-                    let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
-                    let connection_manager = RustlsConnectionManager::new(
-                        self.node_config.dns_name,
-                        self.node_config.authenticator_provider,
-                        self.node_config.config,
-                        keyspace_holder.clone(),
-                        self.frame_encoder_factory,
-                        self.config.compression,
-                        self.config.transport_buffer_size,
-                        self.config.tcp_nodelay,
-                        self.node_config.version,
-                        #[cfg(feature = "http-proxy")]
-                        self.node_config.http_proxy,
-                    );
-
-                    self.config
-                        .into_session(
-                            keyspace_holder,
-                            keyspace_receiver,
-                            self.node_config.contact_points,
-                            connection_manager,
-                            self.node_config.version,
-                            self.node_config.beta_protocol,
-                        )
-                        .await
-                }
-                Err(err) => Err(err),
-            }
+                self.config.transport_buffer_size,
+                self.config.max_in_flight_requests,
+                self.config.metrics_recorder.clone(),
+                self.config.tcp_nodelay,
+                self.node_config.version,
+                #[cfg(feature = "http-proxy")]
+                self.node_config.http_proxy,
+            );
+
+            let (connection_manager, version) = if self.node_config.version_negotiation {
+                negotiate_version(
+                    connection_manager,
+                    RustlsConnectionManager::with_version,
+                    &self.node_config.contact_points,
+                    self.config.compression,
+                    self.node_config.version,
+                    self.node_config.beta_protocol,
+                )
+                .await?
+            } else {
+                (connection_manager, self.node_config.version)
+            };
+
+            verify_compression_configuration(version, self.config.compression)?;
+
+            self.config
+                .into_session(
+                    keyspace_holder,
+                    keyspace_receiver,
+                    self.node_config.contact_points,
+                    connection_manager,
+                    version,
+                    self.node_config.beta_protocol,
+                )
+                .await
         }
         .boxed()
     }
@@ -1361,8 +2350,483 @@ impl<
 
 #[cfg(test)]
 mod tests {
-    use crate::cluster::session::prepare_flags;
-    use cassandra_protocol::frame::Flags;
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::{Error, Result};
+    use cassandra_protocol::frame::events::{
+        SchemaChange, SchemaChangeOptions, SchemaChangeTarget, SchemaChangeType,
+    };
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType, UnavailableError};
+    use cassandra_protocol::frame::message_request::RequestBody;
+    use cassandra_protocol::frame::message_result::{
+        BodyResResultPrepared, BodyResResultRows, ColType, PreparedMetadata, ResResultBody,
+        ResultKind, RowsMetadata, RowsMetadataFlags,
+    };
+    use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+    use cassandra_protocol::types::{CBytes, CBytesShort, CInt};
+    use futures::FutureExt;
+    use std::collections::HashMap;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::broadcast::channel;
+    use tokio::sync::mpsc::unbounded_channel;
+    use uuid::Uuid;
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::connection_pool::ConnectionPoolFactory;
+    use crate::cluster::prepared_statement_cache::PreparedStatementCache;
+    use crate::cluster::session::{
+        create_keyspace_holder, is_schema_change, prepare_flags, Session,
+    };
+    use crate::cluster::topology::{Node, NodeDistance, NodeState};
+    use crate::cluster::{ClusterMetadata, ClusterMetadataManager, QueryResult, SessionContext};
+    use crate::interceptor::{RequestInfo, RequestInterceptor, RequestKind};
+    use crate::load_balancing::node_distance_evaluator::AllLocalNodeDistanceEvaluator;
+    use crate::load_balancing::{
+        InitializingWrapperLoadBalancingStrategy, RoundRobinLoadBalancingStrategy,
+    };
+    use crate::metrics::{InMemoryMetricsRecorder, NoopMetricsRecorder};
+    use crate::request_tracker::{AttemptInfo, RequestReport, RequestTracker};
+    use crate::retry::{
+        DefaultRetryPolicy, DowngradingConsistencyRetryPolicy, MockReconnectionPolicy,
+        RetryDecision, RetryPolicy,
+    };
+    use crate::statement::{StatementParams, StatementParamsBuilder};
+    use crate::test_utils::{create_rows_envelope, SpanRecorder};
+    use crate::transport::MockCdrsTransport;
+
+    type MockSession = Session<
+        MockCdrsTransport,
+        MockConnectionManager<MockCdrsTransport>,
+        RoundRobinLoadBalancingStrategy<
+            MockCdrsTransport,
+            MockConnectionManager<MockCdrsTransport>,
+        >,
+    >;
+
+    const NODE_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+
+    fn create_void_result() -> Envelope {
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            CInt::from(ResultKind::Void).to_be_bytes().to_vec(),
+            None,
+            vec![],
+        )
+    }
+
+    fn create_rows_result(paging_state: Option<CBytes>) -> Envelope {
+        let mut flags = RowsMetadataFlags::NO_METADATA;
+        if paging_state.is_some() {
+            flags.insert(RowsMetadataFlags::HAS_MORE_PAGES);
+        }
+
+        let body = ResResultBody::Rows(BodyResResultRows {
+            metadata: RowsMetadata {
+                flags,
+                columns_count: 0,
+                paging_state,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![],
+            },
+            rows_count: 0,
+            rows_content: vec![],
+            protocol_version: Version::V4,
+        });
+
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec(Version::V4),
+            None,
+            vec![],
+        )
+    }
+
+    fn create_schema_change_result() -> Envelope {
+        let body = ResResultBody::SchemaChange(SchemaChange {
+            change_type: SchemaChangeType::Created,
+            target: SchemaChangeTarget::Table,
+            options: SchemaChangeOptions::TableType("ks".into(), "t".into()),
+        });
+
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec(Version::V4),
+            None,
+            vec![],
+        )
+    }
+
+    fn create_prepared_result() -> Envelope {
+        let body = ResResultBody::Prepared(BodyResResultPrepared {
+            id: CBytesShort::new(b"id".to_vec()),
+            result_metadata_id: None,
+            metadata: PreparedMetadata {
+                pk_indexes: vec![],
+                global_table_spec: None,
+                col_specs: vec![],
+            },
+            result_metadata: RowsMetadata {
+                flags: RowsMetadataFlags::NO_METADATA,
+                columns_count: 0,
+                paging_state: None,
+                new_metadata_id: None,
+                global_table_spec: None,
+                col_specs: vec![],
+            },
+        });
+
+        Envelope::new(
+            Version::V4,
+            Direction::Response,
+            Flags::empty(),
+            Opcode::Result,
+            0,
+            body.serialize_to_vec(Version::V4),
+            None,
+            vec![],
+        )
+    }
+
+    fn create_unavailable_error() -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "Cannot achieve consistency level QUORUM".into(),
+                ty: ErrorType::Unavailable(UnavailableError {
+                    cl: Consistency::Quorum,
+                    required: 2,
+                    alive: 1,
+                }),
+            },
+            addr: NODE_ADDRESS,
+        }
+    }
+
+    fn create_transport(
+        respond: Arc<impl Fn(&Envelope) -> Result<Envelope> + Send + Sync + 'static>,
+        addr: SocketAddr,
+    ) -> MockCdrsTransport {
+        let mut transport = MockCdrsTransport::new();
+        transport
+            .expect_write_envelope()
+            .returning(move |envelope, _| {
+                let response = respond(envelope);
+                async move { response }.boxed()
+            });
+        transport.expect_is_broken().return_const(false);
+        transport.expect_address().return_const(addr);
+        transport
+    }
+
+    // creates a session with a single contact point, which responds to requests using given
+    // function; the contact point is also used as the control connection
+    fn create_session(
+        respond: impl Fn(&Envelope) -> Result<Envelope> + Send + Sync + 'static,
+        retry_policy: Box<dyn RetryPolicy + Send + Sync>,
+        request_interceptors: Vec<Arc<dyn RequestInterceptor + Send + Sync>>,
+        request_tracker: Option<Arc<dyn RequestTracker + Send + Sync>>,
+    ) -> MockSession {
+        let respond = Arc::new(respond);
+
+        let session_context = Arc::new(SessionContext::default());
+        session_context
+            .control_connection_transport
+            .store(Some(Arc::new(create_transport(
+                respond.clone(),
+                NODE_ADDRESS,
+            ))));
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let transport = create_transport(respond.clone(), addr);
+                async move { Ok(transport) }.boxed()
+            });
+
+        let (keyspace_holder, keyspace_receiver) = create_keyspace_holder();
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Version::V4,
+            connection_manager,
+            keyspace_receiver,
+            Arc::new(MockReconnectionPolicy::new()),
+            Arc::new(NoopMetricsRecorder),
+        ));
+
+        let contact_points = vec![Arc::new(Node::new_with_state(
+            connection_pool_factory.clone(),
+            NODE_ADDRESS,
+            None,
+            None,
+            Some(NodeDistance::Local),
+            NodeState::Up,
+            Default::default(),
+            "".into(),
+            "".into(),
+        ))];
+
+        let cluster_metadata_manager = Arc::new(ClusterMetadataManager::new(
+            contact_points.clone(),
+            connection_pool_factory,
+            session_context,
+            Box::new(AllLocalNodeDistanceEvaluator),
+            None,
+            Version::V4,
+            false,
+            unbounded_channel().0,
+        ));
+
+        Session {
+            load_balancing: Arc::new(InitializingWrapperLoadBalancingStrategy::new(
+                RoundRobinLoadBalancingStrategy::new(),
+                contact_points,
+            )),
+            keyspace_holder,
+            retry_policy,
+            speculative_execution_policy: None,
+            request_timeout: None,
+            schema_agreement_timeout: None,
+            timestamp_generator: None,
+            metrics_recorder: Arc::new(NoopMetricsRecorder),
+            request_interceptors,
+            request_tracker,
+            prepared_statement_cache: Arc::new(PreparedStatementCache::new(1)),
+            prepare_on_all_nodes: false,
+            control_connection_handle: tokio::spawn(async {}),
+            event_sender: channel(1).0,
+            cluster_metadata_manager,
+            _transport: Default::default(),
+            _connection_manager: Default::default(),
+            version: Version::V4,
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingTracker {
+        reports: Mutex<Vec<(Vec<AttemptInfo>, bool)>>,
+    }
+
+    impl RequestTracker for RecordingTracker {
+        fn on_request_complete(&self, report: &RequestReport) {
+            assert_eq!(
+                report.coordinator(),
+                report.attempts.last().map(|attempt| attempt.node)
+            );
+
+            self.reports
+                .lock()
+                .unwrap()
+                .push((report.attempts.to_vec(), report.result.is_ok()));
+        }
+    }
+
+    #[tokio::test]
+    async fn should_track_successful_request() {
+        let tracker = Arc::new(RecordingTracker::default());
+        let session = create_session(
+            |_| Ok(create_void_result()),
+            Box::new(DefaultRetryPolicy),
+            vec![],
+            Some(tracker.clone()),
+        );
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+
+        let reports = tracker.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let (attempts, is_ok) = &reports[0];
+        assert!(is_ok);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].node, NODE_ADDRESS);
+        assert!(attempts[0].error.is_none());
+        assert!(attempts[0].retry_decision.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_track_failed_request() {
+        let tracker = Arc::new(RecordingTracker::default());
+        let session = create_session(
+            |_| Err(create_unavailable_error()),
+            Box::new(DefaultRetryPolicy),
+            vec![],
+            Some(tracker.clone()),
+        );
+
+        assert!(session.query("SELECT * FROM ks.t").await.is_err());
+
+        let reports = tracker.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let (attempts, is_ok) = &reports[0];
+        assert!(!is_ok);
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].node, NODE_ADDRESS);
+        assert!(matches!(attempts[0].error, Some(Error::Server { .. })));
+        assert_eq!(
+            attempts[0].retry_decision,
+            Some(RetryDecision::RetryNextNode)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_track_retried_request() {
+        let tracker = Arc::new(RecordingTracker::default());
+        let requests = AtomicUsize::new(0);
+        let session = create_session(
+            move |_| {
+                if requests.fetch_add(1, Ordering::Relaxed) == 0 {
+                    Err(create_unavailable_error())
+                } else {
+                    Ok(create_void_result())
+                }
+            },
+            Box::new(DowngradingConsistencyRetryPolicy),
+            vec![],
+            Some(tracker.clone()),
+        );
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+
+        let reports = tracker.reports.lock().unwrap();
+        assert_eq!(reports.len(), 1);
+
+        let (attempts, is_ok) = &reports[0];
+        assert!(is_ok);
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts[0].error.is_some());
+        assert_eq!(
+            attempts[0].retry_decision,
+            Some(RetryDecision::RetryWithConsistency(Consistency::One))
+        );
+        assert!(attempts[1].error.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_carry_paging_state_between_pages() {
+        let paging_state = CBytes::new(vec![1, 2, 3]);
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let session = {
+            let paging_state = paging_state.clone();
+            let requests = requests.clone();
+            create_session(
+                move |envelope| {
+                    let query_params = match envelope.request_body().unwrap() {
+                        RequestBody::Query(body) => body.query_params,
+                        body => panic!("Unexpected request: {:?}", body),
+                    };
+
+                    // only the first page has more pages
+                    let next_paging_state = if query_params.paging_state.is_none() {
+                        Some(paging_state.clone())
+                    } else {
+                        None
+                    };
+
+                    requests.lock().unwrap().push(query_params);
+                    Ok(create_rows_result(next_paging_state))
+                },
+                Box::new(DefaultRetryPolicy),
+                vec![],
+                None,
+            )
+        };
+
+        let mut pager = session.paged(10);
+        let mut query_pager = pager.query_with_params(
+            "SELECT * FROM ks.t",
+            StatementParamsBuilder::new()
+                .with_consistency(Consistency::Two)
+                .build(),
+        );
+
+        query_pager.next().await.unwrap();
+        assert!(query_pager.has_more());
+        assert_eq!(
+            query_pager.pager_state().cursor(),
+            Some(paging_state.clone())
+        );
+
+        query_pager.next().await.unwrap();
+        assert!(!query_pager.has_more());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].paging_state, None);
+        assert_eq!(requests[1].paging_state, Some(paging_state));
+
+        for query_params in requests.iter() {
+            assert_eq!(query_params.page_size, Some(10));
+            assert_eq!(query_params.consistency, Consistency::Two);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_create_attempt_spans_within_request_span() {
+        let span_recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(span_recorder.clone());
+
+        let requests = AtomicUsize::new(0);
+        let session = create_session(
+            move |_| {
+                if requests.fetch_add(1, Ordering::Relaxed) == 0 {
+                    Err(create_unavailable_error())
+                } else {
+                    Ok(create_void_result())
+                }
+            },
+            Box::new(DowngradingConsistencyRetryPolicy),
+            vec![],
+            None,
+        );
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+
+        let request_spans = span_recorder.find_spans("cassandra.request");
+        assert_eq!(request_spans.len(), 1);
+
+        let request_span = &request_spans[0];
+        assert_eq!(request_span.fields["db.statement"], "SELECT * FROM ks.t");
+        assert!(!request_span.fields.contains_key("error"));
+
+        let attempt_spans = span_recorder.find_spans("cassandra.attempt");
+        assert_eq!(attempt_spans.len(), 2);
+
+        for (attempt, attempt_span) in attempt_spans.iter().enumerate() {
+            assert_eq!(attempt_span.parent, Some(request_span.id));
+            assert_eq!(
+                attempt_span.fields["db.cassandra.attempt"],
+                (attempt + 1).to_string()
+            );
+            assert_eq!(
+                attempt_span.fields["db.cassandra.coordinator.address"],
+                NODE_ADDRESS.to_string()
+            );
+        }
+
+        assert!(attempt_spans[0].fields.contains_key("error"));
+        assert!(attempt_spans[0]
+            .fields
+            .contains_key("db.cassandra.retry_decision"));
+        assert!(!attempt_spans[1].fields.contains_key("error"));
+    }
 
     #[test]
     fn prepare_flags_test() {
@@ -1375,4 +2839,424 @@ mod tests {
         assert!(all.contains(Flags::WARNING));
         assert!(all.contains(Flags::BETA));
     }
+
+    #[test]
+    fn is_schema_change_test() {
+        let create_envelope = |opcode, result_kind: ResultKind| {
+            Envelope::new(
+                Version::V4,
+                Direction::Response,
+                Flags::empty(),
+                opcode,
+                0,
+                CInt::from(result_kind).to_be_bytes().to_vec(),
+                None,
+                vec![],
+            )
+        };
+
+        assert!(is_schema_change(&create_envelope(
+            Opcode::Result,
+            ResultKind::SchemaChange
+        )));
+        assert!(!is_schema_change(&create_envelope(
+            Opcode::Result,
+            ResultKind::Void
+        )));
+        assert!(!is_schema_change(&create_envelope(
+            Opcode::Event,
+            ResultKind::SchemaChange
+        )));
+    }
+
+    // responds to schema version queries with given versions of the node and its single peer,
+    // to schema changing queries with a schema change and to others with a void result
+    fn respond_with_schema_versions(
+        local: Uuid,
+        peer: Uuid,
+    ) -> impl Fn(&Envelope) -> Result<Envelope> + Send + Sync + 'static {
+        move |envelope| {
+            let query = match envelope.request_body()? {
+                RequestBody::Query(body) => body.query,
+                body => panic!("Unexpected request: {:?}", body),
+            };
+
+            if query.contains("system.local") {
+                Ok(create_rows_envelope(
+                    &[("schema_version", ColType::Uuid)],
+                    vec![vec![CBytes::new(local.as_bytes().to_vec())]],
+                ))
+            } else if query.contains("system.peers_v2") {
+                Ok(create_rows_envelope(
+                    &[
+                        ("native_address", ColType::Inet),
+                        ("schema_version", ColType::Uuid),
+                    ],
+                    vec![vec![
+                        CBytes::new(vec![127, 0, 0, 2]),
+                        CBytes::new(peer.as_bytes().to_vec()),
+                    ]],
+                ))
+            } else if query.starts_with("CREATE") {
+                Ok(create_schema_change_result())
+            } else {
+                Ok(create_void_result())
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn should_wait_for_schema_agreement_after_schema_change() {
+        let schema_version = Uuid::new_v4();
+
+        for (peer_schema_version, schema_agreement) in
+            [(schema_version, true), (Uuid::new_v4(), false)]
+        {
+            let mut session = create_session(
+                respond_with_schema_versions(schema_version, peer_schema_version),
+                Box::new(DefaultRetryPolicy),
+                vec![],
+                None,
+            );
+            session.schema_agreement_timeout = Some(Duration::ZERO);
+
+            let result = session
+                .query("CREATE TABLE ks.t (id int PRIMARY KEY)")
+                .await
+                .unwrap();
+            assert_eq!(result.schema_agreement(), Some(schema_agreement));
+
+            let result = session
+                .query("INSERT INTO ks.t (id) VALUES (1)")
+                .await
+                .unwrap();
+            assert_eq!(result.schema_agreement(), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_wait_for_schema_agreement_without_timeout() {
+        let session = create_session(
+            respond_with_schema_versions(Uuid::new_v4(), Uuid::new_v4()),
+            Box::new(DefaultRetryPolicy),
+            vec![],
+            None,
+        );
+
+        let result = session
+            .query("CREATE TABLE ks.t (id int PRIMARY KEY)")
+            .await
+            .unwrap();
+        assert_eq!(result.schema_agreement(), None);
+    }
+
+    // creates a node with given address and state, which responds to requests using given function
+    // and records the opcodes of received requests
+    fn create_node(
+        address: SocketAddr,
+        state: NodeState,
+        respond: fn() -> Result<Envelope>,
+        requests: Arc<Mutex<Vec<(SocketAddr, Opcode)>>>,
+    ) -> Node<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>> {
+        let respond = Arc::new(move |envelope: &Envelope| {
+            requests.lock().unwrap().push((address, envelope.opcode));
+            respond()
+        });
+
+        let mut connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        connection_manager
+            .expect_connection()
+            .returning(move |_, _, addr| {
+                let transport = create_transport(respond.clone(), addr);
+                async move { Ok(transport) }.boxed()
+            });
+
+        let (_, keyspace_receiver) = create_keyspace_holder();
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Version::V4,
+            connection_manager,
+            keyspace_receiver,
+            Arc::new(MockReconnectionPolicy::new()),
+            Arc::new(NoopMetricsRecorder),
+        ));
+
+        Node::new_with_state(
+            connection_pool_factory,
+            address,
+            None,
+            Some(Uuid::new_v4()),
+            Some(NodeDistance::Local),
+            state,
+            Default::default(),
+            "".into(),
+            "".into(),
+        )
+    }
+
+    #[tokio::test]
+    async fn should_prepare_on_all_up_nodes() {
+        let requests = Arc::new(Mutex::new(vec![]));
+        let address =
+            |last_octet| SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, last_octet)), 9042);
+
+        let nodes = vec![
+            create_node(
+                address(2),
+                NodeState::Up,
+                || Ok(create_prepared_result()),
+                requests.clone(),
+            ),
+            create_node(
+                address(3),
+                NodeState::Up,
+                || Err(Error::General("cannot prepare".into())),
+                requests.clone(),
+            ),
+            create_node(
+                address(4),
+                NodeState::Up,
+                || Ok(create_prepared_result()),
+                requests.clone(),
+            ),
+            create_node(
+                address(5),
+                NodeState::Down,
+                || Ok(create_prepared_result()),
+                requests.clone(),
+            ),
+        ];
+
+        let mut session = create_session(
+            |envelope| panic!("Unexpected request: {:?}", envelope),
+            Box::new(DefaultRetryPolicy),
+            vec![],
+            None,
+        );
+        session.prepare_on_all_nodes = true;
+        session
+            .cluster_metadata_manager
+            .store_metadata(ClusterMetadata::new(
+                nodes
+                    .into_iter()
+                    .map(|node| (node.host_id().unwrap(), Arc::new(node)))
+                    .collect(),
+                Default::default(),
+            ));
+
+        let prepared = session.prepare("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(prepared.id, CBytesShort::new(b"id".to_vec()));
+
+        let mut requests = requests.lock().unwrap().clone();
+        requests.sort_unstable_by_key(|(address, _)| *address);
+        assert_eq!(
+            requests,
+            vec![
+                (address(2), Opcode::Prepare),
+                (address(3), Opcode::Prepare),
+                (address(4), Opcode::Prepare),
+            ]
+        );
+    }
+
+    fn query_consistency(envelope: &Envelope) -> Consistency {
+        match envelope.request_body().unwrap() {
+            RequestBody::Query(body) => body.query_params.consistency,
+            body => panic!("Unexpected request: {:?}", body),
+        }
+    }
+
+    struct ShortCircuitingInterceptor;
+
+    impl RequestInterceptor for ShortCircuitingInterceptor {
+        fn before_request(
+            &self,
+            request: &RequestInfo,
+            _parameters: &mut StatementParams,
+        ) -> Option<Result<QueryResult>> {
+            (request.kind == RequestKind::Query)
+                .then(|| Ok(QueryResult::new(create_void_result(), NODE_ADDRESS)))
+        }
+    }
+
+    struct ConsistencyInterceptor(Consistency);
+
+    impl RequestInterceptor for ConsistencyInterceptor {
+        fn before_request(
+            &self,
+            _request: &RequestInfo,
+            parameters: &mut StatementParams,
+        ) -> Option<Result<QueryResult>> {
+            parameters.query_params.consistency = self.0;
+            None
+        }
+    }
+
+    struct SyntheticResponseInterceptor;
+
+    impl RequestInterceptor for SyntheticResponseInterceptor {
+        fn before_attempt(
+            &self,
+            _envelope: &Envelope,
+            _node: SocketAddr,
+            _attempt: usize,
+        ) -> Option<Result<Envelope>> {
+            Some(Ok(create_void_result()))
+        }
+    }
+
+    struct OrderRecordingInterceptor {
+        id: usize,
+        order: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl RequestInterceptor for OrderRecordingInterceptor {
+        fn after_response(&self, _request: &RequestInfo, _result: &Result<QueryResult>) {
+            self.order.lock().unwrap().push(self.id);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_short_circuit_request() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let session = create_session(
+            {
+                let requests = requests.clone();
+                move |_| {
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    Ok(create_void_result())
+                }
+            },
+            Box::new(DefaultRetryPolicy),
+            vec![Arc::new(ShortCircuitingInterceptor)],
+            None,
+        );
+
+        let result = session.query("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(result.coordinator(), NODE_ADDRESS);
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn should_rewrite_request_parameters() {
+        let consistencies = Arc::new(Mutex::new(vec![]));
+        let session = create_session(
+            {
+                let consistencies = consistencies.clone();
+                move |envelope| {
+                    consistencies
+                        .lock()
+                        .unwrap()
+                        .push(query_consistency(envelope));
+                    Ok(create_void_result())
+                }
+            },
+            Box::new(DefaultRetryPolicy),
+            vec![
+                Arc::new(ConsistencyInterceptor(Consistency::Two)),
+                Arc::new(ConsistencyInterceptor(Consistency::All)),
+            ],
+            None,
+        );
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(*consistencies.lock().unwrap(), vec![Consistency::All]);
+    }
+
+    #[tokio::test]
+    async fn should_not_connect_for_synthetic_response() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let mut session = create_session(
+            {
+                let requests = requests.clone();
+                move |_| {
+                    requests.fetch_add(1, Ordering::Relaxed);
+                    Ok(create_void_result())
+                }
+            },
+            Box::new(DefaultRetryPolicy),
+            vec![Arc::new(SyntheticResponseInterceptor)],
+            None,
+        );
+        session.metrics_recorder = Arc::new(InMemoryMetricsRecorder::new());
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(requests.load(Ordering::Relaxed), 0);
+
+        let metrics = session.metrics().unwrap();
+        assert_eq!(metrics.request_latency.count(), 1);
+        assert!(metrics.nodes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn should_call_after_response_in_reverse_order() {
+        let order = Arc::new(Mutex::new(vec![]));
+        let session = create_session(
+            |_| Ok(create_void_result()),
+            Box::new(DefaultRetryPolicy),
+            (1..=3)
+                .map(|id| {
+                    Arc::new(OrderRecordingInterceptor {
+                        id,
+                        order: order.clone(),
+                    }) as Arc<dyn RequestInterceptor + Send + Sync>
+                })
+                .collect(),
+            None,
+        );
+
+        session.query("SELECT * FROM ks.t").await.unwrap();
+        assert_eq!(*order.lock().unwrap(), vec![3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn should_keep_custom_payload_on_consistency_downgrade() {
+        let custom_payload = HashMap::from([("key".to_string(), vec![1, 2, 3])]);
+        let requests = Arc::new(Mutex::new(vec![]));
+        let session = create_session(
+            {
+                let requests = requests.clone();
+                move |envelope| {
+                    // decode what would be sent over the wire
+                    let encoded = envelope.encode_with(Compression::None).unwrap();
+                    let decoded = Envelope::from_buffer(&encoded, Compression::None)
+                        .unwrap()
+                        .envelope;
+
+                    let mut requests = requests.lock().unwrap();
+                    requests.push((
+                        query_consistency(&decoded),
+                        decoded.custom_payload().clone(),
+                    ));
+
+                    if requests.len() == 1 {
+                        Err(create_unavailable_error())
+                    } else {
+                        Ok(create_void_result())
+                    }
+                }
+            },
+            Box::new(DowngradingConsistencyRetryPolicy),
+            vec![],
+            None,
+        );
+
+        let parameters = StatementParamsBuilder::new()
+            .with_consistency(Consistency::Quorum)
+            .with_custom_payload(custom_payload.clone())
+            .build();
+
+        session
+            .query_with_params("SELECT * FROM ks.t", parameters)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                (Consistency::Quorum, custom_payload.clone()),
+                (Consistency::One, custom_payload),
+            ]
+        );
+    }
 }
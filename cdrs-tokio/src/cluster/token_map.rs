@@ -1,16 +1,137 @@
+use fxhash::{FxHashMap, FxHashSet};
+use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fmt::{Debug, Formatter};
 use std::net::SocketAddr;
+use std::ops::Bound;
 use std::sync::Arc;
 
-use crate::cluster::topology::{Node, NodeMap};
+use crate::cluster::topology::{DatacenterMetadata, Node, NodeMap, ReplicationStrategy};
 use crate::cluster::ConnectionManager;
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 use crate::transport::CdrsTransport;
 
+/// Range of tokens owned by the same replicas. The start is exclusive, while the end is inclusive.
+/// If the start is not lower than the end, the range wraps around the ring.
+pub struct TokenRange<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
+    start: Token,
+    end: Token,
+    replicas: Vec<Arc<Node<T, CM>>>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> Clone for TokenRange<T, CM> {
+    fn clone(&self) -> Self {
+        TokenRange {
+            start: self.start.clone(),
+            end: self.end.clone(),
+            replicas: self.replicas.clone(),
+        }
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> Debug for TokenRange<T, CM> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenRange")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .field("replicas", &self.replicas)
+            .finish()
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenRange<T, CM> {
+    /// Returns the exclusive start of the range.
+    #[inline]
+    pub fn start(&self) -> &Token {
+        &self.start
+    }
+
+    /// Returns the inclusive end of the range.
+    #[inline]
+    pub fn end(&self) -> &Token {
+        &self.end
+    }
+
+    /// Returns replicas of the range, in the order of the ring.
+    #[inline]
+    pub fn replicas(&self) -> &[Arc<Node<T, CM>>] {
+        &self.replicas
+    }
+}
+
+/// Precomputed replicas of all token ranges of the ring, for a given replication strategy.
+pub struct ReplicaMap<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
+    token_ranges: Vec<TokenRange<T, CM>>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> Clone for ReplicaMap<T, CM> {
+    fn clone(&self) -> Self {
+        ReplicaMap {
+            token_ranges: self.token_ranges.clone(),
+        }
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> Debug for ReplicaMap<T, CM> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReplicaMap")
+            .field("token_ranges", &self.token_ranges)
+            .finish()
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>> ReplicaMap<T, CM> {
+    /// Returns replicas for given token, in the order of the ring.
+    pub fn replicas(&self, token: &Token) -> &[Arc<Node<T, CM>>] {
+        let index = self
+            .token_ranges
+            .partition_point(|token_range| token_range.end < *token);
+
+        // tokens after the last one belong to the wrapping range
+        self.token_ranges
+            .get(index)
+            .or_else(|| self.token_ranges.first())
+            .map(|token_range| token_range.replicas())
+            .unwrap_or_default()
+    }
+
+    /// Returns all token ranges, ordered by their end tokens.
+    #[inline]
+    pub fn token_ranges(&self) -> &[TokenRange<T, CM>] {
+        &self.token_ranges
+    }
+
+    /// Creates a new map with a node replaced by another one with the same host id. The new node
+    /// must have the same tokens and placement, since replicas are not recomputed.
+    #[must_use]
+    pub fn clone_with_replaced_node(&self, node: &Arc<Node<T, CM>>) -> Self {
+        let token_ranges = self
+            .token_ranges
+            .iter()
+            .map(|token_range| TokenRange {
+                start: token_range.start.clone(),
+                end: token_range.end.clone(),
+                replicas: token_range
+                    .replicas
+                    .iter()
+                    .map(|replica| {
+                        if replica.host_id().is_some() && replica.host_id() == node.host_id() {
+                            node.clone()
+                        } else {
+                            replica.clone()
+                        }
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        ReplicaMap { token_ranges }
+    }
+}
+
 /// Map of tokens to nodes.
 pub struct TokenMap<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
-    token_ring: BTreeMap<Murmur3Token, Arc<Node<T, CM>>>,
+    token_ring: BTreeMap<Token, Arc<Node<T, CM>>>,
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>> Clone for TokenMap<T, CM> {
@@ -45,7 +166,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
                 .flat_map(|(_, node)| {
                     node.tokens()
                         .iter()
-                        .map(move |token| (*token, node.clone()))
+                        .map(move |token| (token.clone(), node.clone()))
                 })
                 .collect(),
         }
@@ -54,34 +175,75 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
     /// Returns local nodes starting at given token and going in the direction of replicas.
     pub fn nodes_for_token_capped(
         &self,
-        token: Murmur3Token,
+        token: &Token,
         replica_count: usize,
     ) -> impl Iterator<Item = Arc<Node<T, CM>>> + '_ {
         self.token_ring
-            .range(token..)
+            .range::<Token, _>((Bound::Included(token), Bound::Unbounded))
             .chain(self.token_ring.iter())
             .take(replica_count)
             .map(|(_, node)| node.clone())
     }
 
     /// Returns local nodes starting at given token and going in the direction of replicas.
-    pub fn nodes_for_token(
-        &self,
-        token: Murmur3Token,
-    ) -> impl Iterator<Item = Arc<Node<T, CM>>> + '_ {
+    pub fn nodes_for_token(&self, token: &Token) -> impl Iterator<Item = Arc<Node<T, CM>>> + '_ {
         self.token_ring
-            .range(token..)
+            .range::<Token, _>((Bound::Included(token), Bound::Unbounded))
             .chain(self.token_ring.iter())
             .take(self.token_ring.len())
             .map(|(_, node)| node.clone())
     }
 
+    /// Computes replicas of all token ranges for given replication strategy.
+    pub fn replica_map(
+        &self,
+        replication_strategy: &ReplicationStrategy,
+        datacenters: &FxHashMap<String, DatacenterMetadata>,
+    ) -> ReplicaMap<T, CM> {
+        let ring = self.token_ring.iter().collect_vec();
+        let token_ranges = (0..ring.len())
+            .map(|index| {
+                let (start, _) = ring[(index + ring.len() - 1) % ring.len()];
+                let (end, _) = ring[index];
+
+                // walk the ring starting at the end of the range, visiting every node once
+                let nodes = ring[index..]
+                    .iter()
+                    .chain(&ring[..index])
+                    .map(|(_, node)| *node)
+                    .unique_by(|node| Arc::as_ptr(node));
+
+                let replicas = match replication_strategy {
+                    ReplicationStrategy::SimpleStrategy { replication_factor } => {
+                        nodes.take(*replication_factor).cloned().collect()
+                    }
+                    ReplicationStrategy::NetworkTopologyStrategy {
+                        datacenter_replication_factor,
+                    } => network_topology_strategy_replicas(
+                        nodes,
+                        datacenter_replication_factor.clone(),
+                        datacenters,
+                    ),
+                    ReplicationStrategy::Other => nodes.take(1).cloned().collect(),
+                };
+
+                TokenRange {
+                    start: start.clone(),
+                    end: end.clone(),
+                    replicas,
+                }
+            })
+            .collect();
+
+        ReplicaMap { token_ranges }
+    }
+
     /// Creates a new map with a new node inserted.
     #[must_use]
     pub fn clone_with_node(&self, node: Arc<Node<T, CM>>) -> Self {
         let mut map = self.clone();
         for token in node.tokens() {
-            map.token_ring.insert(*token, node.clone());
+            map.token_ring.insert(token.clone(), node.clone());
         }
 
         map
@@ -97,7 +259,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
                 if node.broadcast_rpc_address() == broadcast_rpc_address {
                     None
                 } else {
-                    Some((*token, node.clone()))
+                    Some((token.clone(), node.clone()))
                 }
             })
             .collect();
@@ -106,9 +268,65 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TokenMap<T, CM> {
     }
 }
 
+fn network_topology_strategy_replicas<'a, T: CdrsTransport, CM: ConnectionManager<T>>(
+    nodes: impl Iterator<Item = &'a Arc<Node<T, CM>>>,
+    mut datacenter_replication_factor: FxHashMap<String, usize>,
+    datacenters: &FxHashMap<String, DatacenterMetadata>,
+) -> Vec<Arc<Node<T, CM>>> {
+    let desired_replica_count = datacenter_replication_factor.values().sum();
+    let mut same_rack_replicas: FxHashMap<String, usize> = datacenter_replication_factor
+        .iter()
+        .map(|(dc, replication_factor)| {
+            let rack_count = datacenters.get(dc).map(|dc| dc.rack_count).unwrap_or(0);
+            (dc.into(), replication_factor.saturating_sub(rack_count))
+        })
+        .collect();
+
+    let mut result = Vec::with_capacity(desired_replica_count);
+    let mut used_dc_racks: FxHashSet<(&str, &str)> = Default::default();
+
+    for node in nodes {
+        if let Some(datacenter_replication_factor) =
+            datacenter_replication_factor.get_mut(node.datacenter())
+        {
+            if *datacenter_replication_factor == 0 {
+                // found enough nodes in this datacenter
+                continue;
+            }
+
+            let current_node_dc = node.datacenter();
+            let current_node_rack = node.rack();
+
+            if used_dc_racks.contains(&(current_node_dc, current_node_rack)) {
+                // check if we need to put nodes from the same rack multiple times to meet
+                // the replication factor
+                if let Some(same_rack_replicas) = same_rack_replicas.get_mut(current_node_dc) {
+                    if *same_rack_replicas > 0 {
+                        *same_rack_replicas -= 1;
+                        *datacenter_replication_factor -= 1;
+                        result.push(node.clone());
+                    }
+                }
+            } else {
+                *datacenter_replication_factor -= 1;
+
+                used_dc_racks.insert((current_node_dc, current_node_rack));
+                result.push(node.clone());
+            }
+
+            if result.len() == desired_replica_count {
+                break;
+            }
+        }
+    }
+
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use cassandra_protocol::frame::Version;
+    use fxhash::FxHashMap;
     use itertools::Itertools;
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use std::sync::{Arc, LazyLock};
@@ -117,9 +335,10 @@ mod tests {
 
     use crate::cluster::connection_manager::MockConnectionManager;
     use crate::cluster::connection_pool::ConnectionPoolFactory;
-    use crate::cluster::topology::{Node, NodeMap};
-    use crate::cluster::Murmur3Token;
+    use crate::cluster::topology::{DatacenterMetadata, Node, NodeMap, ReplicationStrategy};
     use crate::cluster::TokenMap;
+    use crate::cluster::{Murmur3Token, Token};
+    use crate::metrics::NoopMetricsRecorder;
     use crate::retry::MockReconnectionPolicy;
     use crate::transport::MockCdrsTransport;
 
@@ -137,6 +356,7 @@ mod tests {
             connection_manager,
             keyspace_receiver,
             Arc::new(reconnection_policy),
+            Arc::new(NoopMetricsRecorder),
         ));
 
         let mut nodes = NodeMap::default();
@@ -149,9 +369,9 @@ mod tests {
                 Some(*HOST_ID_1),
                 None,
                 vec![
-                    Murmur3Token::new(-2),
-                    Murmur3Token::new(-1),
-                    Murmur3Token::new(0),
+                    Murmur3Token::new(-2).into(),
+                    Murmur3Token::new(-1).into(),
+                    Murmur3Token::new(0).into(),
                 ],
                 "".into(),
                 "".into(),
@@ -165,7 +385,7 @@ mod tests {
                 None,
                 Some(*HOST_ID_2),
                 None,
-                vec![Murmur3Token::new(20)],
+                vec![Murmur3Token::new(20).into()],
                 "".into(),
                 "".into(),
             )),
@@ -179,9 +399,9 @@ mod tests {
                 Some(*HOST_ID_3),
                 None,
                 vec![
-                    Murmur3Token::new(2),
-                    Murmur3Token::new(1),
-                    Murmur3Token::new(10),
+                    Murmur3Token::new(2).into(),
+                    Murmur3Token::new(1).into(),
+                    Murmur3Token::new(10).into(),
                 ],
                 "".into(),
                 "".into(),
@@ -191,10 +411,10 @@ mod tests {
         nodes
     }
 
-    fn verify_tokens(host_ids: &[Uuid], token: Murmur3Token) {
+    fn verify_tokens(host_ids: &[Uuid], token: Token) {
         let token_map = TokenMap::new(&prepare_nodes());
         let nodes = token_map
-            .nodes_for_token_capped(token, host_ids.len())
+            .nodes_for_token_capped(&token, host_ids.len())
             .collect_vec();
 
         assert_eq!(nodes.len(), host_ids.len());
@@ -207,20 +427,95 @@ mod tests {
     fn should_return_replicas_in_order() {
         verify_tokens(
             &[*HOST_ID_1, *HOST_ID_3, *HOST_ID_3, *HOST_ID_3, *HOST_ID_2],
-            Murmur3Token::new(0),
+            Murmur3Token::new(0).into(),
         );
     }
 
     #[test]
     fn should_return_replicas_in_order_for_non_primary_token() {
-        verify_tokens(&[*HOST_ID_3, *HOST_ID_2], Murmur3Token::new(3));
+        verify_tokens(&[*HOST_ID_3, *HOST_ID_2], Murmur3Token::new(3).into());
     }
 
     #[test]
     fn should_return_replicas_in_a_ring() {
         verify_tokens(
             &[*HOST_ID_2, *HOST_ID_1, *HOST_ID_1, *HOST_ID_1, *HOST_ID_3],
-            Murmur3Token::new(20),
+            Murmur3Token::new(20).into(),
+        );
+    }
+
+    fn verify_replicas(replication_strategy: ReplicationStrategy, host_ids: &[Uuid], token: Token) {
+        let mut datacenters = FxHashMap::default();
+        datacenters.insert("".into(), DatacenterMetadata::new(1));
+
+        let replica_map =
+            TokenMap::new(&prepare_nodes()).replica_map(&replication_strategy, &datacenters);
+        let replicas = replica_map
+            .replicas(&token)
+            .iter()
+            .map(|node| node.host_id().unwrap())
+            .collect_vec();
+
+        assert_eq!(replicas, host_ids);
+    }
+
+    #[test]
+    fn should_return_distinct_simple_strategy_replicas() {
+        let replication_strategy = ReplicationStrategy::SimpleStrategy {
+            replication_factor: 2,
+        };
+
+        verify_replicas(
+            replication_strategy.clone(),
+            &[*HOST_ID_1, *HOST_ID_3],
+            Murmur3Token::new(0).into(),
         );
+        verify_replicas(
+            replication_strategy.clone(),
+            &[*HOST_ID_3, *HOST_ID_2],
+            Murmur3Token::new(3).into(),
+        );
+        verify_replicas(
+            replication_strategy,
+            &[*HOST_ID_1, *HOST_ID_3],
+            Murmur3Token::new(21).into(),
+        );
+    }
+
+    #[test]
+    fn should_return_network_topology_strategy_replicas() {
+        let mut datacenter_replication_factor = FxHashMap::default();
+        datacenter_replication_factor.insert("".into(), 2);
+
+        verify_replicas(
+            ReplicationStrategy::NetworkTopologyStrategy {
+                datacenter_replication_factor,
+            },
+            &[*HOST_ID_2, *HOST_ID_1],
+            Murmur3Token::new(20).into(),
+        );
+    }
+
+    #[test]
+    fn should_return_token_ranges() {
+        let replica_map = TokenMap::new(&prepare_nodes()).replica_map(
+            &ReplicationStrategy::SimpleStrategy {
+                replication_factor: 1,
+            },
+            &Default::default(),
+        );
+
+        let token_ranges = replica_map.token_ranges();
+        assert_eq!(token_ranges.len(), 7);
+
+        let first_range = &token_ranges[0];
+        assert_eq!(first_range.start(), &Murmur3Token::new(20).into());
+        assert_eq!(first_range.end(), &Murmur3Token::new(-2).into());
+        assert_eq!(first_range.replicas()[0].host_id(), Some(*HOST_ID_1));
+
+        let last_range = &token_ranges[6];
+        assert_eq!(last_range.start(), &Murmur3Token::new(10).into());
+        assert_eq!(last_range.end(), &Murmur3Token::new(20).into());
+        assert_eq!(last_range.replicas()[0].host_id(), Some(*HOST_ID_2));
     }
 }
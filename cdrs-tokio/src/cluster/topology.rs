@@ -3,20 +3,35 @@ use std::sync::Arc;
 use fxhash::FxHashMap;
 use uuid::Uuid;
 
+mod aggregate_metadata;
 pub mod cluster_metadata;
+mod column_metadata;
 mod datacenter_metadata;
+mod describe;
+mod function_metadata;
+mod index_metadata;
 mod keyspace_metadata;
 mod node;
 mod node_distance;
 mod node_state;
 mod replication_strategy;
+mod table_metadata;
+mod user_type_metadata;
+mod view_metadata;
 
+pub use self::aggregate_metadata::AggregateMetadata;
+pub use self::column_metadata::{ClusteringOrder, ColumnKind, ColumnMetadata};
 pub use self::datacenter_metadata::DatacenterMetadata;
+pub use self::function_metadata::FunctionMetadata;
+pub use self::index_metadata::{IndexKind, IndexMetadata};
 pub use self::keyspace_metadata::KeyspaceMetadata;
 pub use self::node::Node;
 pub use self::node_distance::NodeDistance;
 pub use self::node_state::NodeState;
 pub use self::replication_strategy::ReplicationStrategy;
+pub use self::table_metadata::TableMetadata;
+pub use self::user_type_metadata::{FieldMetadata, UserTypeMetadata};
+pub use self::view_metadata::ViewMetadata;
 
 /// Map from host id to a node.
 pub type NodeMap<T, CM> = FxHashMap<Uuid, Arc<Node<T, CM>>>;
@@ -0,0 +1,42 @@
+use crate::cluster::topology::describe::{qualified_name, quote_identifier};
+
+/// User defined aggregate metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct AggregateMetadata {
+    pub keyspace: String,
+    pub name: String,
+    /// Argument types as CQL.
+    pub argument_types: Vec<String>,
+    pub state_func: String,
+    pub state_type: String,
+    pub final_func: Option<String>,
+    /// Initial state as a CQL literal.
+    pub initcond: Option<String>,
+    pub return_type: String,
+}
+
+impl AggregateMetadata {
+    /// Returns a CQL statement creating this aggregate.
+    pub fn describe(&self) -> String {
+        let mut ddl = format!(
+            "CREATE AGGREGATE {}({})\n    SFUNC {}\n    STYPE {}",
+            qualified_name(&self.keyspace, &self.name),
+            self.argument_types.join(", "),
+            quote_identifier(&self.state_func),
+            self.state_type
+        );
+
+        if let Some(final_func) = &self.final_func {
+            ddl.push_str("\n    FINALFUNC ");
+            ddl.push_str(&quote_identifier(final_func));
+        }
+
+        if let Some(initcond) = &self.initcond {
+            ddl.push_str("\n    INITCOND ");
+            ddl.push_str(initcond);
+        }
+
+        ddl.push_str(";\n");
+        ddl
+    }
+}
@@ -6,8 +6,8 @@ use uuid::Uuid;
 
 use crate::cluster::topology::keyspace_metadata::KeyspaceMetadata;
 use crate::cluster::topology::node::Node;
-use crate::cluster::topology::{DatacenterMetadata, NodeMap};
-use crate::cluster::{ConnectionManager, TokenMap};
+use crate::cluster::topology::{DatacenterMetadata, NodeMap, ReplicationStrategy};
+use crate::cluster::{ConnectionManager, Partitioner, ReplicaMap, Token, TokenMap, TokenRange};
 use crate::transport::CdrsTransport;
 
 fn build_datacenter_info<T: CdrsTransport, CM: ConnectionManager<T>>(
@@ -29,44 +29,124 @@ fn build_datacenter_info<T: CdrsTransport, CM: ConnectionManager<T>>(
         .collect()
 }
 
+fn build_replica_maps<T: CdrsTransport, CM: ConnectionManager<T>>(
+    token_map: &TokenMap<T, CM>,
+    keyspaces: &FxHashMap<String, Arc<KeyspaceMetadata>>,
+    datacenters: &FxHashMap<String, DatacenterMetadata>,
+) -> FxHashMap<String, Arc<ReplicaMap<T, CM>>> {
+    // keyspaces with the same replication strategy share replicas
+    let mut replica_maps: Vec<(&ReplicationStrategy, Arc<ReplicaMap<T, CM>>)> = vec![];
+
+    keyspaces
+        .iter()
+        .map(|(keyspace_name, keyspace)| {
+            let strategy = &keyspace.replication_strategy;
+            let replica_map = match replica_maps.iter().find(|(other, _)| *other == strategy) {
+                Some((_, replica_map)) => replica_map.clone(),
+                None => {
+                    let replica_map = Arc::new(token_map.replica_map(strategy, datacenters));
+                    replica_maps.push((strategy, replica_map.clone()));
+                    replica_map
+                }
+            };
+
+            (keyspace_name.clone(), replica_map)
+        })
+        .collect()
+}
+
 /// Immutable metadata of the Cassandra cluster that this driver instance is connected to.
 #[derive(Debug, Clone)]
 pub struct ClusterMetadata<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static> {
     nodes: NodeMap<T, CM>,
     token_map: TokenMap<T, CM>,
-    keyspaces: FxHashMap<String, KeyspaceMetadata>,
+    keyspaces: FxHashMap<String, Arc<KeyspaceMetadata>>,
     datacenters: FxHashMap<String, DatacenterMetadata>,
+    partitioner: Partitioner,
+    replica_maps: FxHashMap<String, Arc<ReplicaMap<T, CM>>>,
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
-    pub fn new(nodes: NodeMap<T, CM>, keyspaces: FxHashMap<String, KeyspaceMetadata>) -> Self {
+    pub fn new(nodes: NodeMap<T, CM>, keyspaces: FxHashMap<String, Arc<KeyspaceMetadata>>) -> Self {
+        Self::new_with_partitioner(nodes, keyspaces, Default::default())
+    }
+
+    pub fn new_with_partitioner(
+        nodes: NodeMap<T, CM>,
+        keyspaces: FxHashMap<String, Arc<KeyspaceMetadata>>,
+        partitioner: Partitioner,
+    ) -> Self {
         let token_map = TokenMap::new(&nodes);
         let datacenters = build_datacenter_info(&nodes);
+        let replica_maps = build_replica_maps(&token_map, &keyspaces, &datacenters);
         ClusterMetadata {
             nodes,
             token_map,
             keyspaces,
             datacenters,
+            partitioner,
+            replica_maps,
         }
     }
 
+    /// Returns the partitioner used by the cluster.
+    #[inline]
+    pub fn partitioner(&self) -> Partitioner {
+        self.partitioner
+    }
+
     /// Returns current token map.
     #[inline]
     pub fn token_map(&self) -> &TokenMap<T, CM> {
         &self.token_map
     }
 
+    /// Returns replicas for given token in given keyspace, in the order of the ring, if the
+    /// keyspace is known.
+    #[inline]
+    pub fn replicas(&self, keyspace: &str, token: &Token) -> Option<&[Arc<Node<T, CM>>]> {
+        self.replica_maps
+            .get(keyspace)
+            .map(|replica_map| replica_map.replicas(token))
+    }
+
+    /// Returns all token ranges of given keyspace along with their replicas, if the keyspace is
+    /// known. Ranges are ordered by their end tokens.
+    #[inline]
+    pub fn token_ranges(&self, keyspace: &str) -> Option<&[TokenRange<T, CM>]> {
+        self.replica_maps
+            .get(keyspace)
+            .map(|replica_map| replica_map.token_ranges())
+    }
+
     /// Creates a new metadata with a keyspace replaced/added.
     #[must_use]
     pub fn clone_with_keyspace(&self, keyspace_name: String, keyspace: KeyspaceMetadata) -> Self {
+        let replica_map = self
+            .keyspaces
+            .iter()
+            .find(|(_, other)| other.replication_strategy == keyspace.replication_strategy)
+            .and_then(|(other_name, _)| self.replica_maps.get(other_name).cloned())
+            .unwrap_or_else(|| {
+                Arc::new(
+                    self.token_map
+                        .replica_map(&keyspace.replication_strategy, &self.datacenters),
+                )
+            });
+
+        let mut replica_maps = self.replica_maps.clone();
+        replica_maps.insert(keyspace_name.clone(), replica_map);
+
         let mut keyspaces = self.keyspaces.clone();
-        keyspaces.insert(keyspace_name, keyspace);
+        keyspaces.insert(keyspace_name, Arc::new(keyspace));
 
         ClusterMetadata {
             nodes: self.nodes.clone(),
             token_map: self.token_map.clone(),
             keyspaces,
             datacenters: self.datacenters.clone(),
+            partitioner: self.partitioner,
+            replica_maps,
         }
     }
 
@@ -76,33 +156,76 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
         let mut keyspaces = self.keyspaces.clone();
         keyspaces.remove(keyspace);
 
+        let mut replica_maps = self.replica_maps.clone();
+        replica_maps.remove(keyspace);
+
         ClusterMetadata {
             nodes: self.nodes.clone(),
             token_map: self.token_map.clone(),
             keyspaces,
             datacenters: self.datacenters.clone(),
+            partitioner: self.partitioner,
+            replica_maps,
         }
     }
 
-    /// Creates a new metadata with a node replaced/added. The node must have a host id.
+    /// Creates a new metadata with a node replaced/added. The node must have a host id. Replicas
+    /// are recomputed only if the node changed its placement in the ring.
     #[must_use]
     pub fn clone_with_node(&self, node: Node<T, CM>) -> Self {
         let node = Arc::new(node);
-        let token_map = self.token_map.clone_with_node(node.clone());
+        let host_id = node.host_id().expect("Adding a node without host id!");
+
+        let same_placement = self.nodes.get(&host_id).is_some_and(|old_node| {
+            old_node.broadcast_rpc_address() == node.broadcast_rpc_address()
+                && old_node.tokens() == node.tokens()
+                && old_node.datacenter() == node.datacenter()
+                && old_node.rack() == node.rack()
+        });
 
         let mut nodes = self.nodes.clone();
-        nodes.insert(
-            node.host_id().expect("Adding a node without host id!"),
-            node,
-        );
+        nodes.insert(host_id, node.clone());
+
+        if same_placement {
+            let token_map = self.token_map.clone_with_node(node.clone());
+
+            // keep sharing replicas between keyspaces
+            let mut replaced: FxHashMap<*const ReplicaMap<T, CM>, Arc<ReplicaMap<T, CM>>> =
+                Default::default();
+            let replica_maps = self
+                .replica_maps
+                .iter()
+                .map(|(keyspace, replica_map)| {
+                    let new_replica_map = replaced
+                        .entry(Arc::as_ptr(replica_map))
+                        .or_insert_with(|| Arc::new(replica_map.clone_with_replaced_node(&node)))
+                        .clone();
+
+                    (keyspace.clone(), new_replica_map)
+                })
+                .collect();
+
+            return ClusterMetadata {
+                nodes,
+                token_map,
+                keyspaces: self.keyspaces.clone(),
+                datacenters: self.datacenters.clone(),
+                partitioner: self.partitioner,
+                replica_maps,
+            };
+        }
 
+        let token_map = TokenMap::new(&nodes);
         let datacenters = build_datacenter_info(&nodes);
+        let replica_maps = build_replica_maps(&token_map, &self.keyspaces, &datacenters);
 
         ClusterMetadata {
             nodes,
             token_map,
             keyspaces: self.keyspaces.clone(),
             datacenters,
+            partitioner: self.partitioner,
+            replica_maps,
         }
     }
 
@@ -121,7 +244,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
             })
             .collect();
 
-        Self::new(nodes, self.keyspaces.clone())
+        Self::new_with_partitioner(nodes, self.keyspaces.clone(), self.partitioner)
     }
 
     /// Returns all known nodes.
@@ -132,14 +255,14 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> ClusterMetadata<T, CM> {
 
     /// Returns known keyspaces.
     #[inline]
-    pub fn keyspaces(&self) -> &FxHashMap<String, KeyspaceMetadata> {
+    pub fn keyspaces(&self) -> &FxHashMap<String, Arc<KeyspaceMetadata>> {
         &self.keyspaces
     }
 
     /// Returns known keyspace, if present.
     #[inline]
     pub fn keyspace(&self, keyspace: &str) -> Option<&KeyspaceMetadata> {
-        self.keyspaces.get(keyspace)
+        self.keyspaces.get(keyspace).map(Arc::as_ref)
     }
 
     /// Returns known datacenters.
@@ -240,6 +363,8 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Default for ClusterMetadata<T,
             token_map: Default::default(),
             keyspaces: Default::default(),
             datacenters: Default::default(),
+            partitioner: Default::default(),
+            replica_maps: Default::default(),
         }
     }
 }
@@ -257,7 +382,9 @@ mod tests {
     use crate::cluster::connection_manager::MockConnectionManager;
     use crate::cluster::connection_pool::ConnectionPoolFactory;
     use crate::cluster::topology::cluster_metadata::build_datacenter_info;
-    use crate::cluster::topology::Node;
+    use crate::cluster::topology::{KeyspaceMetadata, Node, NodeState, ReplicationStrategy};
+    use crate::cluster::{ClusterMetadata, Murmur3Token};
+    use crate::metrics::NoopMetricsRecorder;
     use crate::retry::MockReconnectionPolicy;
     use crate::transport::MockCdrsTransport;
 
@@ -272,6 +399,7 @@ mod tests {
             connection_manager,
             keyspace_receiver,
             Arc::new(reconnection_policy),
+            Arc::new(NoopMetricsRecorder),
         ));
 
         let mut nodes = FxHashMap::default();
@@ -332,4 +460,60 @@ mod tests {
         assert_eq!(dc_info.get("dc1").unwrap().rack_count, 2);
         assert_eq!(dc_info.get("dc2").unwrap().rack_count, 1);
     }
+
+    #[test]
+    fn should_update_replicas_with_node() {
+        let (_, keyspace_receiver) = watch::channel(None);
+        let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+        let reconnection_policy = MockReconnectionPolicy::new();
+        let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+            Default::default(),
+            Version::V4,
+            connection_manager,
+            keyspace_receiver,
+            Arc::new(reconnection_policy),
+            Arc::new(NoopMetricsRecorder),
+        ));
+
+        let host_id = Uuid::new_v4();
+        let node = Node::new(
+            connection_pool_factory,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 8080),
+            None,
+            Some(host_id),
+            None,
+            vec![Murmur3Token::new(10).into()],
+            "r1".into(),
+            "dc1".into(),
+        );
+
+        let mut nodes = FxHashMap::default();
+        nodes.insert(host_id, Arc::new(node.clone_with_node_state(NodeState::Up)));
+
+        let mut keyspaces = FxHashMap::default();
+        keyspaces.insert(
+            "ks".into(),
+            Arc::new(KeyspaceMetadata::new(
+                "ks".into(),
+                ReplicationStrategy::SimpleStrategy {
+                    replication_factor: 1,
+                },
+            )),
+        );
+
+        let metadata = ClusterMetadata::new(nodes, keyspaces);
+        let token = Murmur3Token::new(0).into();
+
+        let replicas = metadata.replicas("ks", &token).unwrap();
+        assert_eq!(replicas.len(), 1);
+        assert_eq!(replicas[0].state(), NodeState::Up);
+        assert!(metadata.replicas("other", &token).is_none());
+
+        let metadata = metadata.clone_with_node(node.clone_with_node_state(NodeState::Down));
+
+        let replicas = metadata.replicas("ks", &token).unwrap();
+        assert_eq!(replicas.len(), 1);
+        assert_eq!(replicas[0].state(), NodeState::Down);
+        assert_eq!(metadata.token_ranges("ks").unwrap().len(), 1);
+    }
 }
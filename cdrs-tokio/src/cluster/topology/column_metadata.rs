@@ -0,0 +1,32 @@
+use cassandra_protocol::frame::message_result::ColType;
+
+/// Kind of a table column.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Regular,
+    Static,
+}
+
+/// Order of a clustering column.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ClusteringOrder {
+    Asc,
+    Desc,
+    /// The column is not a clustering column.
+    None,
+}
+
+/// Metadata of a table or materialized view column.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColumnMetadata {
+    pub name: String,
+    pub kind: ColumnKind,
+    /// Position in the partition or clustering key. Other columns have position -1.
+    pub position: i32,
+    pub clustering_order: ClusteringOrder,
+    pub col_type: ColType,
+    /// Column type as CQL, e.g. `frozen<list<int>>`.
+    pub cql_type: String,
+}
@@ -0,0 +1,186 @@
+use itertools::Itertools;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+
+use crate::cluster::topology::{ClusteringOrder, ColumnKind, ColumnMetadata};
+
+// identifiers which need to be quoted, even if lowercase
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "default",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "for",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "keyspace",
+    "limit",
+    "materialized",
+    "mbean",
+    "mbeans",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
+/// Quotes given CQL identifier, if needed.
+pub(crate) fn quote_identifier(identifier: &str) -> Cow<'_, str> {
+    let is_plain = identifier
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase())
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !RESERVED_KEYWORDS.contains(&identifier);
+
+    if is_plain {
+        Cow::Borrowed(identifier)
+    } else {
+        Cow::Owned(format!("\"{}\"", identifier.replace('"', "\"\"")))
+    }
+}
+
+/// Returns a keyspace-qualified name of a schema element.
+pub(crate) fn qualified_name(keyspace: &str, name: &str) -> String {
+    format!("{}.{}", quote_identifier(keyspace), quote_identifier(name))
+}
+
+/// Returns given value as a CQL string literal.
+pub(crate) fn quote_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Returns the primary key clause of a table or view.
+pub(crate) fn describe_primary_key(columns: &[ColumnMetadata]) -> String {
+    let names = |kind: ColumnKind| {
+        columns
+            .iter()
+            .filter(move |column| column.kind == kind)
+            .map(|column| quote_identifier(&column.name))
+            .collect_vec()
+    };
+
+    let partition_key = names(ColumnKind::PartitionKey);
+    let partition_key = if partition_key.len() == 1 {
+        partition_key.join(", ")
+    } else {
+        format!("({})", partition_key.join(", "))
+    };
+
+    let clustering_key = names(ColumnKind::Clustering);
+    if clustering_key.is_empty() {
+        format!("PRIMARY KEY ({partition_key})")
+    } else {
+        format!(
+            "PRIMARY KEY ({partition_key}, {})",
+            clustering_key.join(", ")
+        )
+    }
+}
+
+/// Returns the `WITH` clause of a table or view, containing clustering order and options. Returns
+/// an empty string, if there is nothing to put in the clause.
+pub(crate) fn describe_table_options(
+    columns: &[ColumnMetadata],
+    options: &BTreeMap<String, String>,
+) -> String {
+    let clustering_order = columns
+        .iter()
+        .filter(|column| column.kind == ColumnKind::Clustering)
+        .map(|column| {
+            let order = if column.clustering_order == ClusteringOrder::Desc {
+                "DESC"
+            } else {
+                "ASC"
+            };
+
+            format!("{} {order}", quote_identifier(&column.name))
+        })
+        .join(", ");
+
+    let clustering_order = if clustering_order.is_empty() {
+        None
+    } else {
+        Some(format!("CLUSTERING ORDER BY ({clustering_order})"))
+    };
+
+    let clauses = clustering_order
+        .into_iter()
+        .chain(
+            options
+                .iter()
+                .map(|(name, value)| format!("{name} = {value}")),
+        )
+        .join("\n    AND ");
+
+    if clauses.is_empty() {
+        clauses
+    } else {
+        format!("WITH {clauses}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cluster::topology::describe::{qualified_name, quote_identifier, quote_string};
+
+    #[test]
+    fn should_quote_identifiers() {
+        assert_eq!(quote_identifier("users_2"), "users_2");
+        assert_eq!(quote_identifier("Users"), "\"Users\"");
+        assert_eq!(quote_identifier("2users"), "\"2users\"");
+        assert_eq!(quote_identifier("select"), "\"select\"");
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+        assert_eq!(qualified_name("ks", "Table"), "ks.\"Table\"");
+        assert_eq!(quote_string("it's"), "'it''s'");
+    }
+}
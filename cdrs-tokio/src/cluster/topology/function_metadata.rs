@@ -0,0 +1,48 @@
+use itertools::Itertools;
+
+use crate::cluster::topology::describe::{qualified_name, quote_identifier, quote_string};
+
+/// User defined function metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FunctionMetadata {
+    pub keyspace: String,
+    pub name: String,
+    pub argument_names: Vec<String>,
+    /// Argument types as CQL.
+    pub argument_types: Vec<String>,
+    pub return_type: String,
+    pub language: String,
+    pub body: String,
+    pub called_on_null_input: bool,
+}
+
+impl FunctionMetadata {
+    /// Returns a CQL statement creating this function.
+    pub fn describe(&self) -> String {
+        let arguments = self
+            .argument_names
+            .iter()
+            .zip(&self.argument_types)
+            .map(|(name, argument_type)| format!("{} {argument_type}", quote_identifier(name)))
+            .join(", ");
+
+        let null_input = if self.called_on_null_input {
+            "CALLED ON NULL INPUT"
+        } else {
+            "RETURNS NULL ON NULL INPUT"
+        };
+
+        let body = if self.body.contains("$$") {
+            quote_string(&self.body)
+        } else {
+            format!("$${}$$", self.body)
+        };
+
+        format!(
+            "CREATE FUNCTION {}({arguments})\n    {null_input}\n    RETURNS {}\n    LANGUAGE {}\n    AS {body};\n",
+            qualified_name(&self.keyspace, &self.name),
+            self.return_type,
+            self.language
+        )
+    }
+}
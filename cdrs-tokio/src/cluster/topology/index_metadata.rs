@@ -0,0 +1,59 @@
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+use crate::cluster::topology::describe::{qualified_name, quote_identifier, quote_string};
+
+/// Kind of a secondary index.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IndexKind {
+    Keys,
+    Composites,
+    Custom,
+}
+
+/// Secondary index metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IndexMetadata {
+    pub keyspace: String,
+    pub table: String,
+    pub name: String,
+    pub kind: IndexKind,
+    /// Indexed column, possibly wrapped in a function like `keys(column)`.
+    pub target: String,
+    /// Index implementation class of custom indexes.
+    pub class_name: Option<String>,
+    /// Raw index options, including the target and class name.
+    pub options: BTreeMap<String, String>,
+}
+
+impl IndexMetadata {
+    /// Returns a CQL statement creating this index.
+    pub fn describe(&self) -> String {
+        let name = quote_identifier(&self.name);
+        let table = qualified_name(&self.keyspace, &self.table);
+
+        match (self.kind, &self.class_name) {
+            (IndexKind::Custom, Some(class_name)) => {
+                let options = self
+                    .options
+                    .iter()
+                    .filter(|(name, _)| *name != "target" && *name != "class_name")
+                    .map(|(name, value)| format!("{}: {}", quote_string(name), quote_string(value)))
+                    .join(", ");
+
+                let options = if options.is_empty() {
+                    options
+                } else {
+                    format!(" WITH OPTIONS = {{{options}}}")
+                };
+
+                format!(
+                    "CREATE CUSTOM INDEX {name} ON {table} ({}) USING {}{options};\n",
+                    self.target,
+                    quote_string(class_name)
+                )
+            }
+            _ => format!("CREATE INDEX {name} ON {table} ({});\n", self.target),
+        }
+    }
+}
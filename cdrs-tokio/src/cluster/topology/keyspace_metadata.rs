@@ -1,9 +1,398 @@
-use derive_more::Constructor;
+use fxhash::{FxHashMap, FxHashSet};
+use itertools::Itertools;
+use std::collections::BTreeMap;
 
-use crate::cluster::topology::ReplicationStrategy;
+use crate::cluster::topology::describe::{quote_identifier, quote_string};
+use crate::cluster::topology::{
+    AggregateMetadata, FunctionMetadata, ReplicationStrategy, TableMetadata, UserTypeMetadata,
+    ViewMetadata,
+};
 
 /// Keyspace metadata.
-#[derive(Clone, Debug, Constructor)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct KeyspaceMetadata {
+    pub name: String,
     pub replication_strategy: ReplicationStrategy,
+    /// Raw replication options, including the strategy class. Might be empty, if the metadata
+    /// was not loaded from the cluster.
+    pub replication: BTreeMap<String, String>,
+    pub durable_writes: bool,
+    pub tables: FxHashMap<String, TableMetadata>,
+    pub views: FxHashMap<String, ViewMetadata>,
+    pub user_types: FxHashMap<String, UserTypeMetadata>,
+    /// Functions, possibly overloaded.
+    pub functions: Vec<FunctionMetadata>,
+    /// Aggregates, possibly overloaded.
+    pub aggregates: Vec<AggregateMetadata>,
+}
+
+impl KeyspaceMetadata {
+    /// Creates metadata of a keyspace without any schema elements.
+    pub fn new(name: String, replication_strategy: ReplicationStrategy) -> Self {
+        KeyspaceMetadata {
+            name,
+            replication_strategy,
+            replication: Default::default(),
+            durable_writes: true,
+            tables: Default::default(),
+            views: Default::default(),
+            user_types: Default::default(),
+            functions: Default::default(),
+            aggregates: Default::default(),
+        }
+    }
+
+    /// Returns a table with given name, if present.
+    #[inline]
+    pub fn table(&self, name: &str) -> Option<&TableMetadata> {
+        self.tables.get(name)
+    }
+
+    /// Returns a materialized view with given name, if present.
+    #[inline]
+    pub fn view(&self, name: &str) -> Option<&ViewMetadata> {
+        self.views.get(name)
+    }
+
+    /// Returns a user defined type with given name, if present.
+    #[inline]
+    pub fn user_type(&self, name: &str) -> Option<&UserTypeMetadata> {
+        self.user_types.get(name)
+    }
+
+    /// Returns CQL statements recreating this keyspace along with its user defined types,
+    /// functions, aggregates, tables, indexes and materialized views. User defined types are
+    /// ordered so that types are created before the ones using them.
+    pub fn describe(&self) -> String {
+        let mut statements = vec![format!(
+            "CREATE KEYSPACE {} WITH replication = {} AND durable_writes = {};\n",
+            quote_identifier(&self.name),
+            self.describe_replication(),
+            self.durable_writes
+        )];
+
+        statements.extend(self.ordered_user_types().map(UserTypeMetadata::describe));
+
+        statements.extend(
+            self.functions
+                .iter()
+                .sorted_by(|a, b| (&a.name, &a.argument_types).cmp(&(&b.name, &b.argument_types)))
+                .map(FunctionMetadata::describe),
+        );
+
+        statements.extend(
+            self.aggregates
+                .iter()
+                .sorted_by(|a, b| (&a.name, &a.argument_types).cmp(&(&b.name, &b.argument_types)))
+                .map(AggregateMetadata::describe),
+        );
+
+        // views go after their base tables
+        for table in self.tables.values().sorted_by(|a, b| a.name.cmp(&b.name)) {
+            statements.push(table.describe());
+            statements.extend(
+                self.views
+                    .values()
+                    .filter(|view| view.base_table == table.name)
+                    .sorted_by(|a, b| a.name.cmp(&b.name))
+                    .map(ViewMetadata::describe),
+            );
+        }
+
+        statements.extend(
+            self.views
+                .values()
+                .filter(|view| !self.tables.contains_key(&view.base_table))
+                .sorted_by(|a, b| a.name.cmp(&b.name))
+                .map(ViewMetadata::describe),
+        );
+
+        statements.join("\n")
+    }
+
+    fn describe_replication(&self) -> String {
+        let mut replication = self.replication.clone();
+        if replication.is_empty() {
+            match &self.replication_strategy {
+                ReplicationStrategy::SimpleStrategy { replication_factor } => {
+                    replication.insert(
+                        "class".into(),
+                        "org.apache.cassandra.locator.SimpleStrategy".into(),
+                    );
+                    replication.insert("replication_factor".into(), replication_factor.to_string());
+                }
+                ReplicationStrategy::NetworkTopologyStrategy {
+                    datacenter_replication_factor,
+                } => {
+                    replication.insert(
+                        "class".into(),
+                        "org.apache.cassandra.locator.NetworkTopologyStrategy".into(),
+                    );
+                    replication.extend(datacenter_replication_factor.iter().map(
+                        |(datacenter, replication_factor)| {
+                            (datacenter.clone(), replication_factor.to_string())
+                        },
+                    ));
+                }
+                ReplicationStrategy::Other => {}
+            }
+        }
+
+        // the class goes first
+        let class = replication.remove_entry("class");
+        let replication = class
+            .into_iter()
+            .chain(replication)
+            .map(|(name, value)| format!("{}: {}", quote_string(&name), quote_string(&value)))
+            .join(", ");
+
+        format!("{{{replication}}}")
+    }
+
+    fn ordered_user_types(&self) -> impl Iterator<Item = &UserTypeMetadata> {
+        let mut visited = FxHashSet::default();
+        let mut result = Vec::with_capacity(self.user_types.len());
+
+        for user_type in self
+            .user_types
+            .values()
+            .sorted_by(|a, b| a.name.cmp(&b.name))
+        {
+            self.visit_user_type(user_type, &mut visited, &mut result);
+        }
+
+        result.into_iter()
+    }
+
+    // depth-first search putting dependencies before dependent types
+    fn visit_user_type<'a>(
+        &'a self,
+        user_type: &'a UserTypeMetadata,
+        visited: &mut FxHashSet<&'a str>,
+        result: &mut Vec<&'a UserTypeMetadata>,
+    ) {
+        if !visited.insert(&user_type.name) {
+            return;
+        }
+
+        let dependencies = user_type
+            .fields
+            .iter()
+            .flat_map(|field| {
+                field
+                    .cql_type
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '"'))
+                    .map(|name| name.trim_matches('"'))
+            })
+            .sorted()
+            .dedup();
+
+        for dependency in dependencies {
+            if let Some(dependency) = self.user_types.get(dependency) {
+                self.visit_user_type(dependency, visited, result);
+            }
+        }
+
+        result.push(user_type);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::frame::message_result::ColType;
+
+    use crate::cluster::topology::{
+        AggregateMetadata, ClusteringOrder, ColumnKind, ColumnMetadata, FieldMetadata,
+        FunctionMetadata, IndexKind, IndexMetadata, KeyspaceMetadata, ReplicationStrategy,
+        TableMetadata, UserTypeMetadata, ViewMetadata,
+    };
+
+    fn column(
+        name: &str,
+        kind: ColumnKind,
+        clustering_order: ClusteringOrder,
+        cql_type: &str,
+    ) -> ColumnMetadata {
+        ColumnMetadata {
+            name: name.into(),
+            kind,
+            position: 0,
+            clustering_order,
+            col_type: ColType::Custom,
+            cql_type: cql_type.into(),
+        }
+    }
+
+    fn user_type(name: &str, fields: &[(&str, &str)]) -> UserTypeMetadata {
+        UserTypeMetadata {
+            keyspace: "ks".into(),
+            name: name.into(),
+            fields: fields
+                .iter()
+                .map(|(name, cql_type)| FieldMetadata {
+                    name: (*name).into(),
+                    col_type: ColType::Custom,
+                    cql_type: (*cql_type).into(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn should_describe_keyspace() {
+        let mut keyspace = KeyspaceMetadata::new(
+            "ks".into(),
+            ReplicationStrategy::SimpleStrategy {
+                replication_factor: 1,
+            },
+        );
+
+        for user_type in [
+            user_type(
+                "address",
+                &[("street", "text"), ("location", "frozen<point>")],
+            ),
+            user_type("point", &[("x", "int"), ("y", "int")]),
+        ] {
+            keyspace
+                .user_types
+                .insert(user_type.name.clone(), user_type);
+        }
+
+        keyspace.functions.push(FunctionMetadata {
+            keyspace: "ks".into(),
+            name: "plus".into(),
+            argument_names: vec!["a".into(), "b".into()],
+            argument_types: vec!["int".into(), "int".into()],
+            return_type: "int".into(),
+            language: "java".into(),
+            body: "return a + b;".into(),
+            called_on_null_input: false,
+        });
+
+        keyspace.aggregates.push(AggregateMetadata {
+            keyspace: "ks".into(),
+            name: "sum".into(),
+            argument_types: vec!["int".into()],
+            state_func: "plus".into(),
+            state_type: "int".into(),
+            final_func: None,
+            initcond: Some("0".into()),
+            return_type: "int".into(),
+        });
+
+        let mut table = TableMetadata {
+            keyspace: "ks".into(),
+            name: "users".into(),
+            id: None,
+            columns: vec![
+                column(
+                    "id",
+                    ColumnKind::PartitionKey,
+                    ClusteringOrder::None,
+                    "uuid",
+                ),
+                column(
+                    "created",
+                    ColumnKind::Clustering,
+                    ClusteringOrder::Desc,
+                    "timestamp",
+                ),
+                column("Name", ColumnKind::Regular, ClusteringOrder::None, "text"),
+                column("score", ColumnKind::Regular, ClusteringOrder::None, "int"),
+                column("tag", ColumnKind::Static, ClusteringOrder::None, "text"),
+            ],
+            options: [("comment".to_string(), "'users'".to_string())].into(),
+            indexes: Default::default(),
+        };
+
+        table.indexes.insert(
+            "users_name_idx".into(),
+            IndexMetadata {
+                keyspace: "ks".into(),
+                table: "users".into(),
+                name: "users_name_idx".into(),
+                kind: IndexKind::Composites,
+                target: "\"Name\"".into(),
+                class_name: None,
+                options: Default::default(),
+            },
+        );
+
+        keyspace.tables.insert(table.name.clone(), table);
+
+        keyspace.views.insert(
+            "users_by_score".into(),
+            ViewMetadata {
+                keyspace: "ks".into(),
+                name: "users_by_score".into(),
+                id: None,
+                base_table: "users".into(),
+                include_all_columns: false,
+                where_clause: "score IS NOT NULL AND id IS NOT NULL AND created IS NOT NULL".into(),
+                columns: vec![
+                    column(
+                        "score",
+                        ColumnKind::PartitionKey,
+                        ClusteringOrder::None,
+                        "int",
+                    ),
+                    column("id", ColumnKind::Clustering, ClusteringOrder::Asc, "uuid"),
+                    column(
+                        "created",
+                        ColumnKind::Clustering,
+                        ClusteringOrder::Desc,
+                        "timestamp",
+                    ),
+                ],
+                options: Default::default(),
+            },
+        );
+
+        assert_eq!(
+            keyspace.describe(),
+            r#"CREATE KEYSPACE ks WITH replication = {'class': 'org.apache.cassandra.locator.SimpleStrategy', 'replication_factor': '1'} AND durable_writes = true;
+
+CREATE TYPE ks.point (
+    x int,
+    y int
+);
+
+CREATE TYPE ks.address (
+    street text,
+    location frozen<point>
+);
+
+CREATE FUNCTION ks.plus(a int, b int)
+    RETURNS NULL ON NULL INPUT
+    RETURNS int
+    LANGUAGE java
+    AS $$return a + b;$$;
+
+CREATE AGGREGATE ks.sum(int)
+    SFUNC plus
+    STYPE int
+    INITCOND 0;
+
+CREATE TABLE ks.users (
+    id uuid,
+    created timestamp,
+    "Name" text,
+    score int,
+    tag text static,
+    PRIMARY KEY (id, created)
+) WITH CLUSTERING ORDER BY (created DESC)
+    AND comment = 'users';
+
+CREATE INDEX users_name_idx ON ks.users ("Name");
+
+CREATE MATERIALIZED VIEW ks.users_by_score AS
+    SELECT score, id, created
+    FROM ks.users
+    WHERE score IS NOT NULL AND id IS NOT NULL AND created IS NOT NULL
+    PRIMARY KEY (score, id, created)
+    WITH CLUSTERING ORDER BY (id ASC, created DESC);
+"#
+        );
+    }
 }
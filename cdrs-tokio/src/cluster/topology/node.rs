@@ -1,10 +1,12 @@
 use atomic::Atomic;
 use cassandra_protocol::error::{Error, Result};
+use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
 use cassandra_protocol::frame::Envelope;
 use std::fmt::{Debug, Formatter};
 use std::net::SocketAddr;
-use std::sync::atomic::Ordering;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::OnceCell;
 use tracing::*;
@@ -12,7 +14,7 @@ use uuid::Uuid;
 
 use crate::cluster::connection_pool::{ConnectionPool, ConnectionPoolFactory};
 use crate::cluster::topology::{NodeDistance, NodeState};
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 use crate::cluster::{ConnectionManager, NodeInfo};
 use crate::transport::CdrsTransport;
 
@@ -22,12 +24,35 @@ pub struct Node<T: CdrsTransport + 'static, CM: ConnectionManager<T> + 'static>
     connection_pool: OnceCell<Arc<ConnectionPool<T, CM>>>,
     broadcast_rpc_address: SocketAddr,
     broadcast_address: Option<SocketAddr>,
+    connect_address: SocketAddr,
     distance: Option<NodeDistance>,
     state: Atomic<NodeState>,
     host_id: Option<Uuid>,
-    tokens: Vec<Murmur3Token>,
+    tokens: Vec<Token>,
     rack: String,
     datacenter: String,
+    // shared by all copies of the node, since requests can outlive a copy
+    request_counters: Arc<RequestCounters>,
+}
+
+#[derive(Default, Debug)]
+struct RequestCounters {
+    in_flight: AtomicUsize,
+    overloaded: AtomicUsize,
+    timeouts: AtomicUsize,
+    consecutive_timeouts: AtomicUsize,
+    last_overloaded: Mutex<Option<Instant>>,
+}
+
+/// Guard of a request in flight to a node. Dropping it marks the request as finished.
+pub(crate) struct InFlightRequest<'a> {
+    in_flight: &'a AtomicUsize,
+}
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>> Debug for Node<T, CM> {
@@ -35,12 +60,14 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Debug for Node<T, CM> {
         f.debug_struct("Node")
             .field("broadcast_rpc_address", &self.broadcast_rpc_address)
             .field("broadcast_address", &self.broadcast_address)
+            .field("connect_address", &self.connect_address)
             .field("distance", &self.distance)
             .field("state", &self.state)
             .field("host_id", &self.host_id)
             .field("tokens", &self.tokens)
             .field("rack", &self.rack)
             .field("datacenter", &self.datacenter)
+            .field("request_counters", &self.request_counters)
             .finish()
     }
 }
@@ -53,7 +80,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         broadcast_address: Option<SocketAddr>,
         host_id: Option<Uuid>,
         distance: Option<NodeDistance>,
-        tokens: Vec<Murmur3Token>,
+        tokens: Vec<Token>,
         rack: String,
         datacenter: String,
     ) -> Self {
@@ -62,12 +89,14 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address,
             broadcast_address,
+            connect_address: broadcast_rpc_address,
             distance,
             state: Atomic::new(NodeState::Unknown),
             host_id,
             tokens,
             rack,
             datacenter,
+            request_counters: Default::default(),
         }
     }
 
@@ -79,7 +108,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         host_id: Option<Uuid>,
         distance: Option<NodeDistance>,
         state: NodeState,
-        tokens: Vec<Murmur3Token>,
+        tokens: Vec<Token>,
         rack: String,
         datacenter: String,
     ) -> Self {
@@ -88,12 +117,14 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address,
             broadcast_address,
+            connect_address: broadcast_rpc_address,
             distance,
             state: Atomic::new(state),
             host_id,
             tokens,
             rack,
             datacenter,
+            request_counters: Default::default(),
         }
     }
 
@@ -104,7 +135,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         broadcast_address: Option<SocketAddr>,
         host_id: Option<Uuid>,
         state: NodeState,
-        tokens: Vec<Murmur3Token>,
+        tokens: Vec<Token>,
         rack: String,
         datacenter: String,
     ) -> Self {
@@ -113,12 +144,14 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address,
             broadcast_address,
+            connect_address: broadcast_rpc_address,
             distance: None,
             state: Atomic::new(state),
             host_id,
             tokens,
             rack,
             datacenter,
+            request_counters: Default::default(),
         }
     }
 
@@ -135,15 +168,24 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address,
             broadcast_address,
+            connect_address: broadcast_rpc_address,
             distance: Some(distance),
             state: Atomic::new(NodeState::Unknown),
             host_id,
             tokens: Default::default(),
             rack: Default::default(),
             datacenter: Default::default(),
+            request_counters: Default::default(),
         }
     }
 
+    /// Returns a copy of the node, which connects to given address instead of its broadcast RPC
+    /// address.
+    pub(crate) fn with_connect_address(mut self, connect_address: SocketAddr) -> Self {
+        self.connect_address = connect_address;
+        self
+    }
+
     #[inline]
     pub fn state(&self) -> NodeState {
         self.state.load(Ordering::Relaxed)
@@ -163,6 +205,13 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         self.broadcast_rpc_address
     }
 
+    /// The address used to connect to the node. Equal to the broadcast RPC address, unless
+    /// translated by an [`AddressTranslator`](crate::address_translator::AddressTranslator).
+    #[inline]
+    pub fn connect_address(&self) -> SocketAddr {
+        self.connect_address
+    }
+
     /// The node's broadcast address. That is, the address that other nodes use to communicate with
     /// that node.
     #[inline]
@@ -172,7 +221,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
 
     /// Returns tokens associated with the node.
     #[inline]
-    pub fn tokens(&self) -> &[Murmur3Token] {
+    pub fn tokens(&self) -> &[Token] {
         &self.tokens
     }
 
@@ -188,6 +237,80 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         &self.rack
     }
 
+    /// Returns the number of requests sent to the node, which are still waiting for a response.
+    #[inline]
+    pub fn in_flight_requests(&self) -> usize {
+        self.request_counters.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of `Overloaded` errors returned by the node.
+    #[inline]
+    pub fn overloaded_errors(&self) -> usize {
+        self.request_counters.overloaded.load(Ordering::Relaxed)
+    }
+
+    /// Returns the time of the last `Overloaded` error returned by the node, if any.
+    #[inline]
+    pub fn last_overloaded(&self) -> Option<Instant> {
+        *self.request_counters.last_overloaded.lock().unwrap()
+    }
+
+    /// Returns the number of requests to the node, which timed out.
+    #[inline]
+    pub fn timeout_errors(&self) -> usize {
+        self.request_counters.timeouts.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests to the node, which timed out since the last response.
+    #[inline]
+    pub fn consecutive_timeouts(&self) -> usize {
+        self.request_counters
+            .consecutive_timeouts
+            .load(Ordering::Relaxed)
+    }
+
+    /// Marks a request to the node as in flight, until the returned guard is dropped.
+    #[inline]
+    pub(crate) fn start_request(&self) -> InFlightRequest<'_> {
+        self.request_counters
+            .in_flight
+            .fetch_add(1, Ordering::Relaxed);
+
+        InFlightRequest {
+            in_flight: &self.request_counters.in_flight,
+        }
+    }
+
+    /// Updates error counters with the outcome of a request.
+    pub(crate) fn report_response(&self, response: &Result<Envelope>) {
+        let counters = &self.request_counters;
+        match response {
+            Err(Error::Timeout(_)) => {
+                counters.timeouts.fetch_add(1, Ordering::Relaxed);
+                counters
+                    .consecutive_timeouts
+                    .fetch_add(1, Ordering::Relaxed);
+            }
+            Err(Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::Overloaded,
+                        ..
+                    },
+                ..
+            }) => {
+                counters.overloaded.fetch_add(1, Ordering::Relaxed);
+                *counters.last_overloaded.lock().unwrap() = Some(Instant::now());
+                counters.consecutive_timeouts.store(0, Ordering::Relaxed);
+            }
+            // the node responded, even if with an error
+            Ok(_) | Err(Error::Server { .. }) => {
+                counters.consecutive_timeouts.store(0, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+
     /// Returns a connection to given node.
     #[inline]
     pub async fn persistent_connection(self: &Arc<Self>) -> Result<Arc<T>> {
@@ -198,7 +321,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
 
                 self.connection_pool_factory.create(
                     self.distance.unwrap_or(NodeDistance::Remote),
-                    self.broadcast_rpc_address,
+                    self.connect_address,
                     Arc::downgrade(self),
                 )
             })
@@ -235,7 +358,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         debug!("Establishing new connection to node...");
         self.connection_pool_factory
             .connection_manager()
-            .connection(event_handler, error_handler, self.broadcast_rpc_address)
+            .connection(event_handler, error_handler, self.connect_address)
             .await
     }
 
@@ -245,10 +368,20 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
         self.distance
     }
 
-    /// Checks if the node is local in relation to the driver.
+    /// Checks if the node is local in relation to the driver. Nodes in the local rack are local as
+    /// well.
     #[inline]
     pub fn is_local(&self) -> bool {
-        self.distance == Some(NodeDistance::Local)
+        matches!(
+            self.distance,
+            Some(NodeDistance::LocalRack | NodeDistance::Local)
+        )
+    }
+
+    /// Checks if the node is in the same rack as the driver.
+    #[inline]
+    pub fn is_local_rack(&self) -> bool {
+        self.distance == Some(NodeDistance::LocalRack)
     }
 
     /// Checks if the node is remote in relation to the driver.
@@ -276,7 +409,11 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
     }
 
     #[inline]
-    pub(crate) fn clone_with_node_info(&self, node_info: NodeInfo) -> Self {
+    pub(crate) fn clone_with_node_info(
+        &self,
+        node_info: NodeInfo,
+        connect_address: SocketAddr,
+    ) -> Self {
         let address_changed = self
             .broadcast_address
             .map(|address| address != node_info.broadcast_rpc_address)
@@ -309,6 +446,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address: node_info.broadcast_rpc_address,
             broadcast_address: node_info.broadcast_address,
+            connect_address,
             // since address could change, we can't be sure of distance or state
             distance: if address_changed { None } else { self.distance },
             state: Atomic::new(new_node_state),
@@ -316,6 +454,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             tokens: node_info.tokens,
             rack: node_info.rack,
             datacenter: node_info.datacenter,
+            request_counters: self.request_counters.clone(),
         }
     }
 
@@ -327,12 +466,15 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: self.connection_pool.clone(),
             broadcast_rpc_address: self.broadcast_rpc_address,
             broadcast_address: node_info.broadcast_address,
+            // existing connections are kept, so keep the address they are connected to
+            connect_address: self.connect_address,
             distance: self.distance,
             state: Atomic::new(self.state.load(Ordering::Relaxed)),
             host_id: Some(node_info.host_id),
             tokens: node_info.tokens,
             rack: node_info.rack,
             datacenter: node_info.datacenter,
+            request_counters: self.request_counters.clone(),
         }
     }
 
@@ -340,6 +482,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
     pub(crate) fn clone_with_node_info_and_state(
         &self,
         node_info: NodeInfo,
+        connect_address: SocketAddr,
         state: NodeState,
     ) -> Self {
         Self {
@@ -347,6 +490,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address: node_info.broadcast_rpc_address,
             broadcast_address: node_info.broadcast_address,
+            connect_address,
             // since address could change, we can't be sure of distance
             distance: None,
             state: Atomic::new(state),
@@ -354,6 +498,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             tokens: node_info.tokens,
             rack: node_info.rack,
             datacenter: node_info.datacenter,
+            request_counters: self.request_counters.clone(),
         }
     }
 
@@ -364,12 +509,35 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> Node<T, CM> {
             connection_pool: Default::default(),
             broadcast_rpc_address: self.broadcast_rpc_address,
             broadcast_address: self.broadcast_address,
+            connect_address: self.connect_address,
             distance: self.distance,
             state: Atomic::new(state),
             host_id: self.host_id,
             tokens: self.tokens.clone(),
             rack: self.rack.clone(),
             datacenter: self.datacenter.clone(),
+            request_counters: self.request_counters.clone(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::cluster::topology::NodeState;
+    use crate::load_balancing::test_utils::create_query_plan;
+
+    #[test]
+    fn should_keep_request_counters_in_copies() {
+        let node = create_query_plan().remove(0);
+
+        let request = node.start_request();
+        let copy = node.clone_with_node_state(NodeState::Down);
+        assert_eq!(copy.in_flight_requests(), 1);
+
+        drop(request);
+        assert_eq!(copy.in_flight_requests(), 0);
+
+        let _request = copy.start_request();
+        assert_eq!(node.in_flight_requests(), 1);
+    }
+}
@@ -3,6 +3,9 @@ use derive_more::Display;
 /// Determines how the driver will manage connections to a Cassandra node.
 #[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Display)]
 pub enum NodeDistance {
+    /// A "local" distance for nodes in the same rack as the driver, within the local datacenter.
+    /// Such nodes are preferred over other local nodes, e.g. to avoid cross-rack traffic costs.
+    LocalRack,
     /// An "active" distance that, indicates that the driver should maintain connections to the
     /// node; it also marks it as "preferred", meaning that the node may have priority for
     /// some tasks (for example, being chosen as the control connection host).
@@ -1,7 +1,7 @@
 use fxhash::FxHashMap;
 
 /// A replication strategy determines the nodes where replicas are placed.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ReplicationStrategy {
     SimpleStrategy {
         replication_factor: usize,
@@ -0,0 +1,87 @@
+use fxhash::FxHashMap;
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use uuid::Uuid;
+
+use crate::cluster::topology::describe::{
+    describe_primary_key, describe_table_options, qualified_name, quote_identifier,
+};
+use crate::cluster::topology::{ColumnKind, ColumnMetadata, IndexMetadata};
+
+/// Table metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableMetadata {
+    pub keyspace: String,
+    pub name: String,
+    pub id: Option<Uuid>,
+    /// Partition key columns, followed by clustering columns (both in key order) and the rest of
+    /// the columns, ordered by name.
+    pub columns: Vec<ColumnMetadata>,
+    /// Table options with values as CQL literals, e.g. `comment` with `'some comment'`.
+    pub options: BTreeMap<String, String>,
+    pub indexes: FxHashMap<String, IndexMetadata>,
+}
+
+impl TableMetadata {
+    /// Returns a column with given name, if present.
+    pub fn column(&self, name: &str) -> Option<&ColumnMetadata> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Returns partition key columns in key order.
+    pub fn partition_key(&self) -> impl Iterator<Item = &ColumnMetadata> {
+        self.columns
+            .iter()
+            .filter(|column| column.kind == ColumnKind::PartitionKey)
+    }
+
+    /// Returns clustering columns in key order.
+    pub fn clustering_key(&self) -> impl Iterator<Item = &ColumnMetadata> {
+        self.columns
+            .iter()
+            .filter(|column| column.kind == ColumnKind::Clustering)
+    }
+
+    /// Returns CQL statements creating this table and its indexes. Materialized views are
+    /// described by [`KeyspaceMetadata::describe()`](crate::cluster::topology::KeyspaceMetadata::describe).
+    pub fn describe(&self) -> String {
+        let mut ddl = format!(
+            "CREATE TABLE {} (\n",
+            qualified_name(&self.keyspace, &self.name)
+        );
+
+        for column in &self.columns {
+            let modifier = if column.kind == ColumnKind::Static {
+                " static"
+            } else {
+                ""
+            };
+
+            let _ = writeln!(
+                ddl,
+                "    {} {}{modifier},",
+                quote_identifier(&column.name),
+                column.cql_type
+            );
+        }
+
+        let _ = writeln!(ddl, "    {}", describe_primary_key(&self.columns));
+        ddl.push(')');
+
+        let options = describe_table_options(&self.columns, &self.options);
+        if !options.is_empty() {
+            ddl.push(' ');
+            ddl.push_str(&options);
+        }
+
+        ddl.push_str(";\n");
+
+        for index in self.indexes.values().sorted_by(|a, b| a.name.cmp(&b.name)) {
+            ddl.push('\n');
+            ddl.push_str(&index.describe());
+        }
+
+        ddl
+    }
+}
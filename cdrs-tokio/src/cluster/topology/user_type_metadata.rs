@@ -0,0 +1,35 @@
+use cassandra_protocol::frame::message_result::ColType;
+use itertools::Itertools;
+
+use crate::cluster::topology::describe::{qualified_name, quote_identifier};
+
+/// Field of a user defined type.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct FieldMetadata {
+    pub name: String,
+    pub col_type: ColType,
+    /// Field type as CQL, e.g. `frozen<list<int>>`.
+    pub cql_type: String,
+}
+
+/// User defined type metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UserTypeMetadata {
+    pub keyspace: String,
+    pub name: String,
+    pub fields: Vec<FieldMetadata>,
+}
+
+impl UserTypeMetadata {
+    /// Returns a CQL statement creating this type.
+    pub fn describe(&self) -> String {
+        format!(
+            "CREATE TYPE {} (\n{}\n);\n",
+            qualified_name(&self.keyspace, &self.name),
+            self.fields
+                .iter()
+                .map(|field| format!("    {} {}", quote_identifier(&field.name), field.cql_type))
+                .join(",\n")
+        )
+    }
+}
@@ -0,0 +1,50 @@
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+use crate::cluster::topology::describe::{
+    describe_primary_key, describe_table_options, qualified_name, quote_identifier,
+};
+use crate::cluster::topology::ColumnMetadata;
+
+/// Materialized view metadata.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ViewMetadata {
+    pub keyspace: String,
+    pub name: String,
+    pub id: Option<Uuid>,
+    pub base_table: String,
+    pub include_all_columns: bool,
+    pub where_clause: String,
+    /// Columns ordered the same way as in [`TableMetadata`](crate::cluster::topology::TableMetadata).
+    pub columns: Vec<ColumnMetadata>,
+    /// View options with values as CQL literals.
+    pub options: BTreeMap<String, String>,
+}
+
+impl ViewMetadata {
+    /// Returns a CQL statement creating this view.
+    pub fn describe(&self) -> String {
+        let selection = if self.include_all_columns {
+            "*".to_string()
+        } else {
+            self.columns
+                .iter()
+                .map(|column| quote_identifier(&column.name))
+                .join(", ")
+        };
+
+        let mut options = describe_table_options(&self.columns, &self.options);
+        if !options.is_empty() {
+            options.insert_str(0, "\n    ");
+        }
+
+        format!(
+            "CREATE MATERIALIZED VIEW {} AS\n    SELECT {selection}\n    FROM {}\n    WHERE {}\n    {}{options};\n",
+            qualified_name(&self.keyspace, &self.name),
+            qualified_name(&self.keyspace, &self.base_table),
+            self.where_clause,
+            describe_primary_key(&self.columns),
+        )
+    }
+}
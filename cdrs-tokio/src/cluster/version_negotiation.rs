@@ -0,0 +1,304 @@
+use cassandra_protocol::compression::Compression;
+use cassandra_protocol::error::{Error, Result as CdrsResult};
+use cassandra_protocol::frame::Version;
+use cassandra_protocol::types::ByName;
+use std::net::SocketAddr;
+use tracing::*;
+
+use crate::cluster::cluster_metadata_manager::send_query;
+use crate::cluster::session::SessionBuildError;
+use crate::cluster::ConnectionManager;
+use crate::transport::CdrsTransport;
+
+/// Protocol versions supported by the driver, from the highest.
+const SUPPORTED_VERSIONS: [Version; 3] = [Version::V5, Version::V4, Version::V3];
+
+// returns the highest protocol version supported by given Cassandra release
+fn max_release_protocol_version(release_version: &str) -> Option<Version> {
+    let mut parts = release_version
+        .split(|c: char| !c.is_ascii_digit())
+        .map(|part| part.parse::<u32>());
+
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().and_then(|minor| minor.ok()).unwrap_or(0);
+
+    // V5 is still a beta version in 4.0 alphas and betas
+    let is_pre_release = release_version.contains("-alpha") || release_version.contains("-beta");
+
+    Some(match (major, minor) {
+        (4, 0) if is_pre_release => Version::V4,
+        (4.., _) => Version::V5,
+        (3, _) | (2, 2..) => Version::V4,
+        _ => Version::V3,
+    })
+}
+
+// returns the highest protocol version supported by all nodes, based on their release versions
+async fn max_cluster_protocol_version<T: CdrsTransport>(
+    transport: &T,
+    version: Version,
+    beta_protocol: bool,
+) -> CdrsResult<Option<Version>> {
+    let (local, peers) = tokio::try_join!(
+        send_query(
+            "SELECT release_version FROM system.local",
+            transport,
+            version,
+            beta_protocol,
+        ),
+        send_query(
+            "SELECT release_version FROM system.peers",
+            transport,
+            version,
+            beta_protocol,
+        )
+    )?;
+
+    let mut max_version = None;
+    for row in local.into_iter().chain(peers).flatten() {
+        let release_version: Option<String> = row.by_name("release_version")?;
+        if let Some(node_version) = release_version
+            .as_deref()
+            .and_then(max_release_protocol_version)
+        {
+            max_version = Some(max_version.map_or(node_version, |max_version: Version| {
+                max_version.min(node_version)
+            }));
+        }
+    }
+
+    Ok(max_version)
+}
+
+// connects to the first contact point which is up; nodes rejecting the protocol version end the
+// search, since other nodes are likely to reject it as well
+async fn connect<T: CdrsTransport, CM: ConnectionManager<T>>(
+    connection_manager: &CM,
+    contact_points: &[SocketAddr],
+) -> CdrsResult<T> {
+    let mut result = Err(Error::General("No contact points given!".into()));
+    for contact_point in contact_points {
+        result = connection_manager
+            .connection(None, None, *contact_point)
+            .await;
+
+        match &result {
+            Ok(_) | Err(Error::InvalidProtocol(_)) => return result,
+            Err(error) => {
+                debug!(%error, %contact_point, "Cannot connect to contact point during protocol version negotiation.");
+            }
+        }
+    }
+
+    result
+}
+
+/// Negotiates the protocol version with contact points. Starts with the highest version supported
+/// by the driver and given compression, and downgrades as long as nodes reject it. The resulting
+/// version is further lowered to the highest one supported by all nodes in the cluster, based on
+/// their release versions, so nodes not yet upgraded during a rolling upgrade are reachable. If no
+/// contact point can be reached, the configured version is used, so the session can connect later.
+/// Returns the connection manager for the negotiated version, along with the version itself.
+pub(crate) async fn negotiate_version<T: CdrsTransport, CM: ConnectionManager<T>>(
+    connection_manager: CM,
+    with_version: impl Fn(CM, Version) -> CM,
+    contact_points: &[SocketAddr],
+    compression: Compression,
+    configured_version: Version,
+    beta_protocol: bool,
+) -> Result<(CM, Version), SessionBuildError> {
+    let versions = SUPPORTED_VERSIONS
+        .iter()
+        .copied()
+        // V5 doesn't support Snappy compression
+        .filter(|version| *version < Version::V5 || compression != Compression::Snappy);
+
+    let mut connection_manager = connection_manager;
+    for version in versions {
+        connection_manager = with_version(connection_manager, version);
+
+        match connect(&connection_manager, contact_points).await {
+            Ok(transport) => {
+                let cluster_version =
+                    max_cluster_protocol_version(&transport, version, beta_protocol).await;
+
+                return Ok(match cluster_version {
+                    Ok(Some(cluster_version)) if cluster_version < version => {
+                        debug!(%version, %cluster_version, "Some nodes don't support negotiated protocol version - downgrading.");
+                        (
+                            with_version(connection_manager, cluster_version),
+                            cluster_version,
+                        )
+                    }
+                    Ok(_) => {
+                        debug!(%version, "Negotiated protocol version.");
+                        (connection_manager, version)
+                    }
+                    Err(error) => {
+                        warn!(%error, %version, "Cannot check protocol versions supported by the cluster.");
+                        (connection_manager, version)
+                    }
+                });
+            }
+            Err(Error::InvalidProtocol(addr)) => {
+                debug!(%version, %addr, "Protocol version not supported - downgrading.");
+            }
+            Err(error) => {
+                warn!(%error, %configured_version, "Cannot negotiate protocol version - using configured one.");
+                return Ok((
+                    with_version(connection_manager, configured_version),
+                    configured_version,
+                ));
+            }
+        }
+    }
+
+    Err(SessionBuildError::ProtocolVersionNotSupported)
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::Version;
+    use futures::FutureExt;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::cluster::session::SessionBuildError;
+    use crate::cluster::version_negotiation::{max_release_protocol_version, negotiate_version};
+    use crate::transport::MockCdrsTransport;
+
+    type MockManager = MockConnectionManager<MockCdrsTransport>;
+
+    // creates a manager rejecting protocol versions higher than given one
+    fn with_version(max_version: Version) -> impl Fn(MockManager, Version) -> MockManager + Copy {
+        move |mut connection_manager, version| {
+            connection_manager.checkpoint();
+            connection_manager
+                .expect_connection()
+                .returning(move |_, _, addr| {
+                    if version > max_version {
+                        async move { Err(Error::InvalidProtocol(addr)) }.boxed()
+                    } else {
+                        let mut transport = MockCdrsTransport::new();
+                        transport.expect_write_envelope().returning(|_, _| {
+                            async { Err(Error::General("not available".into())) }.boxed()
+                        });
+
+                        async move { Ok(transport) }.boxed()
+                    }
+                });
+
+            connection_manager
+        }
+    }
+
+    #[test]
+    fn should_map_release_versions() {
+        assert_eq!(max_release_protocol_version("5.0.2"), Some(Version::V5));
+        assert_eq!(max_release_protocol_version("4.0.0"), Some(Version::V5));
+        assert_eq!(max_release_protocol_version("4.0-rc1"), Some(Version::V5));
+        assert_eq!(max_release_protocol_version("4.0-beta1"), Some(Version::V4));
+        assert_eq!(
+            max_release_protocol_version("4.0-alpha4"),
+            Some(Version::V4)
+        );
+        assert_eq!(max_release_protocol_version("3.11.4"), Some(Version::V4));
+        assert_eq!(max_release_protocol_version("2.2.19"), Some(Version::V4));
+        assert_eq!(max_release_protocol_version("2.1.22"), Some(Version::V3));
+        assert_eq!(max_release_protocol_version("unknown"), None);
+    }
+
+    #[tokio::test]
+    async fn should_downgrade_rejected_versions() {
+        let contact_points = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)];
+
+        for max_version in [Version::V5, Version::V4, Version::V3] {
+            let (_, version) = negotiate_version(
+                MockManager::new(),
+                with_version(max_version),
+                &contact_points,
+                Compression::None,
+                Version::V4,
+                false,
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(version, max_version);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_skip_v5_with_snappy() {
+        let contact_points = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)];
+
+        let (_, version) = negotiate_version(
+            MockManager::new(),
+            with_version(Version::V5),
+            &contact_points,
+            Compression::Snappy,
+            Version::V4,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(version, Version::V4);
+    }
+
+    #[tokio::test]
+    async fn should_use_configured_version_for_unreachable_contact_points() {
+        let contact_points = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)];
+
+        let (_, version) = negotiate_version(
+            MockManager::new(),
+            |mut connection_manager: MockManager, _| {
+                connection_manager.checkpoint();
+                connection_manager.expect_connection().returning(|_, _, _| {
+                    async { Err(Error::General("unreachable".into())) }.boxed()
+                });
+
+                connection_manager
+            },
+            &contact_points,
+            Compression::None,
+            Version::V4,
+            false,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(version, Version::V4);
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_all_versions_are_rejected() {
+        let contact_points = [SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042)];
+
+        let result = negotiate_version(
+            MockManager::new(),
+            |mut connection_manager: MockManager, _| {
+                connection_manager.checkpoint();
+                connection_manager
+                    .expect_connection()
+                    .returning(|_, _, addr| {
+                        async move { Err(Error::InvalidProtocol(addr)) }.boxed()
+                    });
+
+                connection_manager
+            },
+            &contact_points,
+            Compression::None,
+            Version::V4,
+            false,
+        )
+        .await;
+
+        assert_eq!(
+            result.err(),
+            Some(SessionBuildError::ProtocolVersionNotSupported)
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use std::net::SocketAddr;
@@ -11,7 +12,8 @@ use cassandra_protocol::frame::{
 };
 use cassandra_protocol::types::data_serialization_types::decode_timeuuid;
 use cassandra_protocol::types::{
-    from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes, UUID_LEN,
+    from_cursor_bytes_map, from_cursor_string_list, try_i16_from_bytes, try_i32_from_bytes,
+    UUID_LEN,
 };
 
 async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
@@ -50,7 +52,7 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
 
     let body_len = full_body.len();
 
-    // Use cursor to get tracing id, warnings and actual body
+    // Use cursor to get tracing id, warnings, custom payload and actual body
     let mut body_cursor = Cursor::new(full_body.as_slice());
 
     let tracing_id = if flags.contains(Flags::TRACING) {
@@ -68,6 +70,12 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
         vec![]
     };
 
+    let custom_payload = if flags.contains(Flags::CUSTOM_PAYLOAD) {
+        from_cursor_bytes_map(&mut body_cursor)?
+    } else {
+        HashMap::new()
+    };
+
     let mut body = Vec::with_capacity(body_len - body_cursor.position() as usize);
 
     std::io::Read::read_to_end(&mut body_cursor, &mut body)?;
@@ -81,6 +89,7 @@ async fn parse_raw_envelope<T: AsyncReadExt + Unpin>(
         body,
         tracing_id,
         warnings,
+        custom_payload,
     };
 
     Ok(envelope)
@@ -0,0 +1,66 @@
+//! Request interceptors, allowing injecting custom behavior around every request sent through a
+//! [`Session`](crate::cluster::session::Session), e.g. auditing, rewriting keyspaces, adding custom
+//! payloads or injecting failures for testing.
+//!
+//! Interceptors are called in the order of registration before a request is sent and before each
+//! attempt, and in the reverse order after a response is received.
+
+use cassandra_protocol::error::Result;
+use cassandra_protocol::frame::Envelope;
+use derive_more::Display;
+use std::net::SocketAddr;
+
+use crate::cluster::QueryResult;
+use crate::statement::StatementParams;
+
+/// Kind of intercepted request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Display)]
+pub enum RequestKind {
+    Query,
+    Execute,
+    Batch,
+    Prepare,
+}
+
+/// Information about an intercepted request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RequestInfo<'a> {
+    pub kind: RequestKind,
+    /// Query text of the request, or of the prepared statement being executed. Not present for
+    /// batches.
+    pub query: Option<&'a str>,
+}
+
+/// Interceptor of session requests. All methods have default implementations which don't change
+/// anything, so interceptors only need to implement the hooks they are interested in.
+pub trait RequestInterceptor {
+    /// Called before the request envelope is built. Parameters can be changed at this point, e.g.
+    /// to set a different keyspace. For prepare requests, only the keyspace, tracing, warnings,
+    /// beta protocol and custom payload parameters are used. Returning a result skips sending the
+    /// request, and the result is returned to the caller instead - subsequent interceptors are not
+    /// called.
+    fn before_request(
+        &self,
+        _request: &RequestInfo,
+        _parameters: &mut StatementParams,
+    ) -> Option<Result<QueryResult>> {
+        None
+    }
+
+    /// Called before each attempt to send the request envelope to a node, with 1-based attempt
+    /// numbers. Returning a response skips connecting to the node and sending the envelope, and the
+    /// response is treated as if it came from the node, including passing errors to the retry
+    /// policy. Such responses are not reported to load balancing strategies and metrics recorders.
+    fn before_attempt(
+        &self,
+        _envelope: &Envelope,
+        _node: SocketAddr,
+        _attempt: usize,
+    ) -> Option<Result<Envelope>> {
+        None
+    }
+
+    /// Called with the final result of the request, including results returned by
+    /// [`before_request`](RequestInterceptor::before_request).
+    fn after_response(&self, _request: &RequestInfo, _result: &Result<QueryResult>) {}
+}
@@ -43,17 +43,25 @@
 #[macro_use]
 mod macros;
 
+pub mod address_translator;
 pub mod cluster;
 pub mod envelope_parser;
 pub mod load_balancing;
 
 pub mod frame_encoding;
 pub mod future;
+pub mod interceptor;
+pub mod metrics;
+pub mod request_tracker;
 pub mod retry;
 pub mod speculative_execution;
 pub mod statement;
+pub mod timestamp_generator;
 pub mod transport;
 
+#[cfg(test)]
+pub(crate) mod test_utils;
+
 pub use cassandra_protocol::authenticators;
 pub use cassandra_protocol::compression;
 pub use cassandra_protocol::consistency;
@@ -1,13 +1,25 @@
+mod in_flight_aware;
 mod initializing_wrapper;
+mod latency_aware;
 pub mod node_distance_evaluator;
 mod random;
 mod request;
 mod round_robin;
+#[cfg(test)]
+pub(crate) mod test_utils;
 mod topology_aware;
 
 use std::sync::Arc;
+use std::time::Duration;
 
+pub use self::in_flight_aware::{
+    InFlightAwareLoadBalancingStrategy, DEFAULT_MAX_CONSECUTIVE_TIMEOUTS,
+    DEFAULT_OVERLOADED_PENALTY,
+};
 pub(crate) use self::initializing_wrapper::InitializingWrapperLoadBalancingStrategy;
+pub use self::latency_aware::{
+    LatencyAwareConfig, LatencyAwareConfigBuilder, LatencyAwareLoadBalancingStrategy,
+};
 pub use self::random::RandomLoadBalancingStrategy;
 pub use self::request::Request;
 pub use self::round_robin::RoundRobinLoadBalancingStrategy;
@@ -27,4 +39,8 @@ pub trait LoadBalancingStrategy<T: CdrsTransport, CM: ConnectionManager<T>> {
         request: Option<Request>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM>;
+
+    /// Called with the time it took given node to respond to a request. Timed out requests are
+    /// reported with the time spent waiting.
+    fn report_latency(&self, _node: &Node<T, CM>, _latency: Duration) {}
 }
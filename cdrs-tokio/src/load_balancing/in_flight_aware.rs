@@ -0,0 +1,194 @@
+use derivative::Derivative;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+
+use crate::cluster::topology::Node;
+use crate::cluster::{ClusterMetadata, ConnectionManager};
+use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
+use crate::transport::CdrsTransport;
+
+pub const DEFAULT_OVERLOADED_PENALTY: Duration = Duration::from_secs(5);
+pub const DEFAULT_MAX_CONSECUTIVE_TIMEOUTS: usize = 3;
+
+/// Wrapper strategy which takes node load into account, using the "power of two choices" approach:
+/// the first two nodes in the query plan of the inner strategy are compared and the one with fewer
+/// requests in flight goes first. When combined with
+/// [`TopologyAwareLoadBalancingStrategy`](crate::load_balancing::TopologyAwareLoadBalancingStrategy),
+/// which shuffles replicas, this means picking the less busy one of two random replicas.
+///
+/// Unhealthy nodes, i.e. which returned an `Overloaded` error recently or timed out too many times
+/// in a row, are moved to the back of the query plan.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct InFlightAwareLoadBalancingStrategy<
+    T: CdrsTransport,
+    CM: ConnectionManager<T>,
+    LB: LoadBalancingStrategy<T, CM>,
+> {
+    #[derivative(Debug = "ignore")]
+    inner: LB,
+    overloaded_penalty: Duration,
+    max_consecutive_timeouts: usize,
+    #[derivative(Debug = "ignore")]
+    _transport: PhantomData<T>,
+    #[derivative(Debug = "ignore")]
+    _connection_manager: PhantomData<CM>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
+    InFlightAwareLoadBalancingStrategy<T, CM, LB>
+{
+    /// Creates new strategy. Nodes are considered unhealthy for `overloaded_penalty` after
+    /// returning an `Overloaded` error, or after `max_consecutive_timeouts` requests in a row timed
+    /// out, until the node responds again.
+    pub fn new(inner: LB, overloaded_penalty: Duration, max_consecutive_timeouts: usize) -> Self {
+        InFlightAwareLoadBalancingStrategy {
+            inner,
+            overloaded_penalty,
+            max_consecutive_timeouts,
+            _transport: Default::default(),
+            _connection_manager: Default::default(),
+        }
+    }
+
+    /// Creates new strategy with default unhealthy node detection.
+    pub fn with_defaults(inner: LB) -> Self {
+        Self::new(
+            inner,
+            DEFAULT_OVERLOADED_PENALTY,
+            DEFAULT_MAX_CONSECUTIVE_TIMEOUTS,
+        )
+    }
+
+    fn is_healthy(&self, node: &Node<T, CM>, now: Instant) -> bool {
+        let recently_overloaded = node.last_overloaded().is_some_and(|last_overloaded| {
+            now.saturating_duration_since(last_overloaded) < self.overloaded_penalty
+        });
+
+        !recently_overloaded && node.consecutive_timeouts() < self.max_consecutive_timeouts
+    }
+
+    fn sort_query_plan(&self, query_plan: QueryPlan<T, CM>, now: Instant) -> QueryPlan<T, CM> {
+        let (mut healthy, unhealthy): (QueryPlan<T, CM>, QueryPlan<T, CM>) = query_plan
+            .into_iter()
+            .partition(|node| self.is_healthy(node, now));
+
+        if healthy.len() >= 2 && healthy[1].in_flight_requests() < healthy[0].in_flight_requests() {
+            healthy.swap(0, 1);
+        }
+
+        healthy.extend(unhealthy);
+        healthy
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
+    LoadBalancingStrategy<T, CM> for InFlightAwareLoadBalancingStrategy<T, CM, LB>
+{
+    fn query_plan(
+        &self,
+        request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        self.sort_query_plan(self.inner.query_plan(request, cluster), Instant::now())
+    }
+
+    fn report_latency(&self, node: &Node<T, CM>, latency: Duration) {
+        self.inner.report_latency(node, latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_error::{ErrorBody, ErrorType};
+    use std::time::{Duration, Instant};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::load_balancing::test_utils::{create_query_plan, ports, MockRoundRobinStrategy};
+    use crate::load_balancing::{
+        InFlightAwareLoadBalancingStrategy, RoundRobinLoadBalancingStrategy,
+    };
+    use crate::transport::MockCdrsTransport;
+
+    type MockStrategy = InFlightAwareLoadBalancingStrategy<
+        MockCdrsTransport,
+        MockConnectionManager<MockCdrsTransport>,
+        MockRoundRobinStrategy,
+    >;
+
+    fn create_strategy() -> MockStrategy {
+        InFlightAwareLoadBalancingStrategy::with_defaults(RoundRobinLoadBalancingStrategy::new())
+    }
+
+    #[test]
+    fn should_prefer_less_busy_node() {
+        let strategy = create_strategy();
+        let query_plan = create_query_plan();
+
+        let first_node = query_plan[0].clone();
+        let _first_request = first_node.start_request();
+        assert_eq!(first_node.in_flight_requests(), 1);
+
+        let query_plan = strategy.sort_query_plan(query_plan, Instant::now());
+        assert_eq!(ports(&query_plan), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn should_finish_in_flight_request_on_drop() {
+        let query_plan = create_query_plan();
+
+        let request = query_plan[0].start_request();
+        drop(request);
+
+        assert_eq!(query_plan[0].in_flight_requests(), 0);
+    }
+
+    #[test]
+    fn should_move_overloaded_node_to_back() {
+        let strategy = create_strategy();
+        let query_plan = create_query_plan();
+
+        query_plan[0].report_response(&Err(Error::Server {
+            body: ErrorBody {
+                message: "overloaded".into(),
+                ty: ErrorType::Overloaded,
+            },
+            addr: query_plan[0].broadcast_rpc_address(),
+        }));
+        assert_eq!(query_plan[0].overloaded_errors(), 1);
+
+        let now = Instant::now();
+        let sorted_query_plan = strategy.sort_query_plan(query_plan.clone(), now);
+        assert_eq!(ports(&sorted_query_plan), vec![2, 3, 1]);
+
+        let sorted_query_plan = strategy.sort_query_plan(query_plan, now + Duration::from_secs(10));
+        assert_eq!(ports(&sorted_query_plan), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn should_move_timing_out_node_to_back() {
+        let strategy = create_strategy();
+        let query_plan = create_query_plan();
+
+        for _ in 0..3 {
+            query_plan[1].report_response(&Err(Error::Timeout("timeout".into())));
+        }
+        assert_eq!(query_plan[1].timeout_errors(), 3);
+
+        let sorted_query_plan = strategy.sort_query_plan(query_plan.clone(), Instant::now());
+        assert_eq!(ports(&sorted_query_plan), vec![1, 3, 2]);
+
+        query_plan[1].report_response(&Err(Error::Server {
+            body: ErrorBody {
+                message: "invalid".into(),
+                ty: ErrorType::Invalid,
+            },
+            addr: query_plan[1].broadcast_rpc_address(),
+        }));
+        assert_eq!(query_plan[1].consecutive_timeouts(), 0);
+
+        let sorted_query_plan = strategy.sort_query_plan(query_plan, Instant::now());
+        assert_eq!(ports(&sorted_query_plan), vec![1, 2, 3]);
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::cluster::topology::Node;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
@@ -29,6 +30,10 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM
             self.contact_points_query_plan.clone()
         }
     }
+
+    fn report_latency(&self, node: &Node<T, CM>, latency: Duration) {
+        self.inner.report_latency(node, latency);
+    }
 }
 
 impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
@@ -0,0 +1,298 @@
+use derivative::Derivative;
+use fxhash::FxHashMap;
+use std::marker::PhantomData;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::cluster::topology::Node;
+use crate::cluster::{ClusterMetadata, ConnectionManager};
+use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
+use crate::transport::CdrsTransport;
+
+/// Configuration for [`LatencyAwareLoadBalancingStrategy`]. See [`LatencyAwareConfigBuilder`].
+#[derive(Clone, Copy, Debug)]
+pub struct LatencyAwareConfig {
+    exclusion_threshold: f64,
+    retry_period: Duration,
+    scale: Duration,
+    min_measured: usize,
+}
+
+impl Default for LatencyAwareConfig {
+    fn default() -> Self {
+        LatencyAwareConfig {
+            exclusion_threshold: 2.0,
+            retry_period: Duration::from_secs(10),
+            scale: Duration::from_millis(100),
+            min_measured: 50,
+        }
+    }
+}
+
+/// A builder for [`LatencyAwareConfig`].
+#[derive(Default, Clone, Debug)]
+pub struct LatencyAwareConfigBuilder {
+    config: LatencyAwareConfig,
+}
+
+impl LatencyAwareConfigBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Sets how many times slower than the fastest node a node needs to be, to be moved to the
+    /// back of query plans. Values lower than 1 are treated as 1.
+    #[must_use]
+    pub fn with_exclusion_threshold(mut self, exclusion_threshold: f64) -> Self {
+        self.config.exclusion_threshold = exclusion_threshold.max(1.0);
+        self
+    }
+
+    /// Sets how long a slow node is penalized without new measurements. After this period, the
+    /// node is treated normally again, so it gets a chance to prove it has recovered.
+    #[must_use]
+    pub fn with_retry_period(mut self, retry_period: Duration) -> Self {
+        self.config.retry_period = retry_period;
+        self
+    }
+
+    /// Sets the scale of the exponentially weighted average. The longer the time between
+    /// measurements relative to the scale, the less weight the previous average has.
+    #[must_use]
+    pub fn with_scale(mut self, scale: Duration) -> Self {
+        self.config.scale = scale;
+        self
+    }
+
+    /// Sets the minimum number of measurements of a node before its latency is taken into account.
+    #[must_use]
+    pub fn with_min_measured(mut self, min_measured: usize) -> Self {
+        self.config.min_measured = min_measured;
+        self
+    }
+
+    /// Build the resulting config.
+    #[must_use]
+    pub fn build(self) -> LatencyAwareConfig {
+        self.config
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct NodeLatency {
+    average: f64,
+    measured: usize,
+    updated: Instant,
+}
+
+impl NodeLatency {
+    fn new(latency: f64, now: Instant) -> Self {
+        NodeLatency {
+            average: latency,
+            measured: 1,
+            updated: now,
+        }
+    }
+
+    fn update(&mut self, latency: f64, now: Instant, scale: Duration) {
+        self.measured += 1;
+
+        let elapsed = now.saturating_duration_since(self.updated);
+        if elapsed.is_zero() {
+            return;
+        }
+
+        // the longer since the last update, the less weight the previous average has
+        let scaled_elapsed = elapsed.as_secs_f64() / scale.as_secs_f64().max(f64::EPSILON);
+        let previous_weight = (scaled_elapsed + 1.0).ln() / scaled_elapsed;
+
+        self.average = (1.0 - previous_weight) * latency + previous_weight * self.average;
+        self.updated = now;
+    }
+}
+
+/// Wrapper strategy which moves nodes much slower than the fastest one to the back of query plans
+/// of the inner strategy. Node latency is an exponentially weighted average of response times,
+/// which only takes effect after a minimum number of measurements. Slow nodes are penalized until
+/// no new measurements arrive within the retry period. See [`LatencyAwareConfig`].
+///
+/// Note: only nodes present in the query plan of the inner strategy are considered when looking
+/// for the fastest node.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct LatencyAwareLoadBalancingStrategy<
+    T: CdrsTransport,
+    CM: ConnectionManager<T>,
+    LB: LoadBalancingStrategy<T, CM>,
+> {
+    #[derivative(Debug = "ignore")]
+    inner: LB,
+    config: LatencyAwareConfig,
+    latencies: Mutex<FxHashMap<SocketAddr, NodeLatency>>,
+    #[derivative(Debug = "ignore")]
+    _transport: PhantomData<T>,
+    #[derivative(Debug = "ignore")]
+    _connection_manager: PhantomData<CM>,
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
+    LatencyAwareLoadBalancingStrategy<T, CM, LB>
+{
+    pub fn new(inner: LB, config: LatencyAwareConfig) -> Self {
+        LatencyAwareLoadBalancingStrategy {
+            inner,
+            config,
+            latencies: Default::default(),
+            _transport: Default::default(),
+            _connection_manager: Default::default(),
+        }
+    }
+
+    fn report_latency_at(&self, node_address: SocketAddr, latency: Duration, now: Instant) {
+        let latency = latency.as_secs_f64();
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(node_address)
+            .and_modify(|node_latency| node_latency.update(latency, now, self.config.scale))
+            .or_insert_with(|| NodeLatency::new(latency, now));
+    }
+
+    fn sort_query_plan(&self, query_plan: QueryPlan<T, CM>, now: Instant) -> QueryPlan<T, CM> {
+        let averages = {
+            let latencies = self.latencies.lock().unwrap();
+            query_plan
+                .iter()
+                .map(|node| {
+                    latencies
+                        .get(&node.broadcast_rpc_address())
+                        .filter(|latency| latency.measured >= self.config.min_measured)
+                        .map(|latency| (latency.average, latency.updated))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let min_average = averages
+            .iter()
+            .flatten()
+            .map(|(average, _)| *average)
+            .reduce(f64::min);
+
+        let min_average = match min_average {
+            Some(min_average) => min_average,
+            None => return query_plan,
+        };
+
+        let (fast, slow): (Vec<_>, Vec<_>) =
+            query_plan
+                .into_iter()
+                .zip(averages)
+                .partition(|(_, average)| match average {
+                    Some((average, updated)) => {
+                        *average <= min_average * self.config.exclusion_threshold
+                            || now.saturating_duration_since(*updated) > self.config.retry_period
+                    }
+                    None => true,
+                });
+
+        fast.into_iter().chain(slow).map(|(node, _)| node).collect()
+    }
+}
+
+impl<T: CdrsTransport, CM: ConnectionManager<T>, LB: LoadBalancingStrategy<T, CM>>
+    LoadBalancingStrategy<T, CM> for LatencyAwareLoadBalancingStrategy<T, CM, LB>
+{
+    fn query_plan(
+        &self,
+        request: Option<Request>,
+        cluster: &ClusterMetadata<T, CM>,
+    ) -> QueryPlan<T, CM> {
+        self.sort_query_plan(self.inner.query_plan(request, cluster), Instant::now())
+    }
+
+    fn report_latency(&self, node: &Node<T, CM>, latency: Duration) {
+        self.report_latency_at(node.broadcast_rpc_address(), latency, Instant::now());
+        self.inner.report_latency(node, latency);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::cluster::connection_manager::MockConnectionManager;
+    use crate::load_balancing::test_utils::{
+        create_query_plan, node_address, ports, MockRoundRobinStrategy,
+    };
+    use crate::load_balancing::{
+        LatencyAwareConfigBuilder, LatencyAwareLoadBalancingStrategy,
+        RoundRobinLoadBalancingStrategy,
+    };
+    use crate::transport::MockCdrsTransport;
+
+    type MockStrategy = LatencyAwareLoadBalancingStrategy<
+        MockCdrsTransport,
+        MockConnectionManager<MockCdrsTransport>,
+        MockRoundRobinStrategy,
+    >;
+
+    fn create_strategy() -> MockStrategy {
+        LatencyAwareLoadBalancingStrategy::new(
+            RoundRobinLoadBalancingStrategy::new(),
+            LatencyAwareConfigBuilder::new()
+                .with_exclusion_threshold(2.0)
+                .with_retry_period(Duration::from_secs(10))
+                .with_scale(Duration::from_millis(100))
+                .with_min_measured(2)
+                .build(),
+        )
+    }
+
+    fn report(strategy: &MockStrategy, port: u16, latency_ms: u64, now: Instant) {
+        strategy.report_latency_at(node_address(port), Duration::from_millis(latency_ms), now);
+    }
+
+    #[test]
+    fn should_move_slow_nodes_to_back() {
+        let strategy = create_strategy();
+        let now = Instant::now();
+
+        for elapsed in [0, 100] {
+            let now = now + Duration::from_millis(elapsed);
+            report(&strategy, 1, 50, now);
+            report(&strategy, 2, 10, now);
+            report(&strategy, 3, 15, now);
+        }
+
+        let query_plan = strategy.sort_query_plan(create_query_plan(), now);
+        assert_eq!(ports(&query_plan), vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn should_ignore_nodes_without_enough_measurements() {
+        let strategy = create_strategy();
+        let now = Instant::now();
+
+        report(&strategy, 1, 50, now);
+        report(&strategy, 2, 10, now);
+
+        let query_plan = strategy.sort_query_plan(create_query_plan(), now);
+        assert_eq!(ports(&query_plan), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn should_retry_slow_nodes_after_retry_period() {
+        let strategy = create_strategy();
+        let now = Instant::now();
+
+        report(&strategy, 1, 50, now);
+        report(&strategy, 1, 50, now + Duration::from_millis(100));
+        report(&strategy, 2, 10, now);
+        report(&strategy, 2, 10, now + Duration::from_secs(20));
+
+        let query_plan =
+            strategy.sort_query_plan(create_query_plan(), now + Duration::from_secs(20));
+        assert_eq!(ports(&query_plan), vec![1, 2, 3]);
+    }
+}
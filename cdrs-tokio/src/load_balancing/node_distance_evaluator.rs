@@ -48,6 +48,38 @@ impl TopologyAwareNodeDistanceEvaluator {
     }
 }
 
+/// An evaluator which is aware of node location in relation to local DC and rack. Nodes in the
+/// local rack of the local DC are [`NodeDistance::LocalRack`], which lets
+/// [`TopologyAwareLoadBalancingStrategy`](crate::load_balancing::TopologyAwareLoadBalancingStrategy)
+/// prefer them over other local nodes, e.g. to cut cross-availability zone traffic.
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
+pub struct RackAwareNodeDistanceEvaluator {
+    local_dc: String,
+    local_rack: String,
+}
+
+impl NodeDistanceEvaluator for RackAwareNodeDistanceEvaluator {
+    fn compute_distance(&self, node: &NodeInfo) -> Option<NodeDistance> {
+        Some(if node.datacenter != self.local_dc {
+            NodeDistance::Remote
+        } else if node.rack == self.local_rack {
+            NodeDistance::LocalRack
+        } else {
+            NodeDistance::Local
+        })
+    }
+}
+
+impl RackAwareNodeDistanceEvaluator {
+    /// Local DC and rack names represent the location local to where the driver is running.
+    pub fn new(local_dc: String, local_rack: String) -> Self {
+        RackAwareNodeDistanceEvaluator {
+            local_dc,
+            local_rack,
+        }
+    }
+}
+
 //noinspection DuplicatedCode
 #[cfg(test)]
 mod tests {
@@ -57,7 +89,9 @@ mod tests {
     use crate::cluster::topology::NodeDistance;
     use crate::cluster::NodeInfo;
     use crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator;
-    use crate::load_balancing::node_distance_evaluator::TopologyAwareNodeDistanceEvaluator;
+    use crate::load_balancing::node_distance_evaluator::{
+        RackAwareNodeDistanceEvaluator, TopologyAwareNodeDistanceEvaluator,
+    };
 
     #[test]
     fn should_return_topology_aware_distance() {
@@ -91,4 +125,32 @@ mod tests {
             NodeDistance::Local
         );
     }
+
+    #[test]
+    fn should_return_rack_aware_distance() {
+        let evaluator = RackAwareNodeDistanceEvaluator::new("dc1".into(), "rack1".into());
+        let node_info = |datacenter: &str, rack: &str| {
+            NodeInfo::new(
+                Uuid::new_v4(),
+                SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
+                None,
+                datacenter.into(),
+                Default::default(),
+                rack.into(),
+            )
+        };
+
+        assert_eq!(
+            evaluator.compute_distance(&node_info("dc1", "rack1")),
+            Some(NodeDistance::LocalRack)
+        );
+        assert_eq!(
+            evaluator.compute_distance(&node_info("dc1", "rack2")),
+            Some(NodeDistance::Local)
+        );
+        assert_eq!(
+            evaluator.compute_distance(&node_info("dc2", "rack1")),
+            Some(NodeDistance::Remote)
+        );
+    }
 }
@@ -1,13 +1,13 @@
 use cassandra_protocol::consistency::Consistency;
 use derive_more::Constructor;
 
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 
 /// A request executed by a `Session`.
 #[derive(Constructor, Clone, Debug)]
 pub struct Request<'a> {
     pub keyspace: Option<&'a str>,
-    pub token: Option<Murmur3Token>,
+    pub token: Option<Token>,
     pub routing_key: Option<&'a [u8]>,
     pub consistency: Option<Consistency>,
 }
@@ -0,0 +1,58 @@
+use cassandra_protocol::frame::Version;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::sync::watch;
+
+use crate::cluster::connection_manager::MockConnectionManager;
+use crate::cluster::connection_pool::ConnectionPoolFactory;
+use crate::cluster::topology::Node;
+use crate::load_balancing::{QueryPlan, RoundRobinLoadBalancingStrategy};
+use crate::metrics::NoopMetricsRecorder;
+use crate::retry::MockReconnectionPolicy;
+use crate::transport::MockCdrsTransport;
+
+pub(crate) type MockQueryPlan =
+    QueryPlan<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+pub(crate) type MockRoundRobinStrategy =
+    RoundRobinLoadBalancingStrategy<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>>;
+
+pub(crate) fn node_address(port: u16) -> SocketAddr {
+    SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port)
+}
+
+// creates a query plan with nodes listening on ports 1 to 3, which never connect
+pub(crate) fn create_query_plan() -> MockQueryPlan {
+    let (_, keyspace_receiver) = watch::channel(None);
+    let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
+    let reconnection_policy = MockReconnectionPolicy::new();
+    let connection_pool_factory = Arc::new(ConnectionPoolFactory::new(
+        Default::default(),
+        Version::V4,
+        connection_manager,
+        keyspace_receiver,
+        Arc::new(reconnection_policy),
+        Arc::new(NoopMetricsRecorder),
+    ));
+
+    (1..=3)
+        .map(|port| {
+            Arc::new(Node::new(
+                connection_pool_factory.clone(),
+                node_address(port),
+                None,
+                None,
+                None,
+                vec![],
+                "".into(),
+                "".into(),
+            ))
+        })
+        .collect()
+}
+
+pub(crate) fn ports(query_plan: &MockQueryPlan) -> Vec<u16> {
+    query_plan
+        .iter()
+        .map(|node| node.broadcast_rpc_address().port())
+        .collect()
+}
@@ -1,15 +1,13 @@
 use crate::cluster::topology::{KeyspaceMetadata, Node, NodeDistance, ReplicationStrategy};
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 use crate::cluster::{ClusterMetadata, ConnectionManager};
 use crate::load_balancing::{LoadBalancingStrategy, QueryPlan, Request};
 use crate::transport::CdrsTransport;
 use cassandra_protocol::consistency::Consistency;
 use derivative::Derivative;
-use fxhash::{FxHashMap, FxHashSet};
 use itertools::Itertools;
 use rand::prelude::*;
 use rand::rng;
-use std::cmp::Ordering as CmpOrdering;
 use std::marker::PhantomData;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -25,6 +23,10 @@ use std::sync::Arc;
 /// fashion. If local nodes are present in the cluster, only those will be used, unless a remote
 /// failover dc is allowed.
 ///
+/// If a rack-aware [`NodeDistanceEvaluator`](crate::load_balancing::node_distance_evaluator::NodeDistanceEvaluator)
+/// is used (e.g. [`RackAwareNodeDistanceEvaluator`](crate::load_balancing::node_distance_evaluator::RackAwareNodeDistanceEvaluator)),
+/// replicas in the local rack are put before other local replicas, followed by remote ones.
+///
 /// Note: if a referenced keyspace doesn't use `NetworkTopologyStrategy`, replica nodes will be
 /// chosen ignoring distance information.
 #[derive(Derivative)]
@@ -78,12 +80,13 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
         request: Request,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
+        let routing_key = request.routing_key;
         let token = request
             .token
-            .or_else(|| request.routing_key.map(Murmur3Token::generate));
+            .or_else(|| routing_key.map(|routing_key| cluster.partitioner().hash(routing_key)));
 
         if let Some(token) = token {
-            self.replicas_for_token(token, request.keyspace, request.consistency, cluster)
+            self.replicas_for_token(&token, request.keyspace, request.consistency, cluster)
         } else {
             self.round_robin_unignored_local_nodes(cluster)
         }
@@ -91,44 +94,44 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
 
     fn replicas_for_token(
         &self,
-        token: Murmur3Token,
+        token: &Token,
         keyspace: Option<&str>,
         consistency: Option<Consistency>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
         keyspace
-            .and_then(|keyspace| cluster.keyspace(keyspace))
-            .map(|keyspace| self.replicas_for_keyspace(token, keyspace, consistency, cluster))
+            .and_then(|keyspace| {
+                Some((
+                    cluster.keyspace(keyspace)?,
+                    cluster.replicas(keyspace, token)?,
+                ))
+            })
+            .map(|(keyspace, replicas)| {
+                self.replicas_for_keyspace(replicas, keyspace, consistency, cluster)
+            })
             .unwrap_or_else(|| self.round_robin_unignored_local_nodes(cluster))
     }
 
     fn replicas_for_keyspace(
         &self,
-        token: Murmur3Token,
+        replicas: &[Arc<Node<T, CM>>],
         keyspace: &KeyspaceMetadata,
         consistency: Option<Consistency>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
         match &keyspace.replication_strategy {
-            ReplicationStrategy::SimpleStrategy { replication_factor } => {
-                self.simple_strategy_replicas(token, *replication_factor, cluster)
+            ReplicationStrategy::NetworkTopologyStrategy { .. } => {
+                self.network_topology_strategy_replicas(replicas, consistency, cluster)
+            }
+            ReplicationStrategy::SimpleStrategy { .. } | ReplicationStrategy::Other => {
+                self.simple_strategy_replicas(replicas, cluster)
             }
-            ReplicationStrategy::NetworkTopologyStrategy {
-                datacenter_replication_factor,
-            } => self.network_topology_strategy_replicas(
-                token,
-                datacenter_replication_factor.clone(),
-                consistency,
-                cluster,
-            ),
-            ReplicationStrategy::Other => self.simple_strategy_replicas(token, 1, cluster),
         }
     }
 
     fn network_topology_strategy_replicas(
         &self,
-        token: Murmur3Token,
-        mut datacenter_replication_factor: FxHashMap<String, usize>,
+        replicas: &[Arc<Node<T, CM>>],
         consistency: Option<Consistency>,
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
@@ -139,84 +142,26 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
         // 4. append round-robin unignored local non-replicas
         // 5. optionally, add shuffled remote unignored non-replicas
 
-        let replicas = cluster.token_map().nodes_for_token(token).collect_vec();
+        let mut result = replicas.to_vec();
 
-        let desired_replica_count = datacenter_replication_factor.values().sum();
-        let mut same_rack_replicas: FxHashMap<String, usize> = datacenter_replication_factor
-            .iter()
-            .map(|(dc, replication_factor)| {
-                let rack_count = cluster.datacenter(dc).map(|dc| dc.rack_count).unwrap_or(0);
-                (dc.into(), replication_factor.saturating_sub(rack_count))
-            })
-            .collect();
-
-        let mut result = Vec::with_capacity(desired_replica_count);
-        let mut used_dc_racks: FxHashSet<(&str, &str)> = Default::default();
-
-        for replica in &replicas {
-            if let Some(datacenter_replication_factor) =
-                datacenter_replication_factor.get_mut(replica.datacenter())
-            {
-                if *datacenter_replication_factor == 0 {
-                    // found enough nodes in this datacenter
-                    continue;
-                }
-
-                let current_node_dc = replica.datacenter();
-                let current_node_rack = replica.rack();
-
-                if used_dc_racks.contains(&(current_node_dc, current_node_rack)) {
-                    // check if we need to put nodes from the same rack multiple times to meet
-                    // the replication factor
-                    if let Some(same_rack_replicas) = same_rack_replicas.get_mut(current_node_dc) {
-                        if *same_rack_replicas > 0 {
-                            *same_rack_replicas -= 1;
-                            *datacenter_replication_factor -= 1;
-                            result.push(replica.clone());
-                        }
-                    }
-                } else {
-                    *datacenter_replication_factor -= 1;
-
-                    used_dc_racks.insert((current_node_dc, current_node_rack));
-                    result.push(replica.clone());
-                }
-
-                if result.len() == desired_replica_count {
-                    break;
-                }
-            }
-        }
-
-        // result now contains mixed local/remote and ignored/unignored nodes - put local in front
-        result.sort_unstable_by(|a, b| {
-            let a_distance = a.distance();
-            let b_distance = b.distance();
-
-            if a_distance == b_distance {
-                return CmpOrdering::Equal;
-            }
-
-            if a_distance == Some(NodeDistance::Local) {
-                return CmpOrdering::Less;
-            }
-            if b_distance == Some(NodeDistance::Local) {
-                return CmpOrdering::Greater;
-            }
-
-            CmpOrdering::Equal
-        });
+        // result now contains mixed local/remote and ignored/unignored nodes - put local rack in
+        // front, followed by the rest of local and then remote
+        result.sort_unstable_by_key(|node| node.distance().unwrap_or(NodeDistance::Remote));
 
         // remove ignored
         result.retain(|node| !node.is_ignored());
 
         let mut rng = rng();
 
-        // find now many local nodes we have
-        let local_count = result.iter().position(|node| node.is_remote()).unwrap_or(0);
-        if local_count > 0 {
-            result[..local_count].shuffle(&mut rng);
-        }
+        // shuffle nodes with the same distance
+        let local_rack_count = result
+            .iter()
+            .take_while(|node| node.is_local_rack())
+            .count();
+        let local_count = result.iter().take_while(|node| node.is_local()).count();
+
+        result[..local_rack_count].shuffle(&mut rng);
+        result[local_rack_count..local_count].shuffle(&mut rng);
 
         // add unignored non-replicas
         let unignored_nodes = self.round_robin_unignored_local_nodes(cluster);
@@ -246,18 +191,20 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
 
     fn simple_strategy_replicas(
         &self,
-        token: Murmur3Token,
-        replica_count: usize,
+        replicas: &[Arc<Node<T, CM>>],
         cluster: &ClusterMetadata<T, CM>,
     ) -> QueryPlan<T, CM> {
-        let mut replicas = cluster
-            .token_map()
-            .nodes_for_token_capped(token, replica_count)
+        let mut replicas = replicas
+            .iter()
             .filter(|node| !node.is_ignored())
+            .cloned()
             .collect_vec();
 
         replicas.shuffle(&mut rng());
 
+        // prefer the local rack, if known
+        replicas.sort_by_key(|node| !node.is_local_rack());
+
         let unignored_nodes = self.round_robin_unignored_nodes(cluster);
         replicas
             .into_iter()
@@ -301,7 +248,7 @@ impl<T: CdrsTransport, CM: ConnectionManager<T>> TopologyAwareLoadBalancingStrat
 #[cfg(test)]
 mod tests {
     use cassandra_protocol::frame::Version;
-    use fxhash::FxHashMap;
+    use fxhash::{FxHashMap, FxHashSet};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
     use std::sync::{Arc, LazyLock};
     use tokio::sync::watch;
@@ -317,6 +264,7 @@ mod tests {
     use crate::load_balancing::{
         LoadBalancingStrategy, Request, TopologyAwareLoadBalancingStrategy,
     };
+    use crate::metrics::NoopMetricsRecorder;
     use crate::retry::MockReconnectionPolicy;
     use crate::transport::MockCdrsTransport;
 
@@ -328,6 +276,20 @@ mod tests {
 
     fn create_cluster(
     ) -> ClusterMetadata<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>> {
+        create_cluster_with_local_rack(None)
+    }
+
+    fn create_cluster_with_local_rack(
+        local_rack: Option<&str>,
+    ) -> ClusterMetadata<MockCdrsTransport, MockConnectionManager<MockCdrsTransport>> {
+        let local_distance = |rack: &str| {
+            if local_rack == Some(rack) {
+                Some(NodeDistance::LocalRack)
+            } else {
+                Some(NodeDistance::Local)
+            }
+        };
+
         let (_, keyspace_receiver) = watch::channel(None);
         let connection_manager = MockConnectionManager::<MockCdrsTransport>::new();
         let reconnection_policy = MockReconnectionPolicy::new();
@@ -337,6 +299,7 @@ mod tests {
             connection_manager,
             keyspace_receiver,
             Arc::new(reconnection_policy),
+            Arc::new(NoopMetricsRecorder),
         ));
 
         let mut nodes = FxHashMap::default();
@@ -347,9 +310,9 @@ mod tests {
                 SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 1),
                 None,
                 Some(*HOST_ID_1),
-                Some(NodeDistance::Local),
+                local_distance("r1"),
                 NodeState::Up,
-                vec![Murmur3Token::new(1), Murmur3Token::new(2)],
+                vec![Murmur3Token::new(1).into(), Murmur3Token::new(2).into()],
                 "r1".into(),
                 "dc1".into(),
             )),
@@ -361,9 +324,9 @@ mod tests {
                 SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 2),
                 None,
                 Some(*HOST_ID_2),
-                Some(NodeDistance::Local),
+                local_distance("r1"),
                 NodeState::Up,
-                vec![Murmur3Token::new(3), Murmur3Token::new(4)],
+                vec![Murmur3Token::new(3).into(), Murmur3Token::new(4).into()],
                 "r1".into(),
                 "dc1".into(),
             )),
@@ -375,9 +338,9 @@ mod tests {
                 SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 2)), 3),
                 None,
                 Some(*HOST_ID_3),
-                Some(NodeDistance::Local),
+                local_distance("r2"),
                 NodeState::Up,
-                vec![Murmur3Token::new(7)],
+                vec![Murmur3Token::new(7).into()],
                 "r2".into(),
                 "dc1".into(),
             )),
@@ -391,7 +354,7 @@ mod tests {
                 None,
                 None,
                 NodeState::Up,
-                vec![Murmur3Token::new(8)],
+                vec![Murmur3Token::new(8).into()],
                 "r2".into(),
                 "dc1".into(),
             )),
@@ -405,7 +368,7 @@ mod tests {
                 Some(*HOST_ID_4),
                 Some(NodeDistance::Remote),
                 NodeState::Up,
-                vec![Murmur3Token::new(5), Murmur3Token::new(6)],
+                vec![Murmur3Token::new(5).into(), Murmur3Token::new(6).into()],
                 "r1".into(),
                 "dc2".into(),
             )),
@@ -419,7 +382,7 @@ mod tests {
                 None,
                 None,
                 NodeState::Up,
-                vec![Murmur3Token::new(9)],
+                vec![Murmur3Token::new(9).into()],
                 "r1".into(),
                 "dc2".into(),
             )),
@@ -433,7 +396,7 @@ mod tests {
                 Some(*HOST_ID_5),
                 Some(NodeDistance::Remote),
                 NodeState::Up,
-                vec![Murmur3Token::new(0)],
+                vec![Murmur3Token::new(0).into()],
                 "r2".into(),
                 "dc2".into(),
             )),
@@ -450,25 +413,37 @@ mod tests {
         let mut keyspaces = FxHashMap::default();
         keyspaces.insert(
             "k1".into(),
-            KeyspaceMetadata::new(ReplicationStrategy::SimpleStrategy {
-                replication_factor: 2,
-            }),
+            Arc::new(KeyspaceMetadata::new(
+                "k1".into(),
+                ReplicationStrategy::SimpleStrategy {
+                    replication_factor: 2,
+                },
+            )),
         );
         keyspaces.insert(
             "k2".into(),
-            KeyspaceMetadata::new(ReplicationStrategy::NetworkTopologyStrategy {
-                datacenter_replication_factor: datacenter_replication_factor_2,
-            }),
+            Arc::new(KeyspaceMetadata::new(
+                "k2".into(),
+                ReplicationStrategy::NetworkTopologyStrategy {
+                    datacenter_replication_factor: datacenter_replication_factor_2,
+                },
+            )),
         );
         keyspaces.insert(
             "k3".into(),
-            KeyspaceMetadata::new(ReplicationStrategy::Other),
+            Arc::new(KeyspaceMetadata::new(
+                "k3".into(),
+                ReplicationStrategy::Other,
+            )),
         );
         keyspaces.insert(
             "k4".into(),
-            KeyspaceMetadata::new(ReplicationStrategy::NetworkTopologyStrategy {
-                datacenter_replication_factor: datacenter_replication_factor_4,
-            }),
+            Arc::new(KeyspaceMetadata::new(
+                "k4".into(),
+                ReplicationStrategy::NetworkTopologyStrategy {
+                    datacenter_replication_factor: datacenter_replication_factor_4,
+                },
+            )),
         );
 
         ClusterMetadata::new(nodes, keyspaces)
@@ -519,7 +494,12 @@ mod tests {
         let lb = TopologyAwareLoadBalancingStrategy::new(None, false);
 
         let query_plan = lb.query_plan(
-            Some(Request::new(None, Some(Murmur3Token::new(4)), None, None)),
+            Some(Request::new(
+                None,
+                Some(Murmur3Token::new(4).into()),
+                None,
+                None,
+            )),
             &cluster,
         );
         assert_eq!(query_plan.len(), 3);
@@ -537,7 +517,7 @@ mod tests {
         let query_plan = lb.query_plan(
             Some(Request::new(
                 Some("k3"),
-                Some(Murmur3Token::new(4)),
+                Some(Murmur3Token::new(4).into()),
                 None,
                 None,
             )),
@@ -555,7 +535,7 @@ mod tests {
         let query_plan = lb.query_plan(
             Some(Request::new(
                 Some("k1"),
-                Some(Murmur3Token::new(4)),
+                Some(Murmur3Token::new(4).into()),
                 None,
                 None,
             )),
@@ -582,7 +562,7 @@ mod tests {
         let query_plan = lb.query_plan(
             Some(Request::new(
                 Some("k2"),
-                Some(Murmur3Token::new(2)),
+                Some(Murmur3Token::new(2).into()),
                 None,
                 None,
             )),
@@ -615,7 +595,7 @@ mod tests {
         let query_plan = lb.query_plan(
             Some(Request::new(
                 Some("k4"),
-                Some(Murmur3Token::new(2)),
+                Some(Murmur3Token::new(2).into()),
                 None,
                 None,
             )),
@@ -643,7 +623,7 @@ mod tests {
         let query_plan = lb.query_plan(
             Some(Request::new(
                 Some("k4"),
-                Some(Murmur3Token::new(2)),
+                Some(Murmur3Token::new(2).into()),
                 None,
                 None,
             )),
@@ -663,4 +643,50 @@ mod tests {
         assert_eq!(query_plan[3].host_id().unwrap(), *HOST_ID_2);
         assert_eq!(query_plan[4].host_id().unwrap(), *HOST_ID_5);
     }
+
+    #[test]
+    fn should_prefer_local_rack_replicas_with_network_topology_strategy() {
+        let cluster = create_cluster_with_local_rack(Some("r2"));
+        let lb = TopologyAwareLoadBalancingStrategy::new(Some(5), false);
+
+        let query_plan = lb.query_plan(
+            Some(Request::new(
+                Some("k4"),
+                Some(Murmur3Token::new(2).into()),
+                None,
+                None,
+            )),
+            &cluster,
+        );
+
+        assert_eq!(query_plan.len(), 5);
+
+        // replicas come first, grouped by distance; nodes within a group are shuffled
+        let tiers = [
+            (NodeDistance::LocalRack, vec![*HOST_ID_3]),
+            (NodeDistance::Local, vec![*HOST_ID_1]),
+            (NodeDistance::Remote, vec![*HOST_ID_4]),
+        ];
+
+        let mut nodes = query_plan.iter();
+        for (distance, host_ids) in &tiers {
+            let tier: FxHashSet<_> = nodes
+                .by_ref()
+                .take(host_ids.len())
+                .map(|node| {
+                    assert_eq!(node.distance(), Some(*distance));
+                    node.host_id()
+                })
+                .collect();
+
+            assert_eq!(tier, host_ids.iter().copied().map(Some).collect());
+        }
+
+        // followed by non-replicas
+        for node in nodes {
+            assert!(!tiers
+                .iter()
+                .any(|(_, host_ids)| host_ids.iter().any(|id| node.host_id() == Some(*id))));
+        }
+    }
 }
@@ -0,0 +1,453 @@
+//! Driver metrics.
+//!
+//! The driver reports request latencies, errors, retries, speculative executions, connection pool
+//! sizes, reconnection attempts and transferred bytes to a [`MetricsRecorder`]. By default, all
+//! measurements are discarded by the [`NoopMetricsRecorder`]. The [`InMemoryMetricsRecorder`]
+//! aggregates them in memory, using HDR-style latency histograms, and its current state can be
+//! read with [`Session::metrics()`](crate::cluster::session::Session::metrics), e.g. to export it
+//! periodically to an external monitoring system. Custom recorders can forward measurements
+//! directly instead.
+//!
+//! Nodes are identified by the addresses the driver connects to.
+
+use cassandra_protocol::error::Error;
+use fxhash::FxHashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+// number of bits used to index linear sub-buckets within a power of two, which gives a relative
+// error below 1%
+const SUB_BUCKET_BITS: u32 = 7;
+const SUB_BUCKET_COUNT: u64 = 1 << SUB_BUCKET_BITS;
+const SUB_BUCKET_HALF_COUNT: u64 = SUB_BUCKET_COUNT / 2;
+
+/// Highest latency tracked by histograms. Higher values are recorded as this one.
+pub const MAX_TRACKABLE_LATENCY: Duration = Duration::from_secs(3600);
+
+/// Recorder of driver measurements. All methods have empty default implementations, so custom
+/// recorders only need to implement the ones they are interested in.
+pub trait MetricsRecorder {
+    /// Records a single attempt to get a response from a node. The error is present if the attempt
+    /// failed, including timeouts.
+    fn record_node_request(&self, _node: SocketAddr, _latency: Duration, _error: Option<&Error>) {}
+
+    /// Records a request sent through the session. The latency includes all retries and
+    /// speculative executions.
+    fn record_request(&self, _latency: Duration, _error: Option<&Error>) {}
+
+    /// Records a retry decided after a failed attempt on given node.
+    fn record_retry(&self, _node: SocketAddr) {}
+
+    /// Records a speculative execution started for a request.
+    fn record_speculative_execution(&self) {}
+
+    /// Records the current number of open connections in the pool of given node.
+    fn record_open_connections(&self, _node: SocketAddr, _count: usize) {}
+
+    /// Records an attempt to reconnect to given node.
+    fn record_reconnection_attempt(&self, _node: SocketAddr) {}
+
+    /// Records bytes written to a connection to given node.
+    fn record_bytes_sent(&self, _node: SocketAddr, _bytes: usize) {}
+
+    /// Records bytes read from a connection to given node.
+    fn record_bytes_received(&self, _node: SocketAddr, _bytes: usize) {}
+
+    /// Returns a snapshot of aggregated metrics, if the recorder keeps them.
+    fn metrics(&self) -> Option<Metrics> {
+        None
+    }
+}
+
+/// Recorder which discards all measurements.
+#[derive(Default, Clone, Copy, Debug)]
+pub struct NoopMetricsRecorder;
+
+impl MetricsRecorder for NoopMetricsRecorder {}
+
+/// Snapshot of a latency histogram.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistogramSnapshot {
+    counts: Vec<u64>,
+    count: u64,
+    sum: u64,
+    max: u64,
+}
+
+impl HistogramSnapshot {
+    /// Returns the number of recorded values.
+    #[inline]
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of recorded values, or zero if there are none.
+    pub fn mean(&self) -> Duration {
+        Duration::from_micros(self.sum.checked_div(self.count).unwrap_or_default())
+    }
+
+    /// Returns the highest recorded value.
+    #[inline]
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    /// Returns the value below which given quantile (0.0 - 1.0) of recorded values fall, or zero
+    /// if there are none.
+    pub fn value_at_quantile(&self, quantile: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+
+        let target = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+
+        let mut total = 0;
+        for (index, count) in self.counts.iter().enumerate() {
+            total += count;
+            if total >= target {
+                return Duration::from_micros(highest_equivalent_value(index).min(self.max));
+            }
+        }
+
+        self.max()
+    }
+
+    /// Returns non-empty buckets as pairs of the highest value in a bucket and the number of
+    /// values in it, in increasing order. Useful for exporting to other histogram formats.
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.counts
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(index, count)| {
+                (
+                    Duration::from_micros(highest_equivalent_value(index)),
+                    *count,
+                )
+            })
+    }
+}
+
+// returns the histogram bucket for given value in microseconds
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKET_COUNT {
+        return value as usize;
+    }
+
+    let shift = u64::BITS - value.leading_zeros() - SUB_BUCKET_BITS;
+    (shift as u64 * SUB_BUCKET_HALF_COUNT + (value >> shift)) as usize
+}
+
+// returns the highest value in microseconds falling into given bucket
+fn highest_equivalent_value(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKET_COUNT {
+        return index;
+    }
+
+    let shift = index / SUB_BUCKET_HALF_COUNT - 1;
+    let sub_bucket = index - shift * SUB_BUCKET_HALF_COUNT;
+    ((sub_bucket + 1) << shift) - 1
+}
+
+/// Lock-free latency histogram with log-linear buckets, in the spirit of HDR histograms. Values
+/// are tracked with microsecond resolution up to [`MAX_TRACKABLE_LATENCY`].
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    counts: Box<[AtomicU64]>,
+    sum: AtomicU64,
+    max: AtomicU64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        let bucket_count = bucket_index(MAX_TRACKABLE_LATENCY.as_micros() as u64) + 1;
+        LatencyHistogram {
+            counts: (0..bucket_count).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+            max: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LatencyHistogram {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records given latency.
+    pub fn record(&self, latency: Duration) {
+        let value = latency.min(MAX_TRACKABLE_LATENCY).as_micros() as u64;
+
+        self.counts[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// Returns current state of the histogram. Values recorded concurrently might be only partially
+    /// visible.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|count| count.load(Ordering::Relaxed))
+            .collect();
+
+        HistogramSnapshot {
+            count: counts.iter().sum(),
+            counts,
+            sum: self.sum.load(Ordering::Relaxed),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of metrics of a single node.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeMetrics {
+    /// Latencies of single attempts to get a response from the node.
+    pub request_latency: HistogramSnapshot,
+    /// Number of failed attempts, including timeouts.
+    pub errors: u64,
+    /// Number of timed out attempts.
+    pub timeouts: u64,
+    /// Number of retries after failed attempts.
+    pub retries: u64,
+    /// Last reported number of open connections.
+    pub open_connections: u64,
+    pub reconnection_attempts: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+}
+
+/// Snapshot of driver metrics.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Metrics {
+    /// Latencies of requests sent through the session, including retries and speculative
+    /// executions.
+    pub request_latency: HistogramSnapshot,
+    /// Number of failed requests.
+    pub request_errors: u64,
+    pub speculative_executions: u64,
+    pub nodes: FxHashMap<SocketAddr, NodeMetrics>,
+}
+
+#[derive(Default, Debug)]
+struct NodeCounters {
+    request_latency: LatencyHistogram,
+    errors: AtomicU64,
+    timeouts: AtomicU64,
+    retries: AtomicU64,
+    open_connections: AtomicU64,
+    reconnection_attempts: AtomicU64,
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+}
+
+impl NodeCounters {
+    fn snapshot(&self) -> NodeMetrics {
+        NodeMetrics {
+            request_latency: self.request_latency.snapshot(),
+            errors: self.errors.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            open_connections: self.open_connections.load(Ordering::Relaxed),
+            reconnection_attempts: self.reconnection_attempts.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Recorder aggregating measurements in memory. Counters are never reset, so exporters should
+/// compute rates from differences between snapshots.
+#[derive(Default, Debug)]
+pub struct InMemoryMetricsRecorder {
+    request_latency: LatencyHistogram,
+    request_errors: AtomicU64,
+    speculative_executions: AtomicU64,
+    nodes: RwLock<FxHashMap<SocketAddr, Arc<NodeCounters>>>,
+}
+
+impl InMemoryMetricsRecorder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn node(&self, node: SocketAddr) -> Arc<NodeCounters> {
+        if let Some(counters) = self.nodes.read().unwrap().get(&node) {
+            return counters.clone();
+        }
+
+        self.nodes.write().unwrap().entry(node).or_default().clone()
+    }
+}
+
+impl MetricsRecorder for InMemoryMetricsRecorder {
+    fn record_node_request(&self, node: SocketAddr, latency: Duration, error: Option<&Error>) {
+        let counters = self.node(node);
+        counters.request_latency.record(latency);
+
+        if let Some(error) = error {
+            counters.errors.fetch_add(1, Ordering::Relaxed);
+
+            if matches!(error, Error::Timeout(_)) {
+                counters.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    fn record_request(&self, latency: Duration, error: Option<&Error>) {
+        self.request_latency.record(latency);
+
+        if error.is_some() {
+            self.request_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn record_retry(&self, node: SocketAddr) {
+        self.node(node).retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_speculative_execution(&self) {
+        self.speculative_executions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_open_connections(&self, node: SocketAddr, count: usize) {
+        self.node(node)
+            .open_connections
+            .store(count as u64, Ordering::Relaxed);
+    }
+
+    fn record_reconnection_attempt(&self, node: SocketAddr) {
+        self.node(node)
+            .reconnection_attempts
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_bytes_sent(&self, node: SocketAddr, bytes: usize) {
+        self.node(node)
+            .bytes_sent
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn record_bytes_received(&self, node: SocketAddr, bytes: usize) {
+        self.node(node)
+            .bytes_received
+            .fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn metrics(&self) -> Option<Metrics> {
+        Some(Metrics {
+            request_latency: self.request_latency.snapshot(),
+            request_errors: self.request_errors.load(Ordering::Relaxed),
+            speculative_executions: self.speculative_executions.load(Ordering::Relaxed),
+            nodes: self
+                .nodes
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(node, counters)| (*node, counters.snapshot()))
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::error::Error;
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+
+    use crate::metrics::{
+        bucket_index, highest_equivalent_value, InMemoryMetricsRecorder, LatencyHistogram,
+        MetricsRecorder, MAX_TRACKABLE_LATENCY,
+    };
+
+    #[test]
+    fn should_map_values_to_buckets() {
+        for value in [0, 1, 127, 128, 129, 255, 256, 1000, 123_456, 3_600_000_000] {
+            let index = bucket_index(value);
+            assert!(highest_equivalent_value(index) >= value);
+            assert!(index == 0 || highest_equivalent_value(index - 1) < value);
+        }
+
+        assert_eq!(bucket_index(127) + 1, bucket_index(128));
+        assert_eq!(highest_equivalent_value(bucket_index(128)), 129);
+    }
+
+    #[test]
+    fn should_compute_quantiles() {
+        let histogram = LatencyHistogram::new();
+        for value in 1..=1000 {
+            histogram.record(Duration::from_millis(value));
+        }
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 1000);
+        assert_eq!(snapshot.max(), Duration::from_millis(1000));
+
+        for (quantile, expected) in [(0.5, 500.0), (0.99, 990.0), (1.0, 1000.0)] {
+            let value = snapshot.value_at_quantile(quantile).as_secs_f64() * 1000.0;
+            assert!(
+                (value - expected).abs() / expected < 0.01,
+                "{}: {}",
+                quantile,
+                value
+            );
+        }
+
+        assert_eq!(
+            snapshot.buckets().map(|(_, count)| count).sum::<u64>(),
+            1000
+        );
+    }
+
+    #[test]
+    fn should_clamp_high_values() {
+        let histogram = LatencyHistogram::new();
+        histogram.record(MAX_TRACKABLE_LATENCY * 2);
+
+        assert_eq!(histogram.snapshot().max(), MAX_TRACKABLE_LATENCY);
+    }
+
+    #[test]
+    fn should_aggregate_node_metrics() {
+        let node = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9042);
+        let recorder = InMemoryMetricsRecorder::new();
+
+        recorder.record_node_request(node, Duration::from_millis(1), None);
+        recorder.record_node_request(
+            node,
+            Duration::from_millis(2),
+            Some(&Error::Timeout("timeout".into())),
+        );
+        recorder.record_node_request(
+            node,
+            Duration::from_millis(3),
+            Some(&Error::General("error".into())),
+        );
+        recorder.record_retry(node);
+        recorder.record_open_connections(node, 2);
+        recorder.record_bytes_sent(node, 10);
+        recorder.record_bytes_received(node, 20);
+        recorder.record_request(Duration::from_millis(6), None);
+        recorder.record_speculative_execution();
+
+        let metrics = recorder.metrics().unwrap();
+        assert_eq!(metrics.request_latency.count(), 1);
+        assert_eq!(metrics.request_errors, 0);
+        assert_eq!(metrics.speculative_executions, 1);
+
+        let node_metrics = &metrics.nodes[&node];
+        assert_eq!(node_metrics.request_latency.count(), 3);
+        assert_eq!(node_metrics.errors, 2);
+        assert_eq!(node_metrics.timeouts, 1);
+        assert_eq!(node_metrics.retries, 1);
+        assert_eq!(node_metrics.open_connections, 2);
+        assert_eq!(node_metrics.bytes_sent, 10);
+        assert_eq!(node_metrics.bytes_received, 20);
+    }
+}
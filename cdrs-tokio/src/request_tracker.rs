@@ -0,0 +1,265 @@
+//! Tracking of completed requests.
+//!
+//! A [`RequestTracker`] is called once per request sent through a
+//! [`Session`](crate::cluster::session::Session), with a [`RequestReport`] containing the query,
+//! its parameters, every attempt to get a response from a node and the total latency. The built-in
+//! [`RequestLogger`] logs slow and failed requests.
+
+use cassandra_protocol::error::{Error, Result};
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::*;
+
+use crate::cluster::QueryResult;
+use crate::interceptor::RequestInfo;
+use crate::retry::RetryDecision;
+use crate::statement::StatementParams;
+
+/// Default threshold above which [`RequestLogger`] considers requests slow.
+pub const DEFAULT_SLOW_REQUEST_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Default number of characters of query text logged by [`RequestLogger`].
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 500;
+
+/// A single attempt to get a response from a node.
+#[derive(Clone, Debug)]
+pub struct AttemptInfo {
+    /// Connect address of the node, as used by [`MetricsRecorder`](crate::metrics::MetricsRecorder).
+    pub node: SocketAddr,
+    pub latency: Duration,
+    /// Error returned by the node, if the attempt failed.
+    pub error: Option<Error>,
+    /// Decision of the retry policy, if the attempt failed.
+    pub retry_decision: Option<RetryDecision>,
+}
+
+impl Display for AttemptInfo {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:?}", self.node, self.latency)?;
+
+        if let Some(error) = &self.error {
+            write!(f, ", error: {error}")?;
+        }
+
+        if let Some(retry_decision) = &self.retry_decision {
+            write!(f, ", retry decision: {retry_decision}")?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+/// Collector of request attempts, shared by all speculative executions of a request.
+#[derive(Default, Debug)]
+pub struct RequestAttempts {
+    attempts: Mutex<Vec<AttemptInfo>>,
+}
+
+impl RequestAttempts {
+    pub fn record(&self, attempt: AttemptInfo) {
+        self.attempts.lock().unwrap().push(attempt);
+    }
+
+    /// Returns recorded attempts, in the order of completion.
+    pub fn into_attempts(self) -> Vec<AttemptInfo> {
+        self.attempts.into_inner().unwrap()
+    }
+}
+
+/// Report of a completed request.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestReport<'a> {
+    pub request: RequestInfo<'a>,
+    /// Parameters of the request, after being changed by request interceptors.
+    pub parameters: &'a StatementParams,
+    /// Attempts in the order of completion. Empty if the request was short-circuited by an
+    /// interceptor or no node was available.
+    pub attempts: &'a [AttemptInfo],
+    /// Total latency, including all retries and speculative executions.
+    pub latency: Duration,
+    pub result: &'a Result<QueryResult>,
+}
+
+impl RequestReport<'_> {
+    /// Returns the connect address of the last node which was attempted. If the request succeeded,
+    /// it's the node which sent the response.
+    pub fn coordinator(&self) -> Option<SocketAddr> {
+        self.attempts.last().map(|attempt| attempt.node)
+    }
+}
+
+/// Tracker of requests sent through a session.
+pub trait RequestTracker {
+    /// Called when a request completes, successfully or not.
+    fn on_request_complete(&self, report: &RequestReport);
+}
+
+/// Tracker logging requests slower than a threshold and failed requests at warn level. Logged query
+/// texts have literal values replaced with `?` and are truncated, while bound values are only
+/// counted, so no data ends up in logs.
+#[derive(Clone, Debug)]
+pub struct RequestLogger {
+    slow_request_threshold: Duration,
+    max_query_length: usize,
+}
+
+impl Default for RequestLogger {
+    fn default() -> Self {
+        RequestLogger::new(DEFAULT_SLOW_REQUEST_THRESHOLD)
+    }
+}
+
+impl RequestLogger {
+    pub fn new(slow_request_threshold: Duration) -> Self {
+        RequestLogger {
+            slow_request_threshold,
+            max_query_length: DEFAULT_MAX_QUERY_LENGTH,
+        }
+    }
+
+    /// Sets the number of characters of query text to log.
+    #[must_use]
+    pub fn with_max_query_length(mut self, max_query_length: usize) -> Self {
+        self.max_query_length = max_query_length;
+        self
+    }
+}
+
+impl RequestTracker for RequestLogger {
+    fn on_request_complete(&self, report: &RequestReport) {
+        let message = match report.result {
+            Err(_) => "Request failed!",
+            Ok(_) if report.latency >= self.slow_request_threshold => "Slow request.",
+            Ok(_) => return,
+        };
+
+        let parameters = report.parameters;
+        warn!(
+            kind = %report.request.kind,
+            query = report
+                .request
+                .query
+                .map(|query| redact_query(query, self.max_query_length)),
+            keyspace = parameters.keyspace.as_deref(),
+            consistency = %parameters.query_params.consistency,
+            values = parameters
+                .query_params
+                .values
+                .as_ref()
+                .map(|values| values.len())
+                .unwrap_or_default(),
+            page_size = parameters.query_params.page_size,
+            is_idempotent = parameters.is_idempotent,
+            coordinator = report.coordinator().map(field::display),
+            attempts = %report.attempts.iter().join(", "),
+            latency = ?report.latency,
+            error = report.result.as_ref().err().map(field::display),
+            "{}",
+            message
+        );
+    }
+}
+
+// replaces string, numeric, blob and uuid literals in given query with `?` and truncates it to
+// given number of characters
+fn redact_query(query: &str, max_length: usize) -> String {
+    let mut redacted = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // quotes inside string literals are escaped by doubling them
+                while let Some(c) = chars.next() {
+                    if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                        break;
+                    }
+                }
+
+                redacted.push('?');
+            }
+            '$' if chars.next_if_eq(&'$').is_some() => {
+                let mut previous = None;
+                for c in chars.by_ref() {
+                    if c == '$' && previous == Some('$') {
+                        break;
+                    }
+
+                    previous = Some(c);
+                }
+
+                redacted.push('?');
+            }
+            '"' => {
+                // quoted identifiers are kept as they are
+                redacted.push(c);
+                while let Some(c) = chars.next() {
+                    redacted.push(c);
+                    if c == '"' && chars.next_if_eq(&'"').map(|c| redacted.push(c)).is_none() {
+                        break;
+                    }
+                }
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    word.push(c);
+                }
+
+                let is_uuid = word.len() == 8
+                    && word.chars().all(|c| c.is_ascii_hexdigit())
+                    && chars.peek() == Some(&'-');
+
+                if word.starts_with(|c: char| c.is_ascii_digit()) || is_uuid {
+                    // numbers, including floats with exponents, blobs and uuids
+                    while chars
+                        .next_if(|c| c.is_alphanumeric() || *c == '.' || *c == '-')
+                        .is_some()
+                    {}
+
+                    redacted.push('?');
+                } else {
+                    redacted.push_str(&word);
+                }
+            }
+            c => redacted.push(c),
+        }
+    }
+
+    match redacted.char_indices().nth(max_length) {
+        Some((index, _)) => {
+            redacted.truncate(index);
+            redacted.push_str("...");
+            redacted
+        }
+        None => redacted,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::request_tracker::redact_query;
+
+    #[test]
+    fn should_redact_literals() {
+        assert_eq!(
+            redact_query(
+                "SELECT * FROM ks.t1 WHERE id = 5 AND name = 'it''s' AND f = -1.5e-3 AND u = \
+                 123e4567-e89b-12d3-a456-426614174000 AND v IN (a23e4567-e89b-12d3-a456-426614174000) \
+                 AND b = 0xcafe AND \"Quoted\"\"1\" = $$it's$$ LIMIT 10",
+                1000
+            ),
+            "SELECT * FROM ks.t1 WHERE id = ? AND name = ? AND f = -? AND u = ? AND v IN (?) \
+             AND b = ? AND \"Quoted\"\"1\" = ? LIMIT ?"
+        );
+    }
+
+    #[test]
+    fn should_truncate_query() {
+        assert_eq!(redact_query("SELECT * FROM t", 8), "SELECT *...");
+        assert_eq!(redact_query("SELECT * FROM t", 15), "SELECT * FROM t");
+    }
+}
@@ -1,8 +1,11 @@
 use derive_more::Display;
+use std::net::SocketAddr;
+use std::time::Duration;
 
+use cassandra_protocol::consistency::Consistency;
 use cassandra_protocol::error::Error;
 use cassandra_protocol::frame::message_error::{
-    ErrorBody, ErrorType, ReadTimeoutError, WriteTimeoutError, WriteType,
+    ErrorBody, ErrorType, ReadTimeoutError, UnavailableError, WriteTimeoutError, WriteType,
 };
 
 #[derive(Debug, PartialEq, Eq, Ord, PartialOrd, Hash, Copy, Clone, Display)]
@@ -10,12 +13,25 @@ pub enum RetryDecision {
     RetrySameNode,
     RetryNextNode,
     DontRetry,
+    /// Retry on the same node with given consistency. Only applicable to queries, executions and
+    /// batches - other requests are not retried.
+    #[display("RetryWithConsistency({_0})")]
+    RetryWithConsistency(Consistency),
+    /// Retry on the same node after given delay.
+    #[display("RetryAfter({_0:?})")]
+    RetryAfter(Duration),
 }
 
 /// Information about a failed query.
 pub struct QueryInfo<'a> {
     pub error: &'a Error,
     pub is_idempotent: bool,
+    /// Consistency used by the failed attempt, if the request has one.
+    pub consistency: Option<Consistency>,
+    /// Number of attempts made so far, including the failed one.
+    pub attempt: usize,
+    /// Broadcast RPC address of the node, which failed.
+    pub node_address: SocketAddr,
 }
 
 /// Query-specific information about current state of retrying.
@@ -70,8 +86,11 @@ pub struct DefaultRetrySession {
 impl RetrySession for DefaultRetrySession {
     fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
         match query_info.error {
+            // the request never left the client, so it's safe to send it elsewhere
+            Error::ConnectionBusy(_) => RetryDecision::RetryNextNode,
             Error::Io(_)
             | Error::General(_)
+            | Error::Timeout(_)
             | Error::Server {
                 body:
                     ErrorBody {
@@ -165,3 +184,319 @@ impl RetrySession for DefaultRetrySession {
         }
     }
 }
+
+/// A retry policy which retries with lower consistency, if the number of replicas which responded
+/// or are alive suggests it might succeed. Other errors are handled like in
+/// [`DefaultRetryPolicy`].
+///
+/// Lowering consistency breaks the consistency guarantees of the original request, so this policy
+/// should only be used when availability is more important than consistency. Behaviour based on
+/// [DataStax Java Driver](https://docs.datastax.com/en/developer/java-driver/4.10/manual/core/retries/#downgrading-consistency-retry-policy)
+#[derive(Default, Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct DowngradingConsistencyRetryPolicy;
+
+impl RetryPolicy for DowngradingConsistencyRetryPolicy {
+    fn new_session(&self) -> Box<dyn RetrySession + Send + Sync> {
+        Box::<DowngradingConsistencyRetrySession>::default()
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct DowngradingConsistencyRetrySession {
+    was_retry: bool,
+    default_session: DefaultRetrySession,
+}
+
+impl DowngradingConsistencyRetrySession {
+    // returns the highest consistency, which the given number of replicas can satisfy
+    fn max_likely_to_work_consistency(known_ok: i32, current: Consistency) -> Option<Consistency> {
+        if known_ok >= 3 {
+            Some(Consistency::Three)
+        } else if known_ok == 2 {
+            Some(Consistency::Two)
+        } else if known_ok == 1 || current == Consistency::EachQuorum {
+            // each quorum doesn't report a global number of alive replicas, so there might be a
+            // node up in some other datacenter
+            Some(Consistency::One)
+        } else {
+            None
+        }
+    }
+
+    fn downgrade(known_ok: i32, current: Consistency) -> RetryDecision {
+        Self::max_likely_to_work_consistency(known_ok, current)
+            .map(RetryDecision::RetryWithConsistency)
+            .unwrap_or(RetryDecision::DontRetry)
+    }
+}
+
+impl RetrySession for DowngradingConsistencyRetrySession {
+    fn decide(&mut self, query_info: QueryInfo) -> RetryDecision {
+        match query_info.error {
+            Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::Unavailable(error @ UnavailableError { .. }),
+                        ..
+                    },
+                ..
+            } => {
+                if self.was_retry {
+                    return RetryDecision::DontRetry;
+                }
+
+                self.was_retry = true;
+
+                if is_serial(error.cl) {
+                    // serial consistency cannot be downgraded, but another coordinator might
+                    // see more replicas
+                    RetryDecision::RetryNextNode
+                } else {
+                    Self::downgrade(error.alive, error.cl)
+                }
+            }
+            Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::ReadTimeout(error @ ReadTimeoutError { .. }),
+                        ..
+                    },
+                ..
+            } => {
+                if self.was_retry || is_serial(error.cl) {
+                    return RetryDecision::DontRetry;
+                }
+
+                self.was_retry = true;
+
+                if error.received < error.block_for {
+                    Self::downgrade(error.received, error.cl)
+                } else if !error.replica_has_responded() {
+                    RetryDecision::RetrySameNode
+                } else {
+                    RetryDecision::DontRetry
+                }
+            }
+            Error::Server {
+                body:
+                    ErrorBody {
+                        ty: ErrorType::WriteTimeout(error @ WriteTimeoutError { .. }),
+                        ..
+                    },
+                ..
+            } => {
+                if self.was_retry || !query_info.is_idempotent {
+                    return RetryDecision::DontRetry;
+                }
+
+                self.was_retry = true;
+
+                match error.write_type {
+                    WriteType::UnloggedBatch => Self::downgrade(error.received, error.cl),
+                    WriteType::BatchLog => RetryDecision::RetrySameNode,
+                    _ => RetryDecision::DontRetry,
+                }
+            }
+            _ => self.default_session.decide(query_info),
+        }
+    }
+}
+
+#[inline]
+fn is_serial(consistency: Consistency) -> bool {
+    consistency == Consistency::Serial || consistency == Consistency::LocalSerial
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::error::Error;
+    use cassandra_protocol::frame::message_error::{
+        ErrorBody, ErrorType, ReadTimeoutError, UnavailableError, WriteTimeoutError, WriteType,
+    };
+    use cassandra_protocol::frame::{FromCursor, Serialize, Version};
+    use std::io::Cursor;
+    use std::net::SocketAddr;
+
+    use crate::retry::{
+        DowngradingConsistencyRetryPolicy, QueryInfo, RetryDecision, RetryPolicy, RetrySession,
+    };
+
+    fn server_error(ty: ErrorType) -> Error {
+        Error::Server {
+            body: ErrorBody {
+                message: "error".into(),
+                ty,
+            },
+            addr: SocketAddr::from(([127, 0, 0, 1], 9042)),
+        }
+    }
+
+    fn read_timeout(cl: Consistency, received: i32, block_for: i32, data_present: bool) -> Error {
+        let mut bytes = cl.serialize_to_vec(Version::V4);
+        bytes.extend_from_slice(&received.to_be_bytes());
+        bytes.extend_from_slice(&block_for.to_be_bytes());
+        bytes.push(data_present as u8);
+
+        let error =
+            ReadTimeoutError::from_cursor(&mut Cursor::new(bytes.as_slice()), Version::V4).unwrap();
+        server_error(ErrorType::ReadTimeout(error))
+    }
+
+    fn write_timeout(cl: Consistency, received: i32, write_type: WriteType) -> Error {
+        server_error(ErrorType::WriteTimeout(WriteTimeoutError {
+            cl,
+            received,
+            block_for: 3,
+            write_type,
+            contentions: None,
+        }))
+    }
+
+    fn unavailable(cl: Consistency, alive: i32) -> Error {
+        server_error(ErrorType::Unavailable(UnavailableError {
+            cl,
+            required: 3,
+            alive,
+        }))
+    }
+
+    fn decide(session: &mut dyn RetrySession, error: &Error, is_idempotent: bool) -> RetryDecision {
+        session.decide(QueryInfo {
+            error,
+            is_idempotent,
+            consistency: Some(Consistency::Quorum),
+            attempt: 1,
+            node_address: SocketAddr::from(([127, 0, 0, 1], 9042)),
+        })
+    }
+
+    #[test]
+    fn should_downgrade_on_unavailable() {
+        let policy = DowngradingConsistencyRetryPolicy;
+
+        let error = unavailable(Consistency::All, 2);
+        let mut session = policy.new_session();
+        assert_eq!(
+            decide(session.as_mut(), &error, false),
+            RetryDecision::RetryWithConsistency(Consistency::Two)
+        );
+        assert_eq!(
+            decide(session.as_mut(), &error, false),
+            RetryDecision::DontRetry
+        );
+
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &unavailable(Consistency::Quorum, 0),
+                false
+            ),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &unavailable(Consistency::EachQuorum, 0),
+                false
+            ),
+            RetryDecision::RetryWithConsistency(Consistency::One)
+        );
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &unavailable(Consistency::Serial, 1),
+                false
+            ),
+            RetryDecision::RetryNextNode
+        );
+    }
+
+    #[test]
+    fn should_downgrade_on_read_timeout() {
+        let policy = DowngradingConsistencyRetryPolicy;
+
+        let error = read_timeout(Consistency::All, 3, 5, false);
+        let mut session = policy.new_session();
+        assert_eq!(
+            decide(session.as_mut(), &error, false),
+            RetryDecision::RetryWithConsistency(Consistency::Three)
+        );
+        assert_eq!(
+            decide(session.as_mut(), &error, false),
+            RetryDecision::DontRetry
+        );
+
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &read_timeout(Consistency::Quorum, 2, 2, false),
+                false
+            ),
+            RetryDecision::RetrySameNode
+        );
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &read_timeout(Consistency::Quorum, 2, 2, true),
+                false
+            ),
+            RetryDecision::DontRetry
+        );
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &read_timeout(Consistency::LocalSerial, 0, 2, false),
+                false
+            ),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn should_downgrade_on_write_timeout() {
+        let policy = DowngradingConsistencyRetryPolicy;
+
+        let error = write_timeout(Consistency::Quorum, 1, WriteType::UnloggedBatch);
+        assert_eq!(
+            decide(policy.new_session().as_mut(), &error, false),
+            RetryDecision::DontRetry
+        );
+
+        let mut session = policy.new_session();
+        assert_eq!(
+            decide(session.as_mut(), &error, true),
+            RetryDecision::RetryWithConsistency(Consistency::One)
+        );
+        assert_eq!(
+            decide(session.as_mut(), &error, true),
+            RetryDecision::DontRetry
+        );
+
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &write_timeout(Consistency::Quorum, 1, WriteType::BatchLog),
+                true
+            ),
+            RetryDecision::RetrySameNode
+        );
+        assert_eq!(
+            decide(
+                policy.new_session().as_mut(),
+                &write_timeout(Consistency::Quorum, 1, WriteType::Simple),
+                true
+            ),
+            RetryDecision::DontRetry
+        );
+    }
+
+    #[test]
+    fn should_display_decisions() {
+        assert_eq!(
+            RetryDecision::RetryWithConsistency(Consistency::One).to_string(),
+            "RetryWithConsistency(One)"
+        );
+        assert_eq!(RetryDecision::DontRetry.to_string(), "DontRetry");
+    }
+}
@@ -1,9 +1,11 @@
 use cassandra_protocol::query::QueryParams;
 use cassandra_protocol::types::value::Value;
 use derivative::Derivative;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 use crate::retry::RetryPolicy;
 use crate::speculative_execution::SpeculativeExecutionPolicy;
 
@@ -21,7 +23,7 @@ pub struct StatementParams {
     pub keyspace: Option<String>,
     /// The token to use for token-aware routing. A load balancer may use this information to
     /// determine which nodes to contact. Takes precedence over `routing_key`.
-    pub token: Option<Murmur3Token>,
+    pub token: Option<Token>,
     /// The partition key to use for token-aware routing. A load balancer may use this information
     /// to determine which nodes to contact. Alternative to `token`. Note: prepared statements
     /// with bound primary key values take precedence over this field.
@@ -39,4 +41,67 @@ pub struct StatementParams {
     /// Enable beta protocol features. Server will respond with ERROR if protocol version is marked
     /// as beta on server and client does not provide this flag.
     pub beta_protocol: bool,
+    /// Custom statement request timeout. Overrides the session-wide default, if any. The timeout
+    /// applies to each attempt of sending the statement to a node.
+    pub request_timeout: Option<Duration>,
+    /// Don't use the session timestamp generator for this statement. Has no effect if an explicit
+    /// timestamp is set.
+    pub skip_timestamp_generator: bool,
+    /// Custom payload sent along with the statement, e.g. for server-side triggers or proxies.
+    /// Requires protocol V4 or higher - ignored otherwise.
+    pub custom_payload: HashMap<String, Vec<u8>>,
+}
+
+impl From<QueryParams> for StatementParams {
+    fn from(query_params: QueryParams) -> Self {
+        StatementParams {
+            keyspace: query_params.keyspace.clone(),
+            query_params,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::consistency::Consistency;
+    use cassandra_protocol::query::{QueryParams, QueryValues};
+    use cassandra_protocol::types::CBytes;
+
+    use crate::statement::StatementParams;
+
+    #[test]
+    fn should_convert_query_params() {
+        let query_params = QueryParams {
+            consistency: Consistency::LocalQuorum,
+            with_names: true,
+            values: Some(QueryValues::NamedValues(
+                vec![("id".to_string(), 1.into())].into_iter().collect(),
+            )),
+            page_size: Some(100),
+            paging_state: Some(CBytes::new(vec![1, 2, 3])),
+            serial_consistency: Some(Consistency::LocalSerial),
+            timestamp: Some(1234),
+            keyspace: Some("ks".into()),
+            now_in_seconds: Some(5678),
+        };
+
+        let params = StatementParams::from(query_params.clone());
+
+        assert_eq!(params.query_params, query_params);
+        assert_eq!(params.keyspace.as_deref(), Some("ks"));
+
+        // fields without a QueryParams counterpart keep their defaults
+        assert!(!params.is_idempotent);
+        assert!(params.token.is_none());
+        assert!(params.routing_key.is_none());
+        assert!(!params.tracing);
+        assert!(!params.warnings);
+        assert!(params.speculative_execution_policy.is_none());
+        assert!(params.retry_policy.is_none());
+        assert!(!params.beta_protocol);
+        assert!(params.request_timeout.is_none());
+        assert!(!params.skip_timestamp_generator);
+        assert!(params.custom_payload.is_empty());
+    }
 }
@@ -3,9 +3,11 @@ use cassandra_protocol::query::{QueryFlags, QueryParams, QueryValues};
 use cassandra_protocol::types::value::Value;
 use cassandra_protocol::types::{CBytes, CInt, CLong};
 use derivative::Derivative;
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
-use crate::cluster::Murmur3Token;
+use crate::cluster::Token;
 use crate::retry::RetryPolicy;
 use crate::speculative_execution::SpeculativeExecutionPolicy;
 use crate::statement::StatementParams;
@@ -24,7 +26,7 @@ pub struct StatementParamsBuilder {
     is_idempotent: bool,
     keyspace: Option<String>,
     now_in_seconds: Option<CInt>,
-    token: Option<Murmur3Token>,
+    token: Option<Token>,
     routing_key: Option<Vec<Value>>,
     tracing: bool,
     warnings: bool,
@@ -33,6 +35,9 @@ pub struct StatementParamsBuilder {
     #[derivative(Debug = "ignore")]
     retry_policy: Option<Arc<dyn RetryPolicy + Send + Sync>>,
     beta_protocol: bool,
+    request_timeout: Option<Duration>,
+    skip_timestamp_generator: bool,
+    custom_payload: HashMap<String, Vec<u8>>,
 }
 
 impl StatementParamsBuilder {
@@ -118,8 +123,8 @@ impl StatementParamsBuilder {
 
     /// Sets new token for routing.
     #[must_use]
-    pub fn with_token(mut self, token: Murmur3Token) -> Self {
-        self.token = Some(token);
+    pub fn with_token(mut self, token: impl Into<Token>) -> Self {
+        self.token = Some(token.into());
         self
     }
 
@@ -168,6 +173,27 @@ impl StatementParamsBuilder {
         self
     }
 
+    /// Sets custom statement request timeout.
+    #[must_use]
+    pub fn with_request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = Some(request_timeout);
+        self
+    }
+
+    /// Disables the session timestamp generator for this statement.
+    #[must_use]
+    pub fn skip_timestamp_generator(mut self, value: bool) -> Self {
+        self.skip_timestamp_generator = value;
+        self
+    }
+
+    /// Sets custom payload.
+    #[must_use]
+    pub fn with_custom_payload(mut self, custom_payload: HashMap<String, Vec<u8>>) -> Self {
+        self.custom_payload = custom_payload;
+        self
+    }
+
     #[must_use]
     pub fn build(self) -> StatementParams {
         StatementParams {
@@ -191,6 +217,9 @@ impl StatementParamsBuilder {
             speculative_execution_policy: self.speculative_execution_policy,
             retry_policy: self.retry_policy,
             beta_protocol: self.beta_protocol,
+            request_timeout: self.request_timeout,
+            skip_timestamp_generator: self.skip_timestamp_generator,
+            custom_payload: self.custom_payload,
         }
     }
 }
@@ -0,0 +1,173 @@
+use cassandra_protocol::frame::message_result::{
+    BodyResResultRows, ColSpec, ColType, ColTypeOption, ResResultBody, RowsMetadata,
+    RowsMetadataFlags, TableSpec,
+};
+use cassandra_protocol::frame::{Direction, Envelope, Flags, Opcode, Serialize, Version};
+use cassandra_protocol::types::CBytes;
+use fxhash::FxHashMap;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+use tracing_core::span::Current;
+
+/// Creates a V4 rows result envelope with given columns and raw row values.
+pub(crate) fn create_rows_envelope(
+    columns: &[(&str, ColType)],
+    rows: Vec<Vec<CBytes>>,
+) -> Envelope {
+    let body = ResResultBody::Rows(BodyResResultRows {
+        metadata: RowsMetadata {
+            flags: RowsMetadataFlags::GLOBAL_TABLE_SPACE,
+            columns_count: columns.len() as i32,
+            paging_state: None,
+            new_metadata_id: None,
+            global_table_spec: Some(TableSpec {
+                ks_name: "system".into(),
+                table_name: "table".into(),
+            }),
+            col_specs: columns
+                .iter()
+                .map(|(name, id)| ColSpec {
+                    table_spec: None,
+                    name: name.to_string(),
+                    col_type: ColTypeOption {
+                        id: *id,
+                        value: None,
+                    },
+                })
+                .collect(),
+        },
+        rows_count: rows.len() as i32,
+        rows_content: rows,
+        protocol_version: Version::V4,
+    });
+
+    Envelope::new(
+        Version::V4,
+        Direction::Response,
+        Flags::empty(),
+        Opcode::Result,
+        0,
+        body.serialize_to_vec(Version::V4),
+        None,
+        vec![],
+    )
+}
+
+/// A span captured by [`SpanRecorder`].
+#[derive(Clone, Debug)]
+pub(crate) struct RecordedSpan {
+    pub(crate) id: u64,
+    pub(crate) name: &'static str,
+    pub(crate) parent: Option<u64>,
+    pub(crate) fields: FxHashMap<&'static str, String>,
+}
+
+#[derive(Default)]
+struct State {
+    spans: Vec<(RecordedSpan, &'static Metadata<'static>)>,
+    entered: Vec<u64>,
+}
+
+/// A subscriber capturing all spans along with their parents and recorded fields. Entered spans
+/// are tracked globally, not per thread, so it's meant for single-threaded runtimes.
+#[derive(Clone, Default)]
+pub(crate) struct SpanRecorder {
+    state: Arc<Mutex<State>>,
+}
+
+impl SpanRecorder {
+    pub(crate) fn spans(&self) -> Vec<RecordedSpan> {
+        self.state
+            .lock()
+            .unwrap()
+            .spans
+            .iter()
+            .map(|(span, _)| span.clone())
+            .collect()
+    }
+
+    pub(crate) fn find_spans(&self, name: &str) -> Vec<RecordedSpan> {
+        self.spans()
+            .into_iter()
+            .filter(|span| span.name == name)
+            .collect()
+    }
+}
+
+impl Subscriber for SpanRecorder {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // other tests might run without a subscriber, so don't let callsites cache interest
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let mut state = self.state.lock().unwrap();
+
+        let parent = if attributes.is_contextual() {
+            state.entered.last().copied()
+        } else {
+            attributes.parent().map(Id::into_u64)
+        };
+
+        let mut span = RecordedSpan {
+            id: state.spans.len() as u64 + 1,
+            name: attributes.metadata().name(),
+            parent,
+            fields: Default::default(),
+        };
+        attributes.record(&mut FieldVisitor(&mut span.fields));
+
+        let id = Id::from_u64(span.id);
+        state.spans.push((span, attributes.metadata()));
+        id
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        let mut state = self.state.lock().unwrap();
+        let (span, _) = &mut state.spans[span.into_u64() as usize - 1];
+        values.record(&mut FieldVisitor(&mut span.fields));
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, span: &Id) {
+        self.state.lock().unwrap().entered.push(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(position) = state.entered.iter().rposition(|id| *id == span.into_u64()) {
+            state.entered.remove(position);
+        }
+    }
+
+    fn current_span(&self) -> Current {
+        let state = self.state.lock().unwrap();
+        match state.entered.last() {
+            Some(id) => Current::new(Id::from_u64(*id), state.spans[*id as usize - 1].1),
+            None => Current::none(),
+        }
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut FxHashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
@@ -0,0 +1,111 @@
+//! Client-side timestamps for statements.
+//!
+//! By default, the coordinator node assigns a write timestamp to each statement, so the order of
+//! writes depends on coordinator clocks. A timestamp generator lets the driver assign timestamps
+//! instead, which gives consistent ordering of writes sent from a single client, regardless of
+//! the coordinator handling them.
+//!
+//! Generated timestamps are only used for statements which don't have an explicit timestamp set,
+//! and can be disabled for individual statements via
+//! [`StatementParams::skip_timestamp_generator`](crate::statement::StatementParams::skip_timestamp_generator).
+
+use cassandra_protocol::types::CLong;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Generator of client-side statement timestamps, in microseconds since UNIX epoch.
+pub trait TimestampGenerator {
+    /// Returns the timestamp for the next statement.
+    fn next_timestamp(&self) -> CLong;
+}
+
+/// A generator returning strictly increasing timestamps based on system clock. If the clock goes
+/// backwards or multiple timestamps are requested within the same microsecond, the last timestamp
+/// is incremented by one microsecond instead, until the clock catches up.
+#[derive(Debug, Default)]
+pub struct MonotonicTimestampGenerator {
+    last_timestamp: AtomicI64,
+}
+
+impl MonotonicTimestampGenerator {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    fn next_timestamp_at(&self, now: CLong) -> CLong {
+        let mut last_timestamp = self.last_timestamp.load(Ordering::Relaxed);
+        loop {
+            let next_timestamp = now.max(last_timestamp + 1);
+            match self.last_timestamp.compare_exchange_weak(
+                last_timestamp,
+                next_timestamp,
+                Ordering::Relaxed,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return next_timestamp,
+                Err(current) => last_timestamp = current,
+            }
+        }
+    }
+}
+
+impl TimestampGenerator for MonotonicTimestampGenerator {
+    fn next_timestamp(&self) -> CLong {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_micros() as CLong)
+            .unwrap_or_default();
+
+        self.next_timestamp_at(now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::timestamp_generator::{MonotonicTimestampGenerator, TimestampGenerator};
+
+    #[test]
+    fn should_follow_clock() {
+        let generator = MonotonicTimestampGenerator::new();
+        assert_eq!(generator.next_timestamp_at(10), 10);
+        assert_eq!(generator.next_timestamp_at(20), 20);
+    }
+
+    #[test]
+    fn should_increase_when_clock_goes_backwards() {
+        let generator = MonotonicTimestampGenerator::new();
+        assert_eq!(generator.next_timestamp_at(10), 10);
+        assert_eq!(generator.next_timestamp_at(10), 11);
+        assert_eq!(generator.next_timestamp_at(5), 12);
+        assert_eq!(generator.next_timestamp_at(20), 20);
+    }
+
+    #[test]
+    fn should_generate_unique_timestamps_concurrently() {
+        let generator = Arc::new(MonotonicTimestampGenerator::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                std::thread::spawn(move || {
+                    (0..1000)
+                        .map(|_| generator.next_timestamp())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut timestamps: Vec<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        let count = timestamps.len();
+        timestamps.sort_unstable();
+        timestamps.dedup();
+
+        assert_eq!(timestamps.len(), count);
+    }
+}
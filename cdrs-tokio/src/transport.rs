@@ -17,17 +17,20 @@ use cassandra_protocol::frame::message_result::ResultKind;
 use cassandra_protocol::frame::{Envelope, StreamId, MAX_FRAME_SIZE};
 use cassandra_protocol::frame::{FromBytes, Opcode, EVENT_STREAM_ID};
 use cassandra_protocol::types::INT_LEN;
-use derive_more::Constructor;
+use derivative::Derivative;
 use futures::FutureExt;
 use fxhash::FxHashMap;
 use itertools::Itertools;
+use std::collections::hash_map::Entry;
 use std::io;
 use std::net::SocketAddr;
-use std::sync::atomic::{AtomicBool, AtomicI16, Ordering};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
 use tokio::io::{
-    split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadHalf,
-    WriteHalf,
+    split, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter, ReadBuf,
+    ReadHalf, WriteHalf,
 };
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot};
@@ -44,10 +47,16 @@ use mockall::*;
 use crate::cluster::KeyspaceHolder;
 use crate::envelope_parser::{convert_envelope_into_result, parse_envelope};
 use crate::future::BoxFuture;
+use crate::metrics::MetricsRecorder;
 use crate::Error;
 use crate::Result;
 
-const INITIAL_STREAM_ID: i16 = 1;
+const INITIAL_STREAM_ID: StreamId = 1;
+const MAX_STREAM_ID: StreamId = StreamId::MAX;
+
+/// Maximum number of requests which can be in flight on a single connection at the same time, as
+/// limited by the number of available stream ids.
+pub const MAX_IN_FLIGHT_REQUESTS: usize = (MAX_STREAM_ID - INITIAL_STREAM_ID + 1) as usize;
 
 /// General CDRS transport trait.
 pub trait CdrsTransport: Send + Sync {
@@ -62,6 +71,12 @@ pub trait CdrsTransport: Send + Sync {
     /// Checks if the connection is broken (e.g. after read or write errors).
     fn is_broken(&self) -> bool;
 
+    /// Checks if the connection has reached its limit of in-flight requests, and new requests
+    /// would be rejected with [`Error::ConnectionBusy`].
+    fn is_busy(&self) -> bool {
+        false
+    }
+
     /// Returns associated node address.
     fn address(&self) -> SocketAddr;
 }
@@ -101,6 +116,8 @@ impl TransportTcp {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
         tcp_nodelay: bool,
     ) -> io::Result<TransportTcp> {
         TcpStream::connect(addr).await.and_then(move |socket| {
@@ -115,6 +132,8 @@ impl TransportTcp {
                 frame_encoder,
                 frame_decoder,
                 buffer_size,
+                max_in_flight_requests,
+                metrics_recorder,
             )
         })
     }
@@ -130,6 +149,8 @@ impl TransportTcp {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     ) -> io::Result<TransportTcp> {
         let (read_half, write_half) = split(stream);
         Ok(TransportTcp {
@@ -139,6 +160,8 @@ impl TransportTcp {
                 frame_encoder,
                 frame_decoder,
                 buffer_size,
+                max_in_flight_requests,
+                metrics_recorder,
                 read_half,
                 write_half,
                 event_handler,
@@ -165,6 +188,11 @@ impl CdrsTransport for TransportTcp {
         self.inner.is_broken()
     }
 
+    #[inline]
+    fn is_busy(&self) -> bool {
+        self.inner.is_busy()
+    }
+
     #[inline]
     fn address(&self) -> SocketAddr {
         self.inner.addr()
@@ -191,6 +219,8 @@ impl TransportRustls {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
         tcp_nodelay: bool,
     ) -> io::Result<Self> {
         let stream = TcpStream::connect(addr).await?;
@@ -208,6 +238,8 @@ impl TransportRustls {
             frame_encoder,
             frame_decoder,
             buffer_size,
+            max_in_flight_requests,
+            metrics_recorder,
         )
         .await
     }
@@ -225,6 +257,8 @@ impl TransportRustls {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
     ) -> io::Result<Self> {
         let connector = RustlsConnector::from(config.clone());
         let stream = connector.connect(dns_name, stream).await?;
@@ -237,6 +271,8 @@ impl TransportRustls {
                 frame_encoder,
                 frame_decoder,
                 buffer_size,
+                max_in_flight_requests,
+                metrics_recorder,
                 read_half,
                 write_half,
                 event_handler,
@@ -264,18 +300,26 @@ impl CdrsTransport for TransportRustls {
         self.inner.is_broken()
     }
 
+    #[inline]
+    fn is_busy(&self) -> bool {
+        self.inner.is_busy()
+    }
+
     #[inline]
     fn address(&self) -> SocketAddr {
         self.inner.addr()
     }
 }
 
-#[derive(Debug)]
+#[derive(Derivative)]
+#[derivative(Debug)]
 struct AsyncTransport {
     addr: SocketAddr,
     compression: Compression,
     write_sender: mpsc::Sender<Request>,
     is_broken: Arc<AtomicBool>,
+    #[derivative(Debug = "ignore")]
+    response_handler_map: Arc<ResponseHandlerMap>,
     processing_handle: JoinHandle<()>,
 }
 
@@ -293,6 +337,8 @@ impl AsyncTransport {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
         buffer_size: usize,
+        max_in_flight_requests: usize,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
         read_half: ReadHalf<T>,
         write_half: WriteHalf<T>,
         event_handler: Option<mpsc::Sender<Envelope>>,
@@ -301,13 +347,15 @@ impl AsyncTransport {
     ) -> Self {
         let (write_sender, write_receiver) = mpsc::channel(buffer_size);
         let is_broken = Arc::new(AtomicBool::new(false));
+        let response_handler_map = Arc::new(ResponseHandlerMap::new(max_in_flight_requests));
 
         let processing_handle = tokio::spawn(Self::start_processing(
             write_receiver,
+            response_handler_map.clone(),
             event_handler,
             error_handler,
-            read_half,
-            write_half,
+            MeteredStream::new(read_half, addr, metrics_recorder.clone()),
+            MeteredStream::new(write_half, addr, metrics_recorder),
             keyspace_holder,
             is_broken.clone(),
             compression,
@@ -321,6 +369,7 @@ impl AsyncTransport {
             compression,
             write_sender,
             is_broken,
+            response_handler_map,
             processing_handle,
         }
     }
@@ -330,6 +379,11 @@ impl AsyncTransport {
         self.is_broken.load(Ordering::Relaxed)
     }
 
+    #[inline]
+    fn is_busy(&self) -> bool {
+        self.response_handler_map.is_full()
+    }
+
     #[inline]
     fn addr(&self) -> SocketAddr {
         self.addr
@@ -338,8 +392,6 @@ impl AsyncTransport {
     async fn write_envelope(&self, envelope: &Envelope, handshake: bool) -> Result<Envelope> {
         let (sender, receiver) = oneshot::channel();
 
-        // leave stream id empty for now and generate it later
-
         // handshake messages are never compressed
         let data = if handshake {
             envelope.encode_with(Compression::None)?
@@ -347,23 +399,39 @@ impl AsyncTransport {
             envelope.encode_with(self.compression)?
         };
 
+        let stream_id = self
+            .response_handler_map
+            .add_handler(sender)
+            .ok_or(Error::ConnectionBusy(self.addr))?;
+
+        // recorded in the attempt span, if present
+        Span::current().record("db.cassandra.stream_id", stream_id);
+
+        // if we stop waiting for the response before it arrives (e.g. due to a timeout), the
+        // stream id needs to be released
+        let mut pending_response = PendingResponse::new(&self.response_handler_map, stream_id);
+
         self.write_sender
-            .send(Request::new(data, sender, handshake))
+            .send(Request::new(data, handshake, stream_id))
             .await
             .map_err(|_| Error::General("Connection closed when writing data!".into()))?;
 
-        receiver
+        let response = receiver
             .await
-            .map_err(|_| Error::General("Connection closed while waiting for response!".into()))?
+            .map_err(|_| Error::General("Connection closed while waiting for response!".into()))?;
+
+        pending_response.complete();
+        response
     }
 
     #[allow(clippy::too_many_arguments)]
     async fn start_processing<T: AsyncRead + AsyncWrite>(
         write_receiver: mpsc::Receiver<Request>,
+        response_handler_map: Arc<ResponseHandlerMap>,
         event_handler: Option<mpsc::Sender<Envelope>>,
         error_handler: Option<mpsc::Sender<Error>>,
-        read_half: ReadHalf<T>,
-        write_half: WriteHalf<T>,
+        read_half: MeteredStream<ReadHalf<T>>,
+        write_half: MeteredStream<WriteHalf<T>>,
         keyspace_holder: Arc<KeyspaceHolder>,
         is_broken: Arc<AtomicBool>,
         compression: Compression,
@@ -371,8 +439,6 @@ impl AsyncTransport {
         frame_encoder: Box<dyn FrameEncoder + Send + Sync>,
         frame_decoder: Box<dyn FrameDecoder + Send + Sync>,
     ) {
-        let response_handler_map = ResponseHandlerMap::new();
-
         let writer = Self::start_writing(
             write_receiver,
             BufWriter::new(write_half),
@@ -515,12 +581,9 @@ impl AsyncTransport {
             frame_stream_ids.clear();
 
             loop {
-                let stream_id = response_handler_map.next_stream_id();
+                let stream_id = request.stream_id;
                 frame_stream_ids.push(stream_id);
 
-                request.set_stream_id(stream_id);
-                response_handler_map.add_handler(stream_id, request.handler);
-
                 if request.handshake {
                     // handshake messages are not framed, so let's write them directly
                     if let Err(error) = write_half.write_all(&request.data).await {
@@ -646,77 +709,442 @@ impl AsyncTransport {
     }
 }
 
+// reports bytes transferred through the underlying stream half
+struct MeteredStream<S> {
+    inner: S,
+    addr: SocketAddr,
+    metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+}
+
+impl<S> MeteredStream<S> {
+    fn new(
+        inner: S,
+        addr: SocketAddr,
+        metrics_recorder: Arc<dyn MetricsRecorder + Send + Sync>,
+    ) -> Self {
+        MeteredStream {
+            inner,
+            addr,
+            metrics_recorder,
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for MeteredStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        let num_read = buf.filled().len() - filled;
+        if num_read > 0 {
+            self.metrics_recorder
+                .record_bytes_received(self.addr, num_read);
+        }
+
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for MeteredStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(num_written)) = result {
+            self.metrics_recorder
+                .record_bytes_sent(self.addr, num_written);
+        }
+
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
 type ResponseHandler = oneshot::Sender<Result<Envelope>>;
 
 struct ResponseHandlerMap {
-    stream_handlers: Mutex<FxHashMap<StreamId, ResponseHandler>>,
-    available_stream_id: AtomicI16,
+    stream_handlers: Mutex<StreamHandlers>,
+    max_in_flight_requests: usize,
+}
+
+struct StreamHandlers {
+    // every stream id present here is in use - released stream ids are kept without a handler
+    // until their response arrives, since the server doesn't know the request has been abandoned
+    handlers: FxHashMap<StreamId, Option<ResponseHandler>>,
+    next_stream_id: StreamId,
 }
 
 impl ResponseHandlerMap {
     #[inline]
-    pub fn new() -> Self {
+    pub fn new(max_in_flight_requests: usize) -> Self {
         ResponseHandlerMap {
-            stream_handlers: Default::default(),
-            available_stream_id: AtomicI16::new(INITIAL_STREAM_ID),
+            stream_handlers: Mutex::new(StreamHandlers {
+                handlers: Default::default(),
+                next_stream_id: INITIAL_STREAM_ID,
+            }),
+            max_in_flight_requests: max_in_flight_requests.clamp(1, MAX_IN_FLIGHT_REQUESTS),
+        }
+    }
+
+    /// Reserves a free stream id for given handler. Returns `None` if the in-flight request limit
+    /// has been reached.
+    pub fn add_handler(&self, handler: ResponseHandler) -> Option<StreamId> {
+        let mut stream_handlers = self.stream_handlers.lock().unwrap();
+        if stream_handlers.handlers.len() >= self.max_in_flight_requests {
+            return None;
+        }
+
+        // the limit guarantees there's at least one free id, so this loop will terminate
+        loop {
+            let stream_id = stream_handlers.next_stream_id;
+            stream_handlers.next_stream_id = if stream_id == MAX_STREAM_ID {
+                INITIAL_STREAM_ID
+            } else {
+                stream_id + 1
+            };
+
+            if let Entry::Vacant(entry) = stream_handlers.handlers.entry(stream_id) {
+                entry.insert(Some(handler));
+                return Some(stream_id);
+            }
         }
     }
 
     #[inline]
-    pub fn add_handler(&self, stream_id: StreamId, handler: ResponseHandler) {
-        self.stream_handlers
-            .lock()
-            .unwrap()
-            .insert(stream_id, handler);
+    pub fn is_full(&self) -> bool {
+        self.stream_handlers.lock().unwrap().handlers.len() >= self.max_in_flight_requests
     }
 
     pub fn send_response(&self, stream_id: StreamId, response: Result<Envelope>) -> Result<()> {
-        match self.stream_handlers.lock().unwrap().remove(&stream_id) {
-            Some(handler) => {
+        match self
+            .stream_handlers
+            .lock()
+            .unwrap()
+            .handlers
+            .remove(&stream_id)
+        {
+            Some(Some(handler)) => {
                 let _ = handler.send(response);
                 Ok(())
             }
+            Some(None) => {
+                debug!(stream_id, "Ignoring response for a released stream.");
+                Ok(())
+            }
             // unmatched stream - probably a bug somewhere
             None => Err(Error::General(format!("Unmatched stream id: {stream_id}"))),
         }
     }
 
-    pub fn signal_general_error(&self, error: &Error) {
-        for (_, handler) in self.stream_handlers.lock().unwrap().drain() {
-            let _ = handler.send(Err(error.clone()));
+    /// Drops the handler waiting for a response on given stream. A late response will be ignored.
+    pub fn release_stream_id(&self, stream_id: StreamId) {
+        if let Some(handler) = self
+            .stream_handlers
+            .lock()
+            .unwrap()
+            .handlers
+            .get_mut(&stream_id)
+        {
+            *handler = None;
         }
     }
 
-    pub fn next_stream_id(&self) -> StreamId {
-        loop {
-            let stream = self.available_stream_id.fetch_add(1, Ordering::Relaxed);
-            if stream < 0 {
-                match self.available_stream_id.compare_exchange(
-                    stream,
-                    INITIAL_STREAM_ID,
-                    Ordering::Relaxed,
-                    Ordering::Relaxed,
-                ) {
-                    Ok(_) => return INITIAL_STREAM_ID,
-                    Err(_) => continue,
-                }
-            }
-
-            return stream;
+    pub fn signal_general_error(&self, error: &Error) {
+        for handler in self
+            .stream_handlers
+            .lock()
+            .unwrap()
+            .handlers
+            .drain()
+            .filter_map(|(_, handler)| handler)
+        {
+            let _ = handler.send(Err(error.clone()));
         }
     }
 }
 
-#[derive(Constructor)]
 struct Request {
     data: Vec<u8>,
-    handler: ResponseHandler,
     handshake: bool,
+    stream_id: StreamId,
 }
 
 impl Request {
     #[inline]
-    fn set_stream_id(&mut self, stream_d: StreamId) {
-        self.data[2..4].copy_from_slice(&stream_d.to_be_bytes());
+    fn new(mut data: Vec<u8>, handshake: bool, stream_id: StreamId) -> Self {
+        data[2..4].copy_from_slice(&stream_id.to_be_bytes());
+        Request {
+            data,
+            handshake,
+            stream_id,
+        }
+    }
+}
+
+struct PendingResponse<'a> {
+    response_handler_map: &'a ResponseHandlerMap,
+    stream_id: StreamId,
+    is_complete: bool,
+}
+
+impl<'a> PendingResponse<'a> {
+    #[inline]
+    fn new(response_handler_map: &'a ResponseHandlerMap, stream_id: StreamId) -> Self {
+        PendingResponse {
+            response_handler_map,
+            stream_id,
+            is_complete: false,
+        }
+    }
+
+    #[inline]
+    fn complete(&mut self) {
+        self.is_complete = true;
+    }
+}
+
+impl Drop for PendingResponse<'_> {
+    fn drop(&mut self) {
+        if !self.is_complete {
+            self.response_handler_map.release_stream_id(self.stream_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cassandra_protocol::compression::Compression;
+    use cassandra_protocol::frame::frame_decoder::LegacyFrameDecoder;
+    use cassandra_protocol::frame::frame_encoder::LegacyFrameEncoder;
+    use cassandra_protocol::frame::{Envelope, Version};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+    use tokio::sync::{oneshot, watch};
+    use tokio::time::{sleep, timeout};
+    use tracing::{field, info_span, Instrument};
+
+    use crate::cluster::KeyspaceHolder;
+    use crate::metrics::{InMemoryMetricsRecorder, MetricsRecorder, NoopMetricsRecorder};
+    use crate::test_utils::SpanRecorder;
+    use crate::transport::{
+        CdrsTransport, ResponseHandlerMap, TransportTcp, INITIAL_STREAM_ID, MAX_IN_FLIGHT_REQUESTS,
+    };
+    use crate::Error;
+
+    #[test]
+    fn should_not_reuse_stream_ids_in_flight() {
+        let response_handler_map = ResponseHandlerMap::new(MAX_IN_FLIGHT_REQUESTS);
+
+        let mut receivers = Vec::with_capacity(MAX_IN_FLIGHT_REQUESTS);
+        for expected_stream_id in INITIAL_STREAM_ID..=i16::MAX {
+            let (sender, receiver) = oneshot::channel();
+            receivers.push(receiver);
+
+            assert_eq!(
+                response_handler_map.add_handler(sender),
+                Some(expected_stream_id)
+            );
+        }
+
+        assert!(response_handler_map.is_full());
+        assert!(response_handler_map
+            .add_handler(oneshot::channel().0)
+            .is_none());
+
+        // a released stream id stays reserved until the response arrives
+        response_handler_map.release_stream_id(5);
+        assert!(response_handler_map
+            .add_handler(oneshot::channel().0)
+            .is_none());
+
+        response_handler_map
+            .send_response(5, Err(Error::General("test".into())))
+            .unwrap();
+        assert_eq!(
+            response_handler_map.add_handler(oneshot::channel().0),
+            Some(5)
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_requests_over_in_flight_limit() {
+        let (client, mut server) = duplex(1024);
+        let (keyspace_sender, _) = watch::channel(None);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+        let transport = TransportTcp::with_stream(
+            client,
+            addr,
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            None,
+            Compression::None,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+            8,
+            1,
+            Arc::new(NoopMetricsRecorder),
+        )
+        .unwrap();
+
+        let request = Envelope::new_req_options(Version::V4);
+        assert!(timeout(
+            Duration::from_millis(10),
+            transport.write_envelope(&request, true)
+        )
+        .await
+        .is_err());
+
+        // the abandoned request is still in flight
+        assert!(transport.is_busy());
+        assert!(matches!(
+            transport.write_envelope(&request, true).await,
+            Err(Error::ConnectionBusy(busy_addr)) if busy_addr == addr
+        ));
+
+        let mut header = [0u8; 9];
+        server.read_exact(&mut header).await.unwrap();
+
+        let response = [0x84, 0, header[2], header[3], 0x02, 0, 0, 0, 0];
+        server.write_all(&response).await.unwrap();
+
+        sleep(Duration::from_millis(10)).await;
+        assert!(!transport.is_busy());
+    }
+
+    #[tokio::test]
+    async fn should_ignore_late_response_to_abandoned_request() {
+        let (client, mut server) = duplex(1024);
+        let (keyspace_sender, _) = watch::channel(None);
+        let transport = TransportTcp::with_stream(
+            client,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042),
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            None,
+            Compression::None,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+            8,
+            MAX_IN_FLIGHT_REQUESTS,
+            Arc::new(NoopMetricsRecorder),
+        )
+        .unwrap();
+
+        let request = Envelope::new_req_options(Version::V4);
+        assert!(timeout(
+            Duration::from_millis(10),
+            transport.write_envelope(&request, true)
+        )
+        .await
+        .is_err());
+
+        let mut header = [0u8; 9];
+        server.read_exact(&mut header).await.unwrap();
+
+        // respond with READY using the stream id of the abandoned request
+        let response = [0x84, 0, header[2], header[3], 0x02, 0, 0, 0, 0];
+        server.write_all(&response).await.unwrap();
+
+        sleep(Duration::from_millis(10)).await;
+        assert!(!transport.is_broken());
+    }
+
+    #[tokio::test]
+    async fn should_record_transferred_bytes() {
+        let (client, mut server) = duplex(1024);
+        let (keyspace_sender, _) = watch::channel(None);
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+        let metrics_recorder = Arc::new(InMemoryMetricsRecorder::new());
+        let transport = TransportTcp::with_stream(
+            client,
+            addr,
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            None,
+            Compression::None,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+            8,
+            MAX_IN_FLIGHT_REQUESTS,
+            metrics_recorder.clone(),
+        )
+        .unwrap();
+
+        let request = Envelope::new_req_options(Version::V4);
+        let (response, _) = tokio::join!(transport.write_envelope(&request, true), async {
+            let mut header = [0u8; 9];
+            server.read_exact(&mut header).await.unwrap();
+
+            let response = [0x84, 0, header[2], header[3], 0x02, 0, 0, 0, 0];
+            server.write_all(&response).await.unwrap();
+        });
+        response.unwrap();
+
+        let metrics = metrics_recorder.metrics().unwrap();
+        assert_eq!(metrics.nodes[&addr].bytes_sent, 9);
+        assert_eq!(metrics.nodes[&addr].bytes_received, 9);
+    }
+
+    #[tokio::test]
+    async fn should_record_stream_id_in_current_span() {
+        let span_recorder = SpanRecorder::default();
+        let _guard = tracing::subscriber::set_default(span_recorder.clone());
+
+        let (client, mut server) = duplex(1024);
+        let (keyspace_sender, _) = watch::channel(None);
+        let transport = TransportTcp::with_stream(
+            client,
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042),
+            Arc::new(KeyspaceHolder::new(keyspace_sender)),
+            None,
+            None,
+            Compression::None,
+            Box::<LegacyFrameEncoder>::default(),
+            Box::<LegacyFrameDecoder>::default(),
+            8,
+            MAX_IN_FLIGHT_REQUESTS,
+            Arc::new(NoopMetricsRecorder),
+        )
+        .unwrap();
+
+        let request = Envelope::new_req_options(Version::V4);
+        let attempt_span = info_span!("cassandra.attempt", db.cassandra.stream_id = field::Empty);
+        let (response, stream_id) = tokio::join!(
+            transport
+                .write_envelope(&request, true)
+                .instrument(attempt_span),
+            async {
+                let mut header = [0u8; 9];
+                server.read_exact(&mut header).await.unwrap();
+
+                let response = [0x84, 0, header[2], header[3], 0x02, 0, 0, 0, 0];
+                server.write_all(&response).await.unwrap();
+
+                i16::from_be_bytes([header[2], header[3]])
+            }
+        );
+        response.unwrap();
+
+        let attempt_spans = span_recorder.find_spans("cassandra.attempt");
+        assert_eq!(attempt_spans.len(), 1);
+        assert_eq!(
+            attempt_spans[0].fields["db.cassandra.stream_id"],
+            stream_id.to_string()
+        );
     }
 }